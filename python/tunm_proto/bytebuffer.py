@@ -62,8 +62,8 @@ class ByteBuffer(object):
             self.rpos = 0
             self.wpos = 0
 
-    def read(self, length=1):
-        return int.from_bytes(self._read(length=length), byteorder=self.endianness)
+    def read(self, length=1, signed=False):
+        return int.from_bytes(self._read(length=length), byteorder=self.endianness, signed=signed)
 
     def write(self, value, size=0, signed=False):
         from tunm_proto import TP_DATA_TYPE
@@ -94,43 +94,43 @@ class ByteBuffer(object):
         self.write(value, 2)
         
     def write_i16(self, value):
-        self.write(value, 2)
+        self.write(value, 2, True)
         
     def write_u32(self, value):
         self.write(value, 4)
         
     def write_i32(self, value):
-        self.write(value, 4)
+        self.write(value, 4, True)
         
     def write_u64(self, value):
         self.write(value, 8)
         
     def write_i64(self, value):
-        self.write(value, 8)
+        self.write(value, 8, True)
         
     def read_u8(self):
         return self.read(1)
         
     def read_i8(self):
-        return self.read(1)
+        return self.read(1, True)
         
     def read_u16(self):
         return self.read(2)
         
     def read_i16(self):
-        return self.read(2)
+        return self.read(2, True)
         
     def read_u32(self):
         return self.read(4)
         
     def read_i32(self):
-        return self.read(4)
+        return self.read(4, True)
         
     def read_u64(self):
         return self.read(8)
         
     def read_i64(self):
-        return self.read(8)
+        return self.read(8, True)
     
     def write_str(self, s: str):
         return self.write(s.encode("utf-8"))
@@ -154,7 +154,7 @@ class ByteBuffer(object):
             return len(self.str_arr) - 1
     
     def get_str(self, idx):
-        if idx < 0 or idx > len(self.str_arr):
+        if idx < 0 or idx >= len(self.str_arr):
             raise Exception("out of range")
         else:
             return self.str_arr[idx]
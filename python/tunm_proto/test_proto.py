@@ -53,7 +53,7 @@ def test_encode_float():
     buffer = ByteBuffer();
 
     TPPacker.encode_field(buffer, 12345.123, TP_DATA_TYPE.TYPE_FLOAT)
-    check_buffer_data(buffer, [11, 35, 95, 188, 0])
+    check_buffer_data(buffer, [11, 198, 252, 226, 11])
     
     number = TPPacker.decode_field(buffer)
     assert number == 12345.123, "Number Not Match"
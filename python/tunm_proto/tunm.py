@@ -29,9 +29,11 @@ class TPPacker:
     @staticmethod
     def get_type_by_ref(ref_type):
         t = type(ref_type)
-        if t == str:
+        if t == bool:
+            return TP_DATA_TYPE.TYPE_BOOL
+        elif t == str:
             return TP_DATA_TYPE.TYPE_STR
-        elif t == bytes:
+        elif t == bytes or t == bytearray:
             return TP_DATA_TYPE.TYPE_RAW
         elif t == dict:
             return TP_DATA_TYPE.TYPE_MAP
@@ -57,11 +59,10 @@ class TPPacker:
             shl_num += 7
             if (data & 0x80) == 0:
                 break
-        is_left = real % 2 == 1
-        if is_left:
-            return -floor(real / 2) - 1
+        if real & 1:
+            return -(real >> 1) - 1
         else:
-            return floor(real / 2)
+            return real >> 1
         
     @staticmethod        
     def decode_type(buffer: ByteBuffer):
@@ -103,8 +104,8 @@ class TPPacker:
         elif pattern == TP_DATA_TYPE.TYPE_RAW:
             length = TPPacker.decode_varint(buffer)
             if length == 0:
-                return ""
-            return buffer.read_bytes(length)
+                return b""
+            return bytes(buffer.read_bytes(length))
         else:
             raise Exception(f"Unknow decode str type {pattern}")
     
@@ -119,11 +120,11 @@ class TPPacker:
         elif pattern >= TP_DATA_TYPE.TYPE_U8 and pattern <= TP_DATA_TYPE.TYPE_I8:
             return TPPacker.decode_number(buffer, pattern)
         elif pattern >= TP_DATA_TYPE.TYPE_U16 and pattern <= TP_DATA_TYPE.TYPE_I64:
-            return TPPacker.decode_varint(buffer)
+            return TPPacker.decode_number(buffer, pattern)
         elif pattern == TP_DATA_TYPE.TYPE_FLOAT:
-            return TPPacker.decode_number(buffer, TP_DATA_TYPE.TYPE_I32) / 1000.0
+            return TPPacker.decode_varint(buffer) / 1000.0
         elif pattern == TP_DATA_TYPE.TYPE_DOUBLE:
-            return TPPacker.decode_number(buffer, TP_DATA_TYPE.TYPE_I64) / 1000000.0
+            return TPPacker.decode_varint(buffer) / 1000000.0
         elif pattern == TP_DATA_TYPE.TYPE_VARINT:
             return TPPacker.decode_varint(buffer) 
         elif pattern == TP_DATA_TYPE.TYPE_STR_IDX:
@@ -258,7 +259,7 @@ class TPPacker:
         if not pattern:
             pattern = TPPacker.get_type_by_ref(value)
         if pattern == TP_DATA_TYPE.TYPE_NIL:
-            return None
+            TPPacker.encode_type(buffer, pattern)
         elif pattern == TP_DATA_TYPE.TYPE_BOOL:
             TPPacker.encode_type(buffer, pattern)
             TPPacker.encode_bool(buffer, value)
@@ -269,16 +270,17 @@ class TPPacker:
             TPPacker.encode_type(buffer, TP_DATA_TYPE.TYPE_VARINT)
             TPPacker.encode_varint(buffer, value)
         elif pattern == TP_DATA_TYPE.TYPE_FLOAT:
+            # 与rust一致, 浮点数*1000后截断为整数再以varint写入
             TPPacker.encode_type(buffer, pattern)
-            TPPacker.encode_number(buffer, value, pattern)
+            TPPacker.encode_varint(buffer, int(value * 1000.0))
         elif pattern == TP_DATA_TYPE.TYPE_DOUBLE:
             TPPacker.encode_type(buffer, pattern)
-            TPPacker.encode_number(buffer, value, pattern)
+            TPPacker.encode_varint(buffer, int(value * 1000000.0))
         elif pattern == TP_DATA_TYPE.TYPE_STR:
             TPPacker.encode_str_idx(buffer, value)
         elif pattern == TP_DATA_TYPE.TYPE_RAW:
             TPPacker.encode_type(buffer, pattern)
-            TPPacker.encode_str_raw(buffer, value)
+            TPPacker.encode_str_raw(buffer, value, pattern)
         elif pattern == TP_DATA_TYPE.TYPE_ARR:
             TPPacker.encode_type(buffer, pattern)
             TPPacker.encode_arr(buffer, value)
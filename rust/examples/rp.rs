@@ -1,10 +1,13 @@
 extern crate tunm_proto as tunm;
 use tunm::{Value, Buffer};
+#[cfg(all(feature = "lz4", feature = "zstd"))]
+use tunm::{CompressConfig, Compression};
 use std::time::{SystemTime};
 
 mod test_data;
 use std::collections::{HashMap};
 
+#[allow(unused_variables, clippy::match_single_binding)]
 fn test_level4_json() {
     let mut now = SystemTime::now();
     let parsed = test_data::get_json();
@@ -12,7 +15,7 @@ fn test_level4_json() {
     now = SystemTime::now();
     // println!("ok!!! parsed= {:?}", parsed);
     let mut buffer = Buffer::new();
-    tunm::encode_proto(&mut buffer, &"cmd_level4_full".to_string(), vec![parsed]).unwrap();
+    tunm::encode_proto(&mut buffer, &"cmd_level4_full".to_string(), vec![parsed.clone()]).unwrap();
     println!("用tunm_proto压缩test_level4_json的长度 = {}k", buffer.data_len() / 1024);
 
     println!("压缩JSON耗时 = {:?}", now.elapsed());
@@ -25,9 +28,22 @@ fn test_level4_json() {
             // println!("value === {:?}", val);
         }
     }
-
+    #[cfg(all(feature = "lz4", feature = "zstd"))]
+    test_level4_compress(&parsed);
 }   
 
+/// the sizes with the `lz4` and `zstd` features
+#[cfg(all(feature = "lz4", feature = "zstd"))]
+fn test_level4_compress(parsed: &Value) {
+    for algorithm in [Compression::Lz4, Compression::Zstd] {
+        let mut packed = Buffer::new();
+        packed.set_compress(Some(CompressConfig::new(algorithm)));
+        tunm::encode_proto(&mut packed, &"cmd_level4_full".to_string(), vec![parsed.clone()]).unwrap();
+        println!("再用{:?}压缩后的长度 = {}k", algorithm, packed.data_len() / 1024);
+    }
+}
+
+#[allow(clippy::unnecessary_cast, clippy::match_single_binding)]
 fn main()
 {
     println!("welcome to tickdream rust protocol");
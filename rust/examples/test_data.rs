@@ -7,6 +7,7 @@ use std::fs::File;
 extern crate tunm_proto as tunm;
 use tunm::{Value};
 
+#[allow(unreachable_patterns, clippy::needless_return)]
 fn iter_json_to_value(v: serde_json::Value) -> Value {
     match v {
         serde_json::Value::Null => Value::Nil,
@@ -41,12 +42,14 @@ fn iter_json_to_value(v: serde_json::Value) -> Value {
     }
 }
 
+#[allow(clippy::needless_return)]
 pub fn get_json() -> Value {
     let f = File::open("./examples/level4-full.json").unwrap();
     let v: serde_json::Value = serde_json::from_reader(f).unwrap();
     return iter_json_to_value(v);
 }
 
+#[allow(dead_code)]
 fn main() {
     
 }
\ No newline at end of file
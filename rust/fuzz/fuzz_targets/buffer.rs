@@ -0,0 +1,85 @@
+#![no_main]
+
+use std::io::{Read, Write};
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use tunm_proto::Buffer;
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Write(Vec<u8>),
+    Read(u16),
+    ReadOffset(u16),
+    /// fill `get_read_array(len)` and commit `commit` bytes of it
+    Fill(u16, u16, u8),
+    Drain(u16),
+    DrainCollect(u16),
+    DrainAllCollect,
+    Extend(Vec<u8>),
+    FixBuffer,
+    Clear,
+}
+
+// the unread bytes of `buffer` are checked against a plain Vec after every op
+fuzz_target!(|ops: Vec<Op>| {
+    let mut buffer = Buffer::new();
+    let mut model: Vec<u8> = Vec::new();
+    for op in ops {
+        match op {
+            Op::Write(data) => {
+                assert_eq!(buffer.write(&data).unwrap(), data.len());
+                model.extend(&data);
+            }
+            Op::Read(len) => {
+                let mut out = vec![0; len as usize];
+                let read = buffer.read(&mut out).unwrap();
+                assert_eq!(read, (len as usize).min(model.len()));
+                assert_eq!(&out[..read], &model[..read]);
+                model.drain(..read);
+            }
+            Op::ReadOffset(len) => {
+                let len = (len as usize).min(model.len());
+                buffer.read_offset(len);
+                model.drain(..len);
+            }
+            Op::Fill(len, commit, byte) => {
+                let array = buffer.get_read_array(len as usize);
+                let commit = (commit as usize).min(array.len());
+                array[..commit].fill(byte);
+                buffer.write_offset(commit);
+                model.extend(std::iter::repeat_n(byte, commit));
+            }
+            Op::Drain(pos) | Op::DrainCollect(pos) => {
+                // drain counts from the start of the storage, already read bytes included
+                let unread = (pos as usize).saturating_sub(buffer.get_rpos()).min(model.len());
+                match op {
+                    Op::Drain(_) => buffer.drain(pos as usize),
+                    _ => {
+                        buffer.drain_collect(pos as usize);
+                    }
+                }
+                model.drain(..unread);
+            }
+            Op::DrainAllCollect => {
+                assert_eq!(buffer.drain_all_collect(), model);
+                model.clear();
+            }
+            Op::Extend(data) => {
+                let mut other = Buffer::new();
+                other.write_all(&data).unwrap();
+                buffer.extend(&other).unwrap();
+                model.extend(&data);
+            }
+            Op::FixBuffer => {
+                buffer.fix_buffer();
+            }
+            Op::Clear => {
+                buffer.clear();
+                model.clear();
+            }
+        }
+        assert_eq!(buffer.data_len(), model.len());
+        assert_eq!(buffer.get_write_data(), &model[..]);
+    }
+});
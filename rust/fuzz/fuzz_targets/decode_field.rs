@@ -0,0 +1,18 @@
+#![no_main]
+
+use std::io::Write;
+
+use libfuzzer_sys::fuzz_target;
+use tunm_proto::{decode_field, encode_field, Buffer};
+
+fuzz_target!(|data: &[u8]| {
+    let mut buffer = Buffer::new();
+    // a small string table so str_idx fields can resolve
+    buffer.add_str("tunm".to_string());
+    buffer.add_str(String::new());
+    buffer.write_all(data).unwrap();
+    if let Ok(value) = decode_field(&mut buffer) {
+        let mut out = Buffer::new();
+        encode_field(&mut out, &value).unwrap();
+    }
+});
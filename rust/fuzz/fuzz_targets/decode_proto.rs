@@ -0,0 +1,20 @@
+#![no_main]
+
+use std::io::Write;
+
+use libfuzzer_sys::fuzz_target;
+use tunm_proto::{decode_proto, encode_proto, Buffer};
+
+fuzz_target!(|data: &[u8]| {
+    let mut buffer = Buffer::new();
+    buffer.write_all(data).unwrap();
+    if let Ok((name, args)) = decode_proto(&mut buffer) {
+        // whatever decodes must encode and decode again
+        let mut out = Buffer::new();
+        encode_proto(&mut out, &name, args.clone()).unwrap();
+        let (read_name, read_args) = decode_proto(&mut out).unwrap();
+        assert_eq!(read_name, name);
+        assert_eq!(read_args.len(), args.len());
+        assert_eq!(out.data_len(), 0);
+    }
+});
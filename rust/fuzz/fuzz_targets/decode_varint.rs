@@ -0,0 +1,21 @@
+#![no_main]
+
+use std::io::Write;
+
+use libfuzzer_sys::fuzz_target;
+use tunm_proto::{decode_varint, encode_varint, Buffer, Value};
+
+fuzz_target!(|data: &[u8]| {
+    let mut buffer = Buffer::new();
+    buffer.write_all(data).unwrap();
+    if let Ok(value) = decode_varint(&mut buffer) {
+        let val: i64 = value.into();
+        let mut out = Buffer::new();
+        encode_varint(&mut out, &Value::Varint(val)).unwrap();
+        // a varint is at most ten bytes and reads back the same
+        assert!(out.data_len() <= 10);
+        let read: i64 = decode_varint(&mut out).unwrap().into();
+        assert_eq!(read, val);
+        assert_eq!(out.data_len(), 0);
+    }
+});
@@ -0,0 +1,38 @@
+'''
+fill corpus/<target> with the golden vectors from tests/golden/vectors.json
+
+    python3 seed_corpus.py && cargo +nightly fuzz run decode_proto
+'''
+import json
+import os
+
+ROOT = os.path.dirname(os.path.abspath(__file__))
+VECTORS = os.path.join(ROOT, "..", "tests", "golden", "vectors.json")
+
+# varint tag, float and double are varints as well
+VARINT_TYPES = (10, 11, 12)
+
+
+def write_seed(target, name, data):
+    path = os.path.join(ROOT, "corpus", target)
+    os.makedirs(path, exist_ok=True)
+    with open(os.path.join(path, "golden_" + name), "wb") as f:
+        f.write(data)
+
+
+def main():
+    with open(VECTORS) as f:
+        vectors = json.load(f)["vectors"]
+    for vector in vectors:
+        data = bytes.fromhex(vector["hex"])
+        if vector["kind"] == "proto":
+            write_seed("decode_proto", vector["name"], data)
+        else:
+            write_seed("decode_field", vector["name"], data)
+            if data and data[0] in VARINT_TYPES:
+                write_seed("decode_varint", vector["name"], data[1:])
+    write_seed("decode_varint", "endless", b"\x80" * 32)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,109 @@
+//! Length-delimited protos over tokio `AsyncRead` and `AsyncWrite`, with the
+//! `tokio` feature.
+//!
+//! Every proto is sent after its varint length, so a reader knows where it
+//! ends before it decodes anything. The protos themselves are written and
+//! read with the settings of the `Buffer` given, a frame of `Checksum` or
+//! `SealKey` goes inside the length.
+
+use std::cmp;
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::buffer::READ_SIZE;
+use crate::{decode_proto, decode_varint, encode_proto, encode_varint};
+use crate::{Buffer, ErrorKind, RpResult, Value};
+
+/// Read from `reader` until a whole proto is in `buffer`, then `decode_proto`
+/// it with the settings of `buffer`. The proto comes after its length, as
+/// `write_proto` sends it, and the data after it stays for the next call.
+///
+/// Cancellation safe: nothing is taken from `buffer` before the whole proto
+/// is there, so when the future is dropped, or reading fails, what was read
+/// so far stays in `buffer` and the next call goes on from it.
+pub async fn read_proto<R: AsyncRead + Unpin>(reader: &mut R, buffer: &mut Buffer) -> RpResult<(String, Vec<Value>)> {
+    let len = loop {
+        if let Some((head, len)) = peek_len(buffer)? {
+            if buffer.data_len() - head >= len {
+                buffer.set_rpos(buffer.get_rpos() + head);
+                break len;
+            }
+        }
+        fill(reader, buffer).await?;
+    };
+
+    let left = buffer.data_len() - len;
+    let result = decode_proto(buffer);
+    let rest = buffer.data_len();
+    // the next proto starts after the frame, whatever this one used of it
+    buffer.set_rpos(buffer.get_wpos() - left);
+    match result {
+        Err(err) if err.kind() == ErrorKind::NoLeftSpaceError => {
+            fail!((ErrorKind::ParseError, "proto longer than its frame"))
+        }
+        Ok(_) if rest < left => fail!((ErrorKind::ParseError, "proto longer than its frame")),
+        Ok(_) if rest > left => fail!((ErrorKind::ParseError, "data left in the frame")),
+        result => result,
+    }
+}
+
+/// `encode_proto` a new buffer and write it to `writer` after its length
+pub async fn write_proto<W: AsyncWrite + Unpin>(writer: &mut W, name: &str, infos: Vec<Value>) -> RpResult<()> {
+    write_proto_with(writer, &mut Buffer::new(), name, infos).await
+}
+
+/// `write_proto` with the settings and string table of `buffer`, data
+/// already in `buffer` stays there.
+pub async fn write_proto_with<W: AsyncWrite + Unpin>(writer: &mut W, buffer: &mut Buffer, name: &str, infos: Vec<Value>) -> RpResult<()> {
+    let len = buffer.data_len();
+    encode_proto(buffer, &name.to_string(), infos)?;
+    let mut head = Buffer::with_capacity(10);
+    encode_varint(&mut head, &Value::U64((buffer.data_len() - len) as u64))?;
+
+    let result = write_parts(writer, head.get_write_data(), &buffer.get_write_data()[len..]).await;
+    buffer.set_wpos(buffer.get_rpos() + len);
+    Ok(result?)
+}
+
+async fn write_parts<W: AsyncWrite + Unpin>(writer: &mut W, head: &[u8], proto: &[u8]) -> io::Result<()> {
+    writer.write_all(head).await?;
+    writer.write_all(proto).await?;
+    writer.flush().await
+}
+
+/// the size of the length before the proto and the length, `None` until
+/// all of the length is there, nothing is taken from `buffer`
+fn peek_len(buffer: &mut Buffer) -> RpResult<Option<(usize, usize)>> {
+    let rpos = buffer.get_rpos();
+    let len = decode_varint(buffer);
+    let head = buffer.get_rpos() - rpos;
+    buffer.set_rpos(rpos);
+    let len: i64 = match len {
+        Ok(len) => len.into(),
+        Err(err) if err.kind() == ErrorKind::NoLeftSpaceError => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let len = match usize::try_from(len) {
+        Ok(len) => len,
+        Err(_) => fail!((ErrorKind::ParseError, "negative length")),
+    };
+    if len.saturating_add(head) > buffer.get_config().max_size {
+        fail!((ErrorKind::BufferOverMaxError, "buffer over max size", format!("proto of {} bytes", len)));
+    }
+    Ok(Some((head, len)))
+}
+
+/// read once from `reader` into `buffer`, the end of `reader` is an error
+async fn fill<R: AsyncRead + Unpin>(reader: &mut R, buffer: &mut Buffer) -> RpResult<()> {
+    let want = cmp::min(READ_SIZE, buffer.room());
+    if want == 0 {
+        fail!((ErrorKind::BufferOverMaxError, "buffer over max size"));
+    }
+    let n = reader.read(&mut buffer.spare_capacity_mut(want)?[..want]).await?;
+    if n == 0 {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    buffer.commit(n)?;
+    Ok(())
+}
@@ -0,0 +1,122 @@
+//! Walks a proto like `decode_proto` does and records where every part sits.
+
+use std::io::Write;
+
+use tunm::decode::MAX_DECODE_DEPTH;
+use tunm::{Buffer, ErrorKind, RpResult, Value, TYPE_ARR, TYPE_MAP, TYPE_STR, TYPE_STR_IDX};
+
+/// one decoded part of the message, `kind` is the wire type or the proto header part
+pub struct Entry {
+    pub offset: usize,
+    pub end: usize,
+    pub depth: usize,
+    pub kind: &'static str,
+    pub text: String,
+}
+
+pub struct Inspect {
+    pub entries: Vec<Entry>,
+    /// the first error, entries stop at `offset`
+    pub error: Option<(usize, String)>,
+}
+
+struct Walker {
+    buffer: Buffer,
+    entries: Vec<Entry>,
+}
+
+impl Walker {
+    fn pos(&self) -> usize {
+        self.buffer.get_rpos()
+    }
+
+    fn push(&mut self, offset: usize, depth: usize, kind: &'static str, text: String) {
+        let end = self.pos();
+        self.entries.push(Entry { offset, end, depth, kind, text });
+    }
+
+    fn proto(&mut self) -> RpResult<()> {
+        let start = self.pos();
+        let name: String = tunm::decode_str_raw(&mut self.buffer, TYPE_STR)?.into();
+        self.push(start, 0, "name", format!("name {:?}", name));
+
+        let start = self.pos();
+        let count: i64 = tunm::decode_varint(&mut self.buffer)?.into();
+        self.push(start, 0, "strings", format!("string table, {} entries", count));
+        for idx in 0..count.max(0) {
+            let start = self.pos();
+            let value: String = tunm::decode_str_raw(&mut self.buffer, TYPE_STR)?.into();
+            if idx > u16::MAX as i64 {
+                return Err((ErrorKind::StringTableError, "string table full").into());
+            }
+            self.buffer.add_str(value.clone());
+            self.push(start, 1, "strings", format!("#{} {:?}", idx, value));
+        }
+
+        if self.buffer.get_write_data().first() != Some(&TYPE_ARR) {
+            return Err((ErrorKind::TypeNotMatchError, "proto is not array").into());
+        }
+        self.field(0, "args ".to_string())?;
+        if !self.buffer.is_empty() {
+            let detail = format!("{} bytes", self.buffer.data_len());
+            return Err((ErrorKind::ParseError, "trailing bytes after proto", detail).into());
+        }
+        Ok(())
+    }
+
+    fn field(&mut self, depth: usize, label: String) -> RpResult<()> {
+        let start = self.pos();
+        let tag = match self.buffer.get_write_data().first() {
+            Some(tag) => *tag,
+            None => return Err((ErrorKind::NoLeftSpaceError, "must left space to read ").into()),
+        };
+        let kind = tunm::get_name_by_type(tag);
+        match tag {
+            TYPE_ARR | TYPE_MAP => {
+                if depth >= MAX_DECODE_DEPTH {
+                    return Err((ErrorKind::ParseError, "nested too deep").into());
+                }
+                tunm::decode_type(&mut self.buffer)?;
+                let len: i64 = tunm::decode_varint(&mut self.buffer)?.into();
+                self.push(start, depth, kind, format!("{}{} len {}", label, kind, len));
+                for idx in 0..len.max(0) {
+                    if tag == TYPE_ARR {
+                        self.field(depth + 1, format!("[{}] ", idx))?;
+                    } else {
+                        self.field(depth + 1, format!("key {} ", idx))?;
+                        self.field(depth + 1, format!("value {} ", idx))?;
+                    }
+                }
+            }
+            TYPE_STR_IDX => {
+                tunm::decode_type(&mut self.buffer)?;
+                let idx: i64 = tunm::decode_varint(&mut self.buffer)?.into();
+                let value = match u16::try_from(idx) {
+                    Ok(idx) => self.buffer.get_str(idx)?,
+                    Err(_) => return Err((ErrorKind::StringTableError, "unknown string index", idx.to_string()).into()),
+                };
+                self.push(start, depth, kind, format!("{}{} #{} {:?}", label, kind, idx, value));
+            }
+            _ => {
+                let value = tunm::decode_field(&mut self.buffer)?;
+                self.push(start, depth, kind, format!("{}{}", label, describe_leaf(&value)));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn describe_leaf(value: &Value) -> String {
+    match value {
+        Value::Raw(raw) => format!("raw {} bytes", raw.len()),
+        _ => format!("{:?}", value),
+    }
+}
+
+pub fn inspect(data: &[u8]) -> Inspect {
+    let mut buffer = Buffer::new();
+    buffer.write_all(data).unwrap();
+    let mut walker = Walker { buffer, entries: Vec::new() };
+    let error = walker.proto().err().map(|err| (walker.pos(), err.to_string()));
+    Inspect { entries: walker.entries, error }
+}
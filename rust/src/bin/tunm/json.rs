@@ -0,0 +1,191 @@
+//! `Value` to json and back, the tagged form is the one of `tests/golden/vectors.json`.
+
+use std::collections::HashMap;
+
+use serde_json::{json, Map, Value as Json};
+use tunm::Value;
+
+/// integers outside of this range are written as strings, javascript can't hold them
+const JSON_SAFE_INT: i64 = 1 << 53;
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn from_hex(hex: &str) -> Result<Vec<u8>, String> {
+    let hex: Vec<u8> = hex.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !hex.len().is_multiple_of(2) {
+        return Err("odd number of hex digits".to_string());
+    }
+    hex.chunks(2).map(|pair| {
+        let pair = std::str::from_utf8(pair).map_err(|_| "bad hex digit".to_string())?;
+        u8::from_str_radix(pair, 16).map_err(|_| format!("bad hex digit {:?}", pair))
+    }).collect()
+}
+
+fn int_json(val: i64) -> Json {
+    if (-JSON_SAFE_INT..=JSON_SAFE_INT).contains(&val) {
+        json!(val)
+    } else {
+        json!(val.to_string())
+    }
+}
+
+fn json_int<T: TryFrom<i128>>(json: &Json) -> Result<T, String> {
+    let val: i128 = match json {
+        Json::String(s) => s.parse().map_err(|_| format!("bad integer {}", json))?,
+        Json::Number(n) if n.is_u64() => n.as_u64().unwrap() as i128,
+        Json::Number(n) if n.is_i64() => n.as_i64().unwrap() as i128,
+        _ => return Err(format!("bad integer {}", json)),
+    };
+    T::try_from(val).map_err(|_| format!("integer {} out of range", json))
+}
+
+fn json_float(json: &Json) -> Result<f64, String> {
+    json.as_f64().ok_or_else(|| format!("bad number {}", json))
+}
+
+/// `{"type": value}` for every value, keeps the exact variant
+pub fn describe(value: &Value) -> Json {
+    match value {
+        Value::Nil => json!({ "nil": null }),
+        Value::Bool(v) => json!({ "bool": v }),
+        Value::U8(v) => json!({ "u8": v }),
+        Value::I8(v) => json!({ "i8": v }),
+        Value::U16(v) => json!({ "u16": v }),
+        Value::I16(v) => json!({ "i16": v }),
+        Value::U32(v) => json!({ "u32": v }),
+        Value::I32(v) => json!({ "i32": v }),
+        Value::U64(v) => {
+            if *v <= JSON_SAFE_INT as u64 { json!({ "u64": v }) } else { json!({ "u64": v.to_string() }) }
+        }
+        Value::I64(v) => json!({ "i64": int_json(*v) }),
+        Value::Varint(v) => json!({ "varint": int_json(*v) }),
+        Value::Float(v) => json!({ "float": *v as f64 }),
+        Value::Double(v) => json!({ "double": v }),
+        Value::Str(v) => json!({ "str": v }),
+        Value::Raw(v) => json!({ "raw": to_hex(v) }),
+        Value::Arr(v) => json!({ "arr": v.iter().map(describe).collect::<Vec<_>>() }),
+        Value::Map(v) => {
+            let mut pairs: Vec<Json> = v.iter().map(|(k, v)| json!([describe(k), describe(v)])).collect();
+            pairs.sort_by_key(|p| p.to_string());
+            json!({ "map": pairs })
+        }
+    }
+}
+
+pub fn parse(json: &Json) -> Result<Value, String> {
+    let (ty, val) = match json.as_object() {
+        Some(obj) if obj.len() == 1 => obj.iter().next().unwrap(),
+        _ => return Err(format!("expect {{\"type\": value}}, found {}", json)),
+    };
+    Ok(match &**ty {
+        "nil" => Value::Nil,
+        "bool" => Value::Bool(val.as_bool().ok_or_else(|| format!("bad bool {}", val))?),
+        "u8" => Value::U8(json_int(val)?),
+        "i8" => Value::I8(json_int(val)?),
+        "u16" => Value::U16(json_int(val)?),
+        "i16" => Value::I16(json_int(val)?),
+        "u32" => Value::U32(json_int(val)?),
+        "i32" => Value::I32(json_int(val)?),
+        "u64" => Value::U64(json_int(val)?),
+        "i64" => Value::I64(json_int(val)?),
+        "varint" => Value::Varint(json_int(val)?),
+        "float" => Value::Float(json_float(val)? as f32),
+        "double" => Value::Double(json_float(val)?),
+        "str" => Value::Str(val.as_str().ok_or_else(|| format!("bad str {}", val))?.to_string()),
+        "raw" => Value::Raw(from_hex(val.as_str().ok_or_else(|| format!("bad raw {}", val))?)?),
+        "arr" => Value::Arr(array(val)?.iter().map(parse).collect::<Result<_, _>>()?),
+        "map" => {
+            let mut map = HashMap::new();
+            for pair in array(val)? {
+                match pair.as_array().map(|p| &p[..]) {
+                    Some([k, v]) => map.insert(parse(k)?, parse(v)?),
+                    _ => return Err(format!("map entries must be [key, value], found {}", pair)),
+                };
+            }
+            Value::Map(map)
+        }
+        _ => return Err(format!("unknown type {:?}", ty)),
+    })
+}
+
+fn array(json: &Json) -> Result<&Vec<Json>, String> {
+    json.as_array().ok_or_else(|| format!("expect array, found {}", json))
+}
+
+/// plain json, integer widths are lost and raw data becomes a hex string, maps
+/// with only string keys become objects and the others `[[key, value], ...]`
+pub fn describe_plain(value: &Value) -> Json {
+    match value {
+        Value::Nil => Json::Null,
+        Value::Bool(v) => json!(v),
+        Value::U8(v) => json!(v),
+        Value::I8(v) => json!(v),
+        Value::U16(v) => json!(v),
+        Value::I16(v) => json!(v),
+        Value::U32(v) => json!(v),
+        Value::I32(v) => json!(v),
+        Value::U64(v) => json!(v),
+        Value::I64(v) | Value::Varint(v) => json!(v),
+        Value::Float(v) => json!(*v as f64),
+        Value::Double(v) => json!(v),
+        Value::Str(v) => json!(v),
+        Value::Raw(v) => json!(to_hex(v)),
+        Value::Arr(v) => Json::Array(v.iter().map(describe_plain).collect()),
+        Value::Map(v) => {
+            if v.keys().all(|k| matches!(k, Value::Str(_))) {
+                let mut pairs: Vec<(&Value, &Value)> = v.iter().collect();
+                pairs.sort_by_key(|(k, _)| match k {
+                    Value::Str(s) => s.clone(),
+                    _ => unreachable!(),
+                });
+                let obj: Map<String, Json> = pairs.into_iter().map(|(k, v)| match k {
+                    Value::Str(s) => (s.clone(), describe_plain(v)),
+                    _ => unreachable!(),
+                }).collect();
+                Json::Object(obj)
+            } else {
+                let mut pairs: Vec<Json> = v.iter().map(|(k, v)| json!([describe_plain(k), describe_plain(v)])).collect();
+                pairs.sort_by_key(|p| p.to_string());
+                Json::Array(pairs)
+            }
+        }
+    }
+}
+
+/// plain json to `Value`, integers become `Varint`, other numbers `Double`
+/// and objects maps with `Str` keys
+pub fn parse_plain(json: &Json) -> Value {
+    match json {
+        Json::Null => Value::Nil,
+        Json::Bool(v) => Value::Bool(*v),
+        Json::Number(n) => match n.as_i64() {
+            Some(v) => Value::Varint(v),
+            None => match n.as_u64() {
+                Some(v) => Value::U64(v),
+                None => Value::Double(n.as_f64().unwrap_or_default()),
+            },
+        },
+        Json::String(v) => Value::Str(v.clone()),
+        Json::Array(v) => Value::Arr(v.iter().map(parse_plain).collect()),
+        Json::Object(v) => Value::Map(v.iter().map(|(k, v)| (Value::Str(k.clone()), parse_plain(v))).collect()),
+    }
+}
+
+/// `{"proto": name, "args": [...]}`
+pub fn describe_proto(name: &str, args: &[Value], plain: bool) -> Json {
+    let describe = if plain { describe_plain } else { describe };
+    json!({ "proto": name, "args": args.iter().map(describe).collect::<Vec<_>>() })
+}
+
+pub fn parse_proto(json: &Json, plain: bool) -> Result<(String, Vec<Value>), String> {
+    let name = json["proto"].as_str().ok_or("missing \"proto\" name")?;
+    let args = json["args"].as_array().ok_or("missing \"args\" array")?;
+    let args = if plain {
+        args.iter().map(parse_plain).collect()
+    } else {
+        args.iter().map(parse).collect::<Result<_, _>>()?
+    };
+    Ok((name.to_string(), args))
+}
@@ -0,0 +1,225 @@
+extern crate serde_json;
+extern crate tunm_proto as tunm;
+
+mod inspect;
+mod json;
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+
+use tunm::Buffer;
+
+use crate::inspect::Inspect;
+
+/// bytes shown per dump line, longer fields continue on the next lines
+const DUMP_WIDTH: usize = 8;
+/// lines shown for one field before the rest is elided
+const DUMP_MAX_LINES: usize = 4;
+
+fn usage() -> ! {
+    eprintln!("usage: tunm <command> [--hex] [--plain] [file...]");
+    eprintln!("  dump       annotated hex with offsets, tags, string table and decoded values");
+    eprintln!("  to-json    decode a proto to json, tagged like {{\"u16\": 5}} or untagged with --plain");
+    eprintln!("  from-json  encode a json proto, written as binary or as hex with --hex");
+    eprintln!("  validate   check that every input decodes with no bytes left over");
+    eprintln!("  stats      bytes used by every type");
+    eprintln!("reads stdin when no file is given, --hex reads hex text instead of binary");
+    process::exit(2);
+}
+
+struct Options {
+    hex: bool,
+    plain: bool,
+    files: Vec<String>,
+}
+
+struct Input {
+    name: String,
+    data: Vec<u8>,
+}
+
+fn fail(msg: String) -> ! {
+    eprintln!("{}", msg);
+    process::exit(1);
+}
+
+fn read_inputs(opts: &Options) -> Vec<Input> {
+    let files = if opts.files.is_empty() { vec!["-".to_string()] } else { opts.files.clone() };
+    files.into_iter().map(|name| {
+        let data = if name == "-" {
+            let mut data = Vec::new();
+            io::stdin().read_to_end(&mut data).map(|_| data)
+        } else {
+            fs::read(&name)
+        };
+        let data = data.unwrap_or_else(|err| fail(format!("read {} failed: {}", name, err)));
+        Input { name, data }
+    }).collect()
+}
+
+/// the proto bytes of an input, hex text is decoded with `--hex`
+fn proto_bytes(opts: &Options, input: &Input) -> Vec<u8> {
+    if !opts.hex {
+        return input.data.clone();
+    }
+    let text = String::from_utf8_lossy(&input.data);
+    json::from_hex(&text).unwrap_or_else(|err| fail(format!("{}: {}", input.name, err)))
+}
+
+fn decode(bytes: &[u8]) -> tunm::RpResult<(String, Vec<tunm::Value>)> {
+    let mut buffer = Buffer::new();
+    buffer.write_all(bytes)?;
+    let proto = tunm::decode_proto(&mut buffer)?;
+    if !buffer.is_empty() {
+        let detail = format!("{} bytes", buffer.data_len());
+        return Err((tunm::ErrorKind::ParseError, "trailing bytes after proto", detail).into());
+    }
+    Ok(proto)
+}
+
+fn dump(bytes: &[u8], out: &mut String) {
+    use std::fmt::Write;
+    let Inspect { entries, error } = inspect::inspect(bytes);
+    for entry in &entries {
+        let data = &bytes[entry.offset..entry.end];
+        let chunks: Vec<&[u8]> = data.chunks(DUMP_WIDTH).collect();
+        for (line, chunk) in chunks.iter().enumerate().take(DUMP_MAX_LINES) {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+            let text = if line == 0 { format!("{}{}", "  ".repeat(entry.depth), entry.text) } else { String::new() };
+            let line = format!("{:06x}  {:<width$}  {}", entry.offset + line * DUMP_WIDTH, hex.join(" "),
+                               text, width = DUMP_WIDTH * 3 - 1);
+            let _ = writeln!(out, "{}", line.trim_end());
+        }
+        if chunks.len() > DUMP_MAX_LINES {
+            let left = data.len() - DUMP_MAX_LINES * DUMP_WIDTH;
+            let _ = writeln!(out, "{:06x}  .. {} more bytes", entry.offset + DUMP_MAX_LINES * DUMP_WIDTH, left);
+        }
+    }
+    match error {
+        Some((offset, err)) => {
+            let _ = writeln!(out, "{:06x}  error: {}", offset, err);
+        }
+        None => {
+            let _ = writeln!(out, "{} bytes", bytes.len());
+        }
+    }
+}
+
+#[derive(Default)]
+struct Stat {
+    count: usize,
+    bytes: usize,
+}
+
+fn stats(inputs: &[(String, Vec<u8>)], out: &mut String) -> bool {
+    use std::fmt::Write;
+    let mut by_kind: HashMap<&'static str, Stat> = HashMap::new();
+    let mut total = 0;
+    let mut ok = true;
+    for (name, bytes) in inputs {
+        let inspect = inspect::inspect(bytes);
+        if let Some((offset, err)) = inspect.error {
+            eprintln!("{}: error at {:#x}: {}", name, offset, err);
+            ok = false;
+            continue;
+        }
+        total += bytes.len();
+        for entry in inspect.entries {
+            let stat = by_kind.entry(entry.kind).or_default();
+            stat.count += 1;
+            stat.bytes += entry.end - entry.offset;
+        }
+    }
+    let mut rows: Vec<_> = by_kind.into_iter().collect();
+    rows.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes).then(a.0.cmp(b.0)));
+    let _ = writeln!(out, "{:<10} {:>8} {:>10} {:>7}", "type", "count", "bytes", "%");
+    for (kind, stat) in rows {
+        let percent = stat.bytes as f64 * 100.0 / total.max(1) as f64;
+        let _ = writeln!(out, "{:<10} {:>8} {:>10} {:>6.1}%", kind, stat.count, stat.bytes, percent);
+    }
+    let _ = writeln!(out, "{:<10} {:>8} {:>10}", "total", inputs.len(), total);
+    ok
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let command = args.next().unwrap_or_else(|| usage());
+    let mut opts = Options { hex: false, plain: false, files: Vec::new() };
+    for arg in args {
+        match &*arg {
+            "--hex" => opts.hex = true,
+            "--plain" => opts.plain = true,
+            "-h" | "--help" => usage(),
+            _ if arg.starts_with("--") => usage(),
+            _ => opts.files.push(arg),
+        }
+    }
+
+    let inputs = read_inputs(&opts);
+    let many = inputs.len() > 1;
+    let mut out = String::new();
+    let mut ok = true;
+    match &*command {
+        "dump" => {
+            for input in &inputs {
+                if many {
+                    out += &format!("== {}\n", input.name);
+                }
+                dump(&proto_bytes(&opts, input), &mut out);
+            }
+        }
+        "to-json" => {
+            for input in &inputs {
+                let (name, args) = decode(&proto_bytes(&opts, input))
+                    .unwrap_or_else(|err| fail(format!("{}: {}", input.name, err)));
+                let json = json::describe_proto(&name, &args, opts.plain);
+                out += &serde_json::to_string_pretty(&json).unwrap();
+                out.push('\n');
+            }
+        }
+        "from-json" => {
+            let mut bytes = Vec::new();
+            for input in &inputs {
+                let proto = serde_json::from_slice(&input.data).map_err(|err| err.to_string())
+                    .and_then(|json| json::parse_proto(&json, opts.plain))
+                    .unwrap_or_else(|err| fail(format!("{}: {}", input.name, err)));
+                let mut buffer = Buffer::new();
+                tunm::encode_proto(&mut buffer, &proto.0, proto.1)
+                    .unwrap_or_else(|err| fail(format!("{}: {}", input.name, err)));
+                if opts.hex {
+                    out += &json::to_hex(buffer.get_write_data());
+                    out.push('\n');
+                } else {
+                    bytes.extend_from_slice(buffer.get_write_data());
+                }
+            }
+            if !bytes.is_empty() {
+                io::stdout().write_all(&bytes).unwrap_or_else(|err| fail(format!("write failed: {}", err)));
+            }
+        }
+        "validate" => {
+            for input in &inputs {
+                let bytes = proto_bytes(&opts, input);
+                match inspect::inspect(&bytes).error {
+                    None => out += &format!("{}: ok, {} bytes\n", input.name, bytes.len()),
+                    Some((offset, err)) => {
+                        out += &format!("{}: error at {:#x}: {}\n", input.name, offset, err);
+                        ok = false;
+                    }
+                }
+            }
+        }
+        "stats" => {
+            let inputs: Vec<(String, Vec<u8>)> = inputs.iter().map(|input| (input.name.clone(), proto_bytes(&opts, input))).collect();
+            ok = stats(&inputs, &mut out);
+        }
+        _ => usage(),
+    }
+    print!("{}", out);
+    if !ok {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,63 @@
+extern crate tunm_proto as tunm;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use tunm::codegen::{self, Lang, ProtoSchema};
+
+fn usage() -> ! {
+    eprintln!("usage: tunm_codegen <schema> <gdscript|javascript|python|csharp|all> [output]");
+    eprintln!("  output is a file for one language or a directory for `all`, stdout if omitted");
+    process::exit(2);
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() < 2 || args.len() > 3 {
+        usage();
+    }
+
+    let text = fs::read_to_string(&args[0]).unwrap_or_else(|err| {
+        eprintln!("read {} failed: {}", args[0], err);
+        process::exit(1);
+    });
+    let schema = ProtoSchema::parse(&text).unwrap_or_else(|err| {
+        eprintln!("{}: {}", args[0], err);
+        process::exit(1);
+    });
+
+    let langs = if args[1] == "all" {
+        Lang::ALL.to_vec()
+    } else {
+        match Lang::from_name(&args[1]) {
+            Some(lang) => vec![lang],
+            None => usage(),
+        }
+    };
+
+    let output = match args.get(2) {
+        Some(output) => Path::new(output),
+        None if langs.len() == 1 => {
+            print!("{}", codegen::generate(&schema, langs[0]));
+            return;
+        }
+        None => usage(),
+    };
+
+    let stem = Path::new(&args[0]).file_stem().and_then(|s| s.to_str()).unwrap_or("protos");
+    for lang in langs {
+        let path = if output.is_dir() || args[1] == "all" {
+            fs::create_dir_all(output).ok();
+            output.join(format!("{}.{}", stem, lang.extension()))
+        } else {
+            output.to_path_buf()
+        };
+        if let Err(err) = fs::write(&path, codegen::generate(&schema, lang)) {
+            eprintln!("write {} failed: {}", path.display(), err);
+            process::exit(1);
+        }
+        println!("{} -> {}", lang.name(), path.display());
+    }
+}
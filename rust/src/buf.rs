@@ -0,0 +1,98 @@
+//! `bytes` crate support with the `bytes` feature.
+//!
+//! `Buffer` is a `Buf` over its unread data and a growing `BufMut`, so it
+//! can be handed to code written for `bytes` as it is. `BytesMut` and
+//! `Bytes` convert to and from `Buffer` without copying the data to a new
+//! allocation when they own it alone, though data that does not start the
+//! allocation is moved to the front. The string table and settings of a
+//! `Buffer` are not kept.
+
+use std::cmp;
+
+use bytes::buf::UninitSlice;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{decode_proto, encode_proto, Buffer, RpResult, Value};
+
+impl Buf for Buffer {
+    fn remaining(&self) -> usize {
+        self.data_len()
+    }
+
+    fn chunk(&self) -> &[u8] {
+        self.get_write_data()
+    }
+
+    fn advance(&mut self, cnt: usize) {
+        assert!(cnt <= self.data_len(), "advance {} past the {} bytes left", cnt, self.data_len());
+        self.set_rpos(self.get_rpos() + cnt);
+    }
+}
+
+unsafe impl BufMut for Buffer {
+    fn remaining_mut(&self) -> usize {
+        cmp::min(self.room(), isize::MAX as usize - self.get_wpos())
+    }
+
+    unsafe fn advance_mut(&mut self, cnt: usize) {
+        assert!(self.get_wpos() + cnt <= self.len(), "advance_mut {} past the room left", cnt);
+        self.set_wpos(self.get_wpos() + cnt);
+    }
+
+    fn chunk_mut(&mut self) -> &mut UninitSlice {
+        let room = self.room();
+        // fits in `max_size`, so it can not fail
+        let spare = self.spare_capacity_mut(cmp::min(64, room)).unwrap_or_default();
+        // never more than `remaining_mut`
+        let len = cmp::min(spare.len(), room);
+        UninitSlice::new(&mut spare[..len])
+    }
+}
+
+impl From<BytesMut> for Buffer {
+    fn from(bytes: BytesMut) -> Buffer {
+        Buffer::from_vec(bytes.into())
+    }
+}
+
+impl From<Bytes> for Buffer {
+    /// copies when `bytes` is shared
+    fn from(bytes: Bytes) -> Buffer {
+        Buffer::from_vec(bytes.into())
+    }
+}
+
+impl From<Buffer> for BytesMut {
+    fn from(buffer: Buffer) -> BytesMut {
+        let (val, rpos) = buffer.into_vec();
+        let mut bytes = BytesMut::from(Bytes::from(val));
+        bytes.advance(rpos);
+        bytes
+    }
+}
+
+impl From<Buffer> for Bytes {
+    fn from(buffer: Buffer) -> Bytes {
+        let (val, rpos) = buffer.into_vec();
+        let mut bytes = Bytes::from(val);
+        bytes.advance(rpos);
+        bytes
+    }
+}
+
+/// `encode_proto` with the settings and string table of `buffer`, the proto
+/// is then moved to `out`. Data already in `buffer` stays there.
+pub fn encode_proto_buf<B: BufMut>(buffer: &mut Buffer, out: &mut B, name: &str, infos: Vec<Value>) -> RpResult<()> {
+    let len = buffer.data_len();
+    encode_proto(buffer, &name.to_string(), infos)?;
+    out.put_slice(&buffer.get_write_data()[len..]);
+    buffer.set_wpos(buffer.get_rpos() + len);
+    Ok(())
+}
+
+/// Move all of `buf` to `buffer` and `decode_proto` from it, a partial proto
+/// waits in `buffer` for the rest to come with the next call.
+pub fn decode_proto_buf<B: Buf>(buffer: &mut Buffer, buf: &mut B) -> RpResult<(String, Vec<Value>)> {
+    buffer.put(buf);
+    decode_proto(buffer)
+}
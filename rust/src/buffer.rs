@@ -1,12 +1,71 @@
-use std::io::{Read, Write, Result};
+use std::io::{self, IoSlice, Read, Write, Result};
 use std::collections::HashMap;
-use std::ptr;
+use std::error::Error;
 use std::fmt;
 use std::cmp;
+use std::sync::Arc;
 
 use log::{warn, info, trace};
 
-use crate::{ErrorKind, RpResult};
+use crate::{Checksum, CompressConfig, ErrorKind, RpResult, SealKey, StrDict};
+#[cfg(feature = "sign")]
+use crate::SignKeys;
+
+/// a session string table is started over before it grows past this many strings
+pub const MAX_SESSION_STRS: usize = 65536;
+
+/// the most a read from a reader grows the buffer by at a time
+pub(crate) const READ_SIZE: usize = 4096;
+
+/// How a `Buffer` grows and shrinks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferConfig {
+    /// the bytes allocated up front
+    pub initial_capacity: usize,
+    /// a full buffer grows to this many times the size it needs, 1 grows
+    /// exactly as much as needed
+    pub growth_factor: usize,
+    /// a buffer larger than this shrinks once all of it is read
+    pub shrink_threshold: usize,
+    /// the size it then shrinks to
+    pub shrink_target: usize,
+    /// the most unread data the buffer holds, `write` fails beyond it and
+    /// `decode_proto` reports it as `ErrorKind::BufferOverMaxError`
+    pub max_size: usize,
+}
+
+impl BufferConfig {
+    pub fn new() -> BufferConfig {
+        BufferConfig {
+            initial_capacity: 2048,
+            growth_factor: 2,
+            shrink_threshold: 51200,
+            shrink_target: 51200,
+            max_size: usize::MAX,
+        }
+    }
+}
+
+impl Default for BufferConfig {
+    fn default() -> BufferConfig {
+        BufferConfig::new()
+    }
+}
+
+/// the error in the `io::Error` of a write past `BufferConfig::max_size`
+#[derive(Debug)]
+pub(crate) struct OverMaxSize {
+    size: usize,
+    max_size: usize,
+}
+
+impl fmt::Display for OverMaxSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} bytes over max size {}", self.size, self.max_size)
+    }
+}
+
+impl Error for OverMaxSize {}
 
 pub struct Buffer {
     val: Vec<u8>,
@@ -14,36 +73,218 @@ pub struct Buffer {
     wpos: usize,
     pub str_arr: Vec<String>,
     pub str_map: HashMap<String, u16>,
+    str_session: bool,
+    str_dict: Option<Arc<StrDict>>,
+    compress: Option<CompressConfig>,
+    checksum: Option<Checksum>,
+    seal: Option<SealKey>,
+    #[cfg(feature = "sign")]
+    sign: Option<Arc<SignKeys>>,
+    canonical: bool,
+    config: BufferConfig,
 }
 
 impl Buffer {
     pub fn new() -> Buffer {
-        let mut vec = Vec::with_capacity(2048);
-        vec.resize(2048, 0);
+        Buffer::with_config(BufferConfig::new())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Buffer {
+        Buffer::with_config(BufferConfig { initial_capacity: capacity, ..BufferConfig::new() })
+    }
+
+    pub fn with_config(config: BufferConfig) -> Buffer {
+        let mut buffer = Buffer::with_val(vec![0; config.initial_capacity]);
+        buffer.config = config;
+        buffer
+    }
+
+    fn with_val(val: Vec<u8>) -> Buffer {
         Buffer {
-            val: vec,
+            val,
             rpos: 0,
             wpos: 0,
             str_arr: Vec::new(),
             str_map: HashMap::new(),
+            str_session: false,
+            str_dict: None,
+            compress: None,
+            checksum: None,
+            seal: None,
+            #[cfg(feature = "sign")]
+            sign: None,
+            canonical: false,
+            config: BufferConfig::new(),
+        }
+    }
+
+    /// Grow, shrink and cap the buffer as `config` says from now on, data
+    /// already in it stays even when it is over `max_size`.
+    pub fn set_config(&mut self, config: BufferConfig) {
+        self.config = config;
+    }
+
+    pub fn get_config(&self) -> &BufferConfig {
+        &self.config
+    }
+
+    /// Keep the string table across messages, `encode_proto` then only sends
+    /// the strings the peer has not seen and `decode_proto` remembers them.
+    /// Both peers must turn it on for the buffers of one direction, a buffer
+    /// in session mode holds the table of one direction only.
+    pub fn set_str_session(&mut self, session: bool) {
+        self.str_session = session;
+        self.clear_str();
+    }
+
+    pub fn is_str_session(&self) -> bool {
+        self.str_session
+    }
+
+    /// Use the pre-shared `dict` for strings, both peers must set the same
+    /// version. The string table is cleared, its indices start after the
+    /// dictionary.
+    pub fn set_str_dict(&mut self, dict: Option<Arc<StrDict>>) {
+        self.str_dict = dict;
+        self.clear_str();
+    }
+
+    pub fn get_str_dict(&self) -> Option<&Arc<StrDict>> {
+        self.str_dict.as_ref()
+    }
+
+    /// Compress the bodies `encode_proto` writes and decompress the ones
+    /// `decode_proto` reads, see `CompressConfig`. Both peers must set it.
+    pub fn set_compress(&mut self, config: Option<CompressConfig>) {
+        self.compress = config;
+    }
+
+    pub fn get_compress(&self) -> Option<&CompressConfig> {
+        self.compress.as_ref()
+    }
+
+    /// Frame every proto with its length and a checksum, see `Checksum`.
+    /// Both peers must set the same one.
+    pub fn set_checksum(&mut self, checksum: Option<Checksum>) {
+        self.checksum = checksum;
+    }
+
+    pub fn get_checksum(&self) -> Option<Checksum> {
+        self.checksum
+    }
+
+    /// Seal every proto `encode_proto` writes or open every one `decode_proto`
+    /// reads with `key`, see `SealKey`. The peer's buffer of this direction
+    /// must have the same key. The tag replaces the checksum, which is not
+    /// used while a key is set.
+    pub fn set_seal(&mut self, key: Option<SealKey>) {
+        self.seal = key;
+    }
+
+    pub fn get_seal(&self) -> Option<&SealKey> {
+        self.seal.as_ref()
+    }
+
+    /// Sign every proto `encode_proto` writes with the current key and verify
+    /// every one `decode_proto` reads, see `SignKeys`. The tag replaces the
+    /// checksum, a seal key replaces the signature.
+    #[cfg(feature = "sign")]
+    pub fn set_sign_keys(&mut self, keys: Option<Arc<SignKeys>>) {
+        self.sign = keys;
+    }
+
+    #[cfg(feature = "sign")]
+    pub fn get_sign_keys(&self) -> Option<&Arc<SignKeys>> {
+        self.sign.as_ref()
+    }
+
+    /// whether sign keys are set, never without the `sign` feature
+    pub(crate) fn is_signed(&self) -> bool {
+        #[cfg(feature = "sign")]
+        return self.sign.is_some();
+        #[cfg(not(feature = "sign"))]
+        return false;
+    }
+
+    /// Encode the same values to the same bytes: map entries are sorted and
+    /// floats are rounded instead of truncated. Always on while signing.
+    pub fn set_canonical(&mut self, canonical: bool) {
+        self.canonical = canonical;
+    }
+
+    pub fn is_canonical(&self) -> bool {
+        self.canonical || self.is_signed()
+    }
+
+    /// hand the string table, session and dictionary over to `other`
+    pub(crate) fn swap_str_state(&mut self, other: &mut Buffer) {
+        std::mem::swap(&mut self.str_arr, &mut other.str_arr);
+        std::mem::swap(&mut self.str_map, &mut other.str_map);
+        std::mem::swap(&mut self.str_session, &mut other.str_session);
+        std::mem::swap(&mut self.str_dict, &mut other.str_dict);
+    }
+
+    /// where the string table starts in the `TYPE_STR_IDX` indices
+    pub fn str_base(&self) -> usize {
+        self.str_dict.as_ref().map_or(0, |dict| dict.len())
+    }
+
+    /// Forget all strings, in session mode the next `encode_proto` starts a
+    /// new table and tells the peer to drop its own.
+    pub fn clear_str(&mut self) {
+        self.str_arr.clear();
+        self.str_map.clear();
+    }
+
+    /// drop the strings added after the first `len`
+    pub(crate) fn truncate_str(&mut self, len: usize) {
+        if len >= self.str_arr.len() {
+            return;
+        }
+        for value in self.str_arr.drain(len..) {
+            self.str_map.remove(&value);
         }
     }
     
+    /// The index of `value`, added to the table when it is new. Panics when
+    /// the dictionary and the table already hold `u16::MAX + 1` strings,
+    /// `encode_field` writes such strings inline instead.
     pub fn add_str(&mut self, value: String) -> u16 {
-        if self.str_map.contains_key(&value) {
-            self.str_map[&value]
-        } else {
-            self.str_arr.push(value.clone());
-            self.str_map.insert(value, self.str_arr.len() as u16 - 1);
-            self.str_arr.len() as u16 - 1
+        match self.try_add_str(value) {
+            Some(idx) => idx,
+            None => panic!("string table is full"),
         }
     }
 
+    /// `add_str`, `None` when the index of `value` does not fit in a `u16`
+    pub(crate) fn try_add_str(&mut self, value: String) -> Option<u16> {
+        if let Some(idx) = self.str_dict.as_ref().and_then(|dict| dict.index_of(&value)) {
+            return u16::try_from(idx).ok();
+        }
+        let base = self.str_base();
+        if let Some(idx) = self.str_map.get(&value) {
+            return u16::try_from(base + *idx as usize).ok();
+        }
+        let idx = u16::try_from(base + self.str_arr.len()).ok()?;
+        self.str_map.insert(value.clone(), self.str_arr.len() as u16);
+        self.str_arr.push(value);
+        Some(idx)
+    }
+
     pub fn get_str(&self, idx: u16) -> RpResult<String> {
-        if idx as usize >= self.str_arr.len() {
+        let idx = idx as usize;
+        if let Some(dict) = &self.str_dict {
+            if let Some(value) = dict.get(idx) {
+                return Ok(value.to_string());
+            }
+        }
+        let base = self.str_base();
+        if idx - base >= self.str_arr.len() && (self.str_session || self.str_dict.is_some()) {
+            fail!((ErrorKind::StringTableError, "unknown string index", idx.to_string()));
+        } else if idx - base >= self.str_arr.len() {
             fail!((ErrorKind::BufferOverMaxError, "must left space to read "));
         } else {
-            Ok(self.str_arr[idx as usize].clone())
+            Ok(self.str_arr[idx - base].clone())
         }
     }
 
@@ -59,12 +300,13 @@ impl Buffer {
         self.val.len()
     }
 
+    /// true when there is no unread data left, see `data_len`
+    pub fn is_empty(&self) -> bool {
+        self.data_len() == 0
+    }
+
     pub fn data_len(&self) -> usize {
-        if self.wpos > self.rpos {
-            (self.wpos - self.rpos) as usize
-        } else {
-            0
-        }
+        self.wpos.saturating_sub(self.rpos)
     }
 
     pub fn set_rpos(&mut self, rpos: usize) {
@@ -83,19 +325,85 @@ impl Buffer {
         self.wpos
     }
     
+    /// room to read up to `max_bytes` into, less when `max_size` leaves less
     pub fn get_read_array(&mut self, max_bytes: usize) -> &mut [u8] {
-        if self.val.len() - self.wpos < max_bytes {
-            self.val.resize(self.wpos + max_bytes + 1, 0);
+        let max_bytes = cmp::min(max_bytes, self.room());
+        match self.spare_capacity_mut(max_bytes) {
+            Ok(spare) => &mut spare[..max_bytes],
+            Err(_) => &mut [],
         }
-        &mut self.val[self.wpos .. (self.wpos+max_bytes-1)]
     }
 
+    /// the unread bytes left, the same as `data_len`
+    pub fn remaining(&self) -> usize {
+        self.data_len()
+    }
+
+    /// `Read::read` without moving the data, the decoders go back to where
+    /// they started when a proto is cut
+    pub(crate) fn read_bytes(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let read = cmp::min(self.data_len(), buf.len());
+        buf[..read].copy_from_slice(&self.val[self.rpos..self.rpos + read]);
+        self.rpos += read;
+        Ok(read)
+    }
+
+    /// the next `n` unread bytes, without reading them
+    pub fn peek(&self, n: usize) -> RpResult<&[u8]> {
+        if n > self.data_len() {
+            fail!((ErrorKind::NoLeftSpaceError, "must left space to read ",
+                   format!("peek {}, left {}", n, self.data_len())));
+        }
+        Ok(&self.val[self.rpos..self.rpos + n])
+    }
+
+    /// the next unread byte, without reading it
+    pub fn peek_u8(&self) -> RpResult<u8> {
+        Ok(self.peek(1)?[0])
+    }
+
+    /// read the next `n` bytes, nothing is read when fewer are left
+    pub fn read_exact_slice(&mut self, n: usize) -> RpResult<&[u8]> {
+        self.peek(n)?;
+        self.rpos += n;
+        Ok(&self.val[self.rpos - n..self.rpos])
+    }
+
+    /// give back the last `n` bytes read, they must not have been dropped
+    /// by `fix_buffer`, `Read::read` or a write that moved the data yet
+    pub fn unread(&mut self, n: usize) -> RpResult<()> {
+        if n > self.rpos {
+            fail!((ErrorKind::NoLeftSpaceError, "unread before the data",
+                   format!("unread {}, read {}", n, self.rpos)));
+        }
+        self.rpos -= n;
+        Ok(())
+    }
+
+    /// At least `n` bytes of room after the data to write into, `commit`
+    /// then adds what was written. Fails when `max_size` leaves less.
+    pub fn spare_capacity_mut(&mut self, n: usize) -> RpResult<&mut [u8]> {
+        self.reserve(n)?;
+        Ok(&mut self.val[self.wpos..])
+    }
+
+    /// add `n` bytes written into `spare_capacity_mut` to the data
+    pub fn commit(&mut self, n: usize) -> RpResult<()> {
+        if n > self.val.len() - self.wpos {
+            fail!((ErrorKind::NoLeftSpaceError, "commit past the room",
+                   format!("commit {}, room {}", n, self.val.len() - self.wpos)));
+        }
+        self.wpos += n;
+        Ok(())
+    }
+
+    /// commit `pos` bytes written into `get_read_array`, never past the end of the data
     pub fn write_offset(&mut self, pos: usize) {
-        self.wpos = self.wpos + pos;
+        self.wpos = cmp::min(self.wpos.saturating_add(pos), self.val.len());
     }
     
     pub fn read_offset(&mut self, pos: usize) -> bool {
-        self.rpos = self.rpos + pos;
+        self.rpos = cmp::min(self.rpos.saturating_add(pos), self.wpos);
         self.fix_buffer();
         self.rpos == self.wpos
     }
@@ -128,18 +436,16 @@ impl Buffer {
             self.rpos = 0;
             self.wpos = 0;
 
-            if self.val.len() > 51200 {
-                self.val.resize(51200, 0);
-                warn!("TunmProto: buffer len big than 50k, resize to 50k");
+            if self.val.len() > self.config.shrink_threshold {
+                self.val.truncate(self.config.shrink_target);
+                self.val.shrink_to_fit();
+                trace!("TunmProto: read all size, shrink to {}", self.val.len());
             } else {
                 trace!("TunmProto: read all size, reset to zero");
             }
         } else if self.rpos > self.val.len() / 2 {
-            unsafe {
-                ptr::copy(&self.val[self.rpos], &mut self.val[0], self.wpos - self.rpos);
-                info!("TunmProto: fix buffer {} has half space so move position", self.rpos);
-                (self.rpos, self.wpos) = (0, self.wpos - self.rpos);
-            }
+            info!("TunmProto: fix buffer {} has half space so move position", self.rpos);
+            self.compact();
         }
         true
     }
@@ -149,9 +455,166 @@ impl Buffer {
         self.wpos = 0;
     }
 
+    /// move the unread data to the front
+    fn compact(&mut self) {
+        self.val.copy_within(self.rpos..self.wpos, 0);
+        (self.rpos, self.wpos) = (0, self.wpos - self.rpos);
+    }
+
+    /// make room for `additional` more bytes after the data
+    fn reserve(&mut self, additional: usize) -> Result<()> {
+        if matches!(self.wpos.checked_add(additional), Some(end) if end <= self.val.len()) {
+            return Ok(());
+        }
+        let size = match self.data_len().checked_add(additional) {
+            Some(size) if size <= self.config.max_size => size,
+            size => {
+                // a size that does not fit in usize is over any max size
+                let size = size.unwrap_or(usize::MAX);
+                return Err(io::Error::new(io::ErrorKind::OutOfMemory,
+                                          OverMaxSize { size, max_size: self.config.max_size }));
+            }
+        };
+        // the bytes already read make room before growing does
+        if self.rpos > 0 {
+            self.compact();
+        }
+        // the data starts at 0 now, so the end of the new data is `size`
+        if size > self.val.len() {
+            let len = cmp::max(size, size.saturating_mul(self.config.growth_factor));
+            let len = cmp::min(len, self.config.max_size);
+            if len > self.config.shrink_threshold {
+                warn!("TunmProto: resize buffer length to {:?}k", len / 1024);
+            }
+            self.val.resize(len, 0);
+        }
+        Ok(())
+    }
+
+    /// Clear the data and strings and turn every setting off, the buffer is
+    /// then like a new one but keeps its allocations.
+    pub fn reset(&mut self) {
+        self.clear();
+        self.clear_str();
+        self.str_session = false;
+        self.str_dict = None;
+        self.compress = None;
+        self.checksum = None;
+        self.seal = None;
+        #[cfg(feature = "sign")]
+        {
+            self.sign = None;
+        }
+        self.canonical = false;
+        self.config = BufferConfig::new();
+    }
+
     pub fn extend(&mut self, buffer: &Buffer) -> Result<usize> {
         self.write(buffer.get_write_data())
     }
+
+    /// Read from `r` straight into the buffer until `max` bytes, the end of
+    /// `r` or `WouldBlock`. Gives the bytes read, `0` at the end of `r`, and
+    /// `WouldBlock` only when nothing could be read.
+    pub fn read_from<R: Read>(&mut self, r: &mut R, max: usize) -> Result<usize> {
+        let mut read = 0;
+        while read < max {
+            // fill the room there is first, then grow by `READ_SIZE` at most
+            let chunk = match self.val.len() - self.wpos {
+                0 => READ_SIZE,
+                spare => spare,
+            };
+            let want = cmp::min(cmp::min(max - read, self.room()), chunk);
+            if want == 0 {
+                break;
+            }
+            self.reserve(want)?;
+            match r.read(&mut self.val[self.wpos..self.wpos + want]) {
+                Ok(0) => break,
+                Ok(n) => {
+                    self.wpos += n;
+                    read += n;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock && read > 0 => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(read)
+    }
+
+    /// Write the unread data to `w` until it is all gone or `w` gives
+    /// `WouldBlock`, what was written is read from the buffer so the rest
+    /// goes with the next call. `WouldBlock` is only given when nothing
+    /// could be written.
+    pub fn write_to<W: Write>(&mut self, w: &mut W) -> Result<usize> {
+        let mut written = 0;
+        while !self.is_empty() {
+            match w.write(self.get_write_data()) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    self.rpos += n;
+                    written += n;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock && written > 0 => break,
+                Err(e) => return Err(e),
+            }
+        }
+        self.fix_buffer();
+        Ok(written)
+    }
+
+    /// `write_to` for a header in this buffer and the `body` after it, both
+    /// go out with vectored writes instead of being copied together first.
+    pub fn write_vectored_to<W: Write>(&mut self, body: &mut Buffer, w: &mut W) -> Result<usize> {
+        let mut written = 0;
+        while !self.is_empty() || !body.is_empty() {
+            let bufs = [IoSlice::new(self.get_write_data()), IoSlice::new(body.get_write_data())];
+            match w.write_vectored(&bufs) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    let head = cmp::min(n, self.data_len());
+                    self.rpos += head;
+                    body.rpos += n - head;
+                    written += n;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock && written > 0 => break,
+                Err(e) => return Err(e),
+            }
+        }
+        self.fix_buffer();
+        body.fix_buffer();
+        Ok(written)
+    }
+
+    #[cfg(feature = "bytes")]
+    /// a buffer holding `val` as unread data, without copying it
+    pub(crate) fn from_vec(val: Vec<u8>) -> Buffer {
+        let mut buffer = Buffer::with_val(val);
+        buffer.wpos = buffer.val.len();
+        buffer
+    }
+
+    #[cfg(feature = "bytes")]
+    /// the data up to `wpos` and where the unread part starts
+    pub(crate) fn into_vec(self) -> (Vec<u8>, usize) {
+        let mut val = self.val;
+        val.truncate(self.wpos);
+        (val, self.rpos)
+    }
+
+    /// the most that can still be written
+    pub(crate) fn room(&self) -> usize {
+        self.config.max_size.saturating_sub(self.data_len())
+    }
+}
+
+impl Default for Buffer {
+    fn default() -> Buffer {
+        Buffer::new()
+    }
 }
 
 impl fmt::Debug for Buffer {
@@ -160,43 +623,30 @@ impl fmt::Debug for Buffer {
     }
 }
 
+/// Reading moves the unread data to the front once all of it is read or it
+/// starts past half of the buffer, so a buffer that is only read from and
+/// written to keeps its size.
 impl Read for Buffer {
     #[inline(always)]
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
-        let left = self.wpos - self.rpos;
-        if left == 0 || buf.len() == 0 {
-            return Ok(0);
-        }
-        let read = if left > buf.len() {
-            buf.len()
-        } else {
-            left
-        };
-        unsafe {
-            ptr::copy(&self.val[self.rpos], &mut buf[0], read);
+        let read = self.read_bytes(buf)?;
+        if self.rpos >= self.wpos {
+            (self.rpos, self.wpos) = (0, 0);
+        } else if self.rpos > self.val.len() / 2 {
+            self.compact();
         }
-        self.rpos += read;
         Ok(read)
     }
-
-    
 }
 
 impl Write for Buffer {
     #[inline(always)]
     fn write(&mut self, buf: &[u8]) -> Result<usize> {
-        if self.val.len() < self.wpos + buf.len() {
-            self.val.resize((self.wpos + buf.len()) * 2, 0);
-            if self.val.len() > 51200 {
-                warn!("TunmProto: resize buffer length to {:?}k", self.val.len() / 1024);
-            }
-        }
-        if buf.len() == 0 {
-            return Ok(buf.len());
-        }
-        unsafe {
-            ptr::copy(&buf[0], &mut self.val[self.wpos], buf.len());
+        if buf.is_empty() {
+            return Ok(0);
         }
+        self.reserve(buf.len())?;
+        self.val[self.wpos..self.wpos + buf.len()].copy_from_slice(buf);
         self.wpos += buf.len();
         Ok(buf.len())
     }
@@ -0,0 +1,89 @@
+//! Optional integrity check of every proto, CRC32C with the `crc32c` feature
+//! and xxHash64 with the `xxhash` feature.
+//!
+//! With a `Checksum` set on a `Buffer` (see `Buffer::set_checksum`) each
+//! proto `encode_proto` writes becomes a frame: the varint length of the
+//! proto, the proto, and the checksum of the length and proto as a little
+//! endian trailer of 4 (CRC32C) or 8 (xxHash64) bytes. `decode_proto` checks
+//! the trailer before it decodes anything and fails with
+//! `ErrorKind::ChecksumMismatch` on a corrupt frame. A frame that is not
+//! complete yet is left in the buffer, the whole frame is consumed otherwise.
+
+use std::io::Write;
+
+use crate::{decode_varint, encode_varint, Buffer, ErrorKind, RpResult, Value};
+
+/// `Crc32c` comes with the `crc32c` feature and `XxHash64` with the `xxhash`
+/// feature
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Checksum {
+    #[cfg(feature = "crc32c")]
+    Crc32c,
+    #[cfg(feature = "xxhash")]
+    XxHash64,
+}
+
+impl Checksum {
+    /// the size of the trailer
+    pub fn size(&self) -> usize {
+        match *self {
+            #[cfg(feature = "crc32c")]
+            Checksum::Crc32c => 4,
+            #[cfg(feature = "xxhash")]
+            Checksum::XxHash64 => 8,
+        }
+    }
+
+    #[cfg_attr(not(any(feature = "crc32c", feature = "xxhash")), allow(unused_variables))]
+    fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match *self {
+            #[cfg(feature = "crc32c")]
+            Checksum::Crc32c => crc32c::crc32c(data).to_le_bytes().to_vec(),
+            #[cfg(feature = "xxhash")]
+            Checksum::XxHash64 => xxhash_rust::xxh64::xxh64(data, 0).to_le_bytes().to_vec(),
+        }
+    }
+}
+
+/// write `proto` as a frame with its length and checksum
+pub(crate) fn write_frame(buffer: &mut Buffer, proto: &[u8], checksum: Checksum) -> RpResult<()> {
+    let start = buffer.data_len();
+    encode_varint(buffer, &Value::U64(proto.len() as u64))?;
+    buffer.write_all(proto)?;
+    let trailer = checksum.digest(&buffer.get_write_data()[start..]);
+    buffer.write_all(&trailer)?;
+    Ok(())
+}
+
+/// read a frame and return the proto in it once the checksum matches
+pub(crate) fn read_frame(buffer: &mut Buffer, checksum: Checksum) -> RpResult<Vec<u8>> {
+    let rpos = buffer.get_rpos();
+    let len: i64 = match decode_varint(buffer) {
+        Ok(len) => len.into(),
+        Err(err) => {
+            buffer.set_rpos(rpos);
+            return Err(err);
+        }
+    };
+    let len = match usize::try_from(len) {
+        Ok(len) => len,
+        Err(_) => fail!((ErrorKind::ParseError, "negative length")),
+    };
+    if buffer.data_len() < len.saturating_add(checksum.size()) {
+        // wait for the rest of the frame
+        buffer.set_rpos(rpos);
+        fail!((ErrorKind::NoLeftSpaceError, "must left space to read "));
+    }
+    let head_len = buffer.get_rpos() - rpos;
+    let expect = checksum.digest(&buffer.get_data()[rpos..rpos + head_len + len]);
+
+    let mut proto = vec![0; len];
+    try_read!(buffer.read_bytes(&mut proto), len);
+    let mut trailer = vec![0; checksum.size()];
+    try_read!(buffer.read_bytes(&mut trailer), trailer.len());
+    if trailer != expect {
+        fail!((ErrorKind::ChecksumMismatch, "checksum mismatch",
+               format!("{:?} of a {} bytes proto", checksum, len)));
+    }
+    Ok(proto)
+}
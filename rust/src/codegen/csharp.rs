@@ -0,0 +1,109 @@
+use std::fmt::{Result, Write};
+
+use super::{ProtoArg, ProtoSchema, GENERATED_HEADER};
+use crate::{TYPE_ARR, TYPE_BOOL, TYPE_DOUBLE, TYPE_FLOAT, TYPE_I16, TYPE_I32, TYPE_I64, TYPE_I8, TYPE_MAP, TYPE_NIL,
+            TYPE_RAW, TYPE_STR, TYPE_U16, TYPE_U32, TYPE_U64, TYPE_U8, TYPE_VARINT};
+
+fn type_name(pattern: u8) -> &'static str {
+    match pattern {
+        TYPE_BOOL => "bool",
+        TYPE_U8 => "byte",
+        TYPE_I8 => "sbyte",
+        TYPE_U16 => "ushort",
+        TYPE_I16 => "short",
+        TYPE_U32 => "uint",
+        TYPE_I32 => "int",
+        TYPE_U64 => "ulong",
+        TYPE_I64 | TYPE_VARINT => "long",
+        TYPE_FLOAT => "float",
+        TYPE_DOUBLE => "double",
+        TYPE_STR => "string",
+        TYPE_RAW => "byte[]",
+        TYPE_ARR => "ArrayList",
+        TYPE_MAP => "Dictionary<Object, Object>",
+        _ => "object",
+    }
+}
+
+/// `TunmValues.get_type_by_name` does not know `SByte`, send i8 as a varint
+fn arg_value(arg: &ProtoArg) -> String {
+    match arg.pattern {
+        TYPE_I8 => format!("(long){}", arg.name),
+        _ => arg.name.clone(),
+    }
+}
+
+/// decoded numbers come back as `long` or the fixed width type, convert them to the declared one
+fn arg_read(pattern: u8, idx: usize) -> String {
+    let convert = match pattern {
+        TYPE_NIL => return format!("args[{}]", idx),
+        TYPE_BOOL => "ToBoolean",
+        TYPE_U8 => "ToByte",
+        TYPE_I8 => "ToSByte",
+        TYPE_U16 => "ToUInt16",
+        TYPE_I16 => "ToInt16",
+        TYPE_U32 => "ToUInt32",
+        TYPE_I32 => "ToInt32",
+        TYPE_U64 => "ToUInt64",
+        TYPE_I64 | TYPE_VARINT => "ToInt64",
+        TYPE_FLOAT => "ToSingle",
+        TYPE_DOUBLE => "ToDouble",
+        _ => return format!("({})args[{}]", type_name(pattern), idx),
+    };
+    format!("Convert.{}(args[{}])", convert, idx)
+}
+
+pub fn write(out: &mut String, schema: &ProtoSchema) -> Result {
+    writeln!(out, "// {}", GENERATED_HEADER)?;
+    writeln!(out, "using System;")?;
+    writeln!(out, "using System.Collections;")?;
+    writeln!(out, "using System.Collections.Generic;")?;
+    writeln!(out)?;
+    writeln!(out, "namespace proto.tunm {{")?;
+    writeln!(out)?;
+    writeln!(out, "    public interface ITunmHandler {{")?;
+    for proto in &schema.protos {
+        let params: Vec<String> = proto.args.iter().map(|a| format!("{} {}", type_name(a.pattern), a.name)).collect();
+        writeln!(out, "        void on_{}({});", proto.name, params.join(", "))?;
+    }
+    writeln!(out, "    }}")?;
+    writeln!(out)?;
+    writeln!(out, "    public class TunmProtos {{")?;
+    for proto in &schema.protos {
+        writeln!(out, "        public const string {} = \"{}\";", proto.const_name(), proto.name)?;
+    }
+
+    for proto in &schema.protos {
+        let mut params = vec!["ref TunmBuffer buffer".to_string()];
+        params.extend(proto.args.iter().map(|a| format!("{} {}", type_name(a.pattern), a.name)));
+        let values: Vec<String> = proto.args.iter().map(arg_value).collect();
+        writeln!(out)?;
+        writeln!(out, "        public static void send_{}({}) {{", proto.name, params.join(", "))?;
+        if values.is_empty() {
+            writeln!(out, "            var infos = new ArrayList();")?;
+        } else {
+            writeln!(out, "            var infos = new ArrayList {{ {} }};", values.join(", "))?;
+        }
+        writeln!(out, "            TunmEncode.encode_proto(ref buffer, {}, infos);", proto.const_name())?;
+        writeln!(out, "        }}")?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "        // call `handler.on_<proto>(args...)`, return false if the proto is unknown")?;
+    writeln!(out, "        public static bool dispatch(string name, ArrayList args, ITunmHandler handler) {{")?;
+    writeln!(out, "            switch (name) {{")?;
+    for proto in &schema.protos {
+        let call_args: Vec<String> = proto.args.iter().enumerate().map(|(i, a)| arg_read(a.pattern, i)).collect();
+        writeln!(out, "                case {}:", proto.const_name())?;
+        writeln!(out, "                    if (args.Count != {}) {{", proto.args.len())?;
+        writeln!(out, "                        return false;")?;
+        writeln!(out, "                    }}")?;
+        writeln!(out, "                    handler.on_{}({});", proto.name, call_args.join(", "))?;
+        writeln!(out, "                    return true;")?;
+    }
+    writeln!(out, "            }}")?;
+    writeln!(out, "            return false;")?;
+    writeln!(out, "        }}")?;
+    writeln!(out, "    }}")?;
+    writeln!(out, "}}")
+}
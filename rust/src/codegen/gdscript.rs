@@ -0,0 +1,60 @@
+use std::fmt::{Result, Write};
+
+use super::{ProtoArg, ProtoSchema, GENERATED_HEADER};
+use crate::{TYPE_ARR, TYPE_BOOL, TYPE_DOUBLE, TYPE_FLOAT, TYPE_MAP, TYPE_NIL, TYPE_RAW, TYPE_STR};
+
+fn type_name(pattern: u8) -> Option<&'static str> {
+    match pattern {
+        TYPE_NIL => None,
+        TYPE_BOOL => Some("bool"),
+        TYPE_FLOAT | TYPE_DOUBLE => Some("float"),
+        TYPE_STR => Some("String"),
+        TYPE_RAW => Some("PoolByteArray"),
+        TYPE_ARR => Some("Array"),
+        TYPE_MAP => Some("Dictionary"),
+        _ => Some("int"),
+    }
+}
+
+fn param(arg: &ProtoArg) -> String {
+    match type_name(arg.pattern) {
+        Some(ty) => format!("{}: {}", arg.name, ty),
+        None => arg.name.clone(),
+    }
+}
+
+pub fn write(out: &mut String, schema: &ProtoSchema) -> Result {
+    writeln!(out, "# {}", GENERATED_HEADER)?;
+    writeln!(out)?;
+    writeln!(out, "const Core = preload(\"res://proto/core.gd\")")?;
+    writeln!(out)?;
+    for proto in &schema.protos {
+        writeln!(out, "const {} = \"{}\"", proto.const_name(), proto.name)?;
+    }
+
+    for proto in &schema.protos {
+        let mut params = vec!["buffer: Buffer".to_string()];
+        params.extend(proto.args.iter().map(param));
+        writeln!(out)?;
+        writeln!(out, "static func send_{}({}) -> void:", proto.name, params.join(", "))?;
+        writeln!(out, "\tCore.PBPacker.encode_proto(buffer, {}, [{}])",
+                 proto.const_name(), proto.arg_names().join(", "))?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "# call `handler.on_<proto>(args...)`, return false if the proto is unknown")?;
+    writeln!(out, "static func dispatch(name: String, args: Array, handler: Object) -> bool:")?;
+    if !schema.protos.is_empty() {
+        writeln!(out, "\tmatch name:")?;
+    }
+    for proto in &schema.protos {
+        let call_args: Vec<String> = (0..proto.args.len()).map(|i| format!("args[{}]", i)).collect();
+        writeln!(out, "\t\t{}:", proto.const_name())?;
+        writeln!(out, "\t\t\tif len(args) != {} or not handler.has_method(\"on_{}\"):",
+                 proto.args.len(), proto.name)?;
+        writeln!(out, "\t\t\t\treturn false")?;
+        writeln!(out, "\t\t\thandler.on_{}({})", proto.name, call_args.join(", "))?;
+        writeln!(out, "\t\t\treturn true")?;
+    }
+    writeln!(out, "\treturn false")
+}
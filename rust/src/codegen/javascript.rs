@@ -0,0 +1,60 @@
+use std::fmt::{Result, Write};
+
+use super::{ProtoSchema, GENERATED_HEADER};
+use crate::{TYPE_ARR, TYPE_BOOL, TYPE_MAP, TYPE_NIL, TYPE_RAW, TYPE_STR};
+
+fn type_name(pattern: u8) -> &'static str {
+    match pattern {
+        TYPE_NIL => "*",
+        TYPE_BOOL => "boolean",
+        TYPE_STR => "string",
+        TYPE_RAW => "Uint8Array",
+        TYPE_ARR => "Array",
+        TYPE_MAP => "Object",
+        _ => "number",
+    }
+}
+
+pub fn write(out: &mut String, schema: &ProtoSchema) -> Result {
+    writeln!(out, "// {}", GENERATED_HEADER)?;
+    writeln!(out)?;
+    writeln!(out, "var TunmProtos = {{")?;
+    for proto in &schema.protos {
+        writeln!(out, "    {}: \"{}\",", proto.const_name(), proto.name)?;
+    }
+    writeln!(out, "}};")?;
+
+    for proto in &schema.protos {
+        let mut params = vec!["rt", "buffer"];
+        params.extend(proto.arg_names());
+        writeln!(out)?;
+        writeln!(out, "/**")?;
+        writeln!(out, " * @param {{RtProto}} rt")?;
+        writeln!(out, " * @param {{ByteBuffer}} buffer")?;
+        for arg in &proto.args {
+            writeln!(out, " * @param {{{}}} {}", type_name(arg.pattern), arg.name)?;
+        }
+        writeln!(out, " */")?;
+        writeln!(out, "TunmProtos.send_{} = function ({}) {{", proto.name, params.join(", "))?;
+        writeln!(out, "    return rt.encode_proto(buffer, TunmProtos.{}, [{}])",
+                 proto.const_name(), proto.arg_names().join(", "))?;
+        writeln!(out, "}}")?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "// call `handler.on_<proto>(args...)`, return false if the proto is unknown")?;
+    writeln!(out, "TunmProtos.dispatch = function (proto, list, handler) {{")?;
+    writeln!(out, "    switch (proto) {{")?;
+    for proto in &schema.protos {
+        let call_args: Vec<String> = (0..proto.args.len()).map(|i| format!("list[{}]", i)).collect();
+        writeln!(out, "        case TunmProtos.{}:", proto.const_name())?;
+        writeln!(out, "            if (list.length != {} || !handler.on_{}) {{", proto.args.len(), proto.name)?;
+        writeln!(out, "                return false")?;
+        writeln!(out, "            }}")?;
+        writeln!(out, "            handler.on_{}({})", proto.name, call_args.join(", "))?;
+        writeln!(out, "            return true")?;
+    }
+    writeln!(out, "    }}")?;
+    writeln!(out, "    return false")?;
+    writeln!(out, "}}")
+}
@@ -0,0 +1,175 @@
+//! Generate typed send/receive helpers for the GDScript, JavaScript, Python
+//! and C# ports from a proto schema.
+//!
+//! The schema is a plain text file with one proto per line, the argument
+//! types are the names used by `get_type_by_name`:
+//!
+//! ```text
+//! # comments start with '#'
+//! cmd_login(account: str, password: str, server_id: u16)
+//! msg_enter_game(server: map)
+//! cmd_check_heart()
+//! ```
+
+use std::collections::HashSet;
+
+use crate::{get_name_by_type, get_type_by_name, ErrorKind, RpResult, TYPE_NIL, TYPE_STR_IDX};
+
+mod csharp;
+mod gdscript;
+mod javascript;
+mod python;
+
+pub const GENERATED_HEADER: &str = "Code generated by tunm_codegen. DO NOT EDIT.";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtoArg {
+    pub name: String,
+    pub pattern: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtoDef {
+    pub name: String,
+    pub args: Vec<ProtoArg>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProtoSchema {
+    pub protos: Vec<ProtoDef>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    GdScript,
+    JavaScript,
+    Python,
+    CSharp,
+}
+
+impl Lang {
+    pub const ALL: [Lang; 4] = [Lang::GdScript, Lang::JavaScript, Lang::Python, Lang::CSharp];
+
+    pub fn from_name(name: &str) -> Option<Lang> {
+        match name {
+            "gd" | "gdscript" | "godot" => Some(Lang::GdScript),
+            "js" | "javascript" => Some(Lang::JavaScript),
+            "py" | "python" => Some(Lang::Python),
+            "cs" | "csharp" | "dotnet" => Some(Lang::CSharp),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Lang::GdScript => "gdscript",
+            Lang::JavaScript => "javascript",
+            Lang::Python => "python",
+            Lang::CSharp => "csharp",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            Lang::GdScript => "gd",
+            Lang::JavaScript => "js",
+            Lang::Python => "py",
+            Lang::CSharp => "cs",
+        }
+    }
+}
+
+fn is_ident(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_error<T>(line: usize, detail: &str) -> RpResult<T> {
+    fail!((ErrorKind::ParseError, "schema parse error", format!("line {}: {}", line, detail)))
+}
+
+fn parse_arg(line: usize, text: &str) -> RpResult<ProtoArg> {
+    let (name, ty) = match text.split_once(':') {
+        Some((name, ty)) => (name.trim(), ty.trim()),
+        None => return parse_error(line, &format!("argument `{}` must be `name: type`", text)),
+    };
+    if !is_ident(name) {
+        return parse_error(line, &format!("invalid argument name `{}`", name));
+    }
+    let pattern = get_type_by_name(ty);
+    if (pattern == TYPE_NIL && ty != get_name_by_type(TYPE_NIL)) || pattern == TYPE_STR_IDX {
+        return parse_error(line, &format!("unknown type `{}`", ty));
+    }
+    Ok(ProtoArg { name: name.to_string(), pattern })
+}
+
+impl ProtoSchema {
+    pub fn parse(text: &str) -> RpResult<ProtoSchema> {
+        let mut schema = ProtoSchema::default();
+        let mut names = HashSet::new();
+        for (idx, raw) in text.lines().enumerate() {
+            let line = idx + 1;
+            let raw = match raw.find('#') {
+                Some(pos) => &raw[..pos],
+                None => raw,
+            };
+            let raw = raw.trim();
+            if raw.is_empty() {
+                continue;
+            }
+
+            let (name, rest) = match raw.split_once('(') {
+                Some(v) => v,
+                None => return parse_error(line, "expect `name(args...)`"),
+            };
+            let name = name.trim();
+            let body = match rest.trim_end().strip_suffix(')') {
+                Some(body) => body,
+                None => return parse_error(line, "missing `)`"),
+            };
+            if !is_ident(name) {
+                return parse_error(line, &format!("invalid proto name `{}`", name));
+            }
+            if !names.insert(name.to_string()) {
+                return parse_error(line, &format!("duplicate proto `{}`", name));
+            }
+
+            let mut args: Vec<ProtoArg> = Vec::new();
+            for arg in body.split(',').map(str::trim).filter(|a| !a.is_empty()) {
+                let arg = parse_arg(line, arg)?;
+                if args.iter().any(|a| a.name == arg.name) {
+                    return parse_error(line, &format!("duplicate argument `{}`", arg.name));
+                }
+                args.push(arg);
+            }
+            schema.protos.push(ProtoDef { name: name.to_string(), args });
+        }
+        Ok(schema)
+    }
+}
+
+impl ProtoDef {
+    /// the constant name used for the proto name in generated code
+    pub fn const_name(&self) -> String {
+        self.name.to_ascii_uppercase()
+    }
+
+    fn arg_names(&self) -> Vec<&str> {
+        self.args.iter().map(|a| &*a.name).collect()
+    }
+}
+
+pub fn generate(schema: &ProtoSchema, lang: Lang) -> String {
+    let mut out = String::new();
+    match lang {
+        Lang::GdScript => gdscript::write(&mut out, schema),
+        Lang::JavaScript => javascript::write(&mut out, schema),
+        Lang::Python => python::write(&mut out, schema),
+        Lang::CSharp => csharp::write(&mut out, schema),
+    }.expect("writing into a String can't fail");
+    out
+}
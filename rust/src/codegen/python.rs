@@ -0,0 +1,67 @@
+use std::fmt::{Result, Write};
+
+use super::{ProtoArg, ProtoSchema, GENERATED_HEADER};
+use crate::{TYPE_ARR, TYPE_BOOL, TYPE_DOUBLE, TYPE_FLOAT, TYPE_MAP, TYPE_NIL, TYPE_RAW, TYPE_STR};
+
+fn type_name(pattern: u8) -> &'static str {
+    match pattern {
+        TYPE_NIL => "Any",
+        TYPE_BOOL => "bool",
+        TYPE_FLOAT | TYPE_DOUBLE => "float",
+        TYPE_STR => "str",
+        TYPE_RAW => "bytes",
+        TYPE_ARR => "list",
+        TYPE_MAP => "dict",
+        _ => "int",
+    }
+}
+
+/// the python port picks the type from the value, so `1` would go out as u8
+fn arg_value(arg: &ProtoArg) -> String {
+    match arg.pattern {
+        TYPE_FLOAT | TYPE_DOUBLE => format!("float({})", arg.name),
+        _ => arg.name.clone(),
+    }
+}
+
+pub fn write(out: &mut String, schema: &ProtoSchema) -> Result {
+    let use_any = schema.protos.iter().any(|p| p.args.iter().any(|a| a.pattern == TYPE_NIL));
+
+    writeln!(out, "# {}", GENERATED_HEADER)?;
+    if use_any {
+        writeln!(out, "from typing import Any")?;
+        writeln!(out)?;
+    }
+    writeln!(out, "from tunm_proto import ByteBuffer, TPPacker")?;
+    writeln!(out)?;
+    for proto in &schema.protos {
+        writeln!(out, "{} = \"{}\"", proto.const_name(), proto.name)?;
+    }
+
+    for proto in &schema.protos {
+        let mut params = vec!["buffer: ByteBuffer".to_string()];
+        params.extend(proto.args.iter().map(|a| format!("{}: {}", a.name, type_name(a.pattern))));
+        let values: Vec<String> = proto.args.iter().map(arg_value).collect();
+        writeln!(out)?;
+        writeln!(out)?;
+        writeln!(out, "def send_{}({}):", proto.name, params.join(", "))?;
+        writeln!(out, "    TPPacker.encode_proto(buffer, {}, [{}])", proto.const_name(), values.join(", "))?;
+    }
+
+    writeln!(out)?;
+    writeln!(out)?;
+    writeln!(out, "def dispatch(name: str, args: list, handler) -> bool:")?;
+    writeln!(out, "    '''")?;
+    writeln!(out, "    call `handler.on_<proto>(args...)`, return False if the proto is unknown")?;
+    writeln!(out, "    '''")?;
+    for proto in &schema.protos {
+        let call_args: Vec<String> = (0..proto.args.len()).map(|i| format!("args[{}]", i)).collect();
+        writeln!(out, "    if name == {}:", proto.const_name())?;
+        writeln!(out, "        func = getattr(handler, \"on_{}\", None)", proto.name)?;
+        writeln!(out, "        if func is None or len(args) != {}:", proto.args.len())?;
+        writeln!(out, "            return False")?;
+        writeln!(out, "        func({})", call_args.join(", "))?;
+        writeln!(out, "        return True")?;
+    }
+    writeln!(out, "    return False")
+}
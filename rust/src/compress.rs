@@ -0,0 +1,153 @@
+//! Optional compression of the proto body, LZ4 with the `lz4` feature and
+//! zstd with the `zstd` feature.
+//!
+//! With a `CompressConfig` set on a `Buffer` (see `Buffer::set_compress`),
+//! `encode_proto` writes a flag byte right after the proto name. `0` means the
+//! body follows as it is, which is what bodies shorter than `threshold` or
+//! ones that do not get smaller use. Otherwise the flag names the algorithm
+//! and is followed by the varint body size, the varint compressed size and
+//! the compressed body. `decode_proto` refuses bodies larger than `max_size`
+//! before it decompresses anything.
+
+use std::io::Write;
+
+use crate::{decode_varint, encode_sure_type, encode_varint, Buffer, ErrorKind, RpResult, Value};
+
+pub const COMPRESS_NONE: u8 = 0;
+pub const COMPRESS_LZ4: u8 = 1;
+pub const COMPRESS_ZSTD: u8 = 2;
+
+/// `Lz4` comes with the `lz4` feature and `Zstd` with the `zstd` feature, a
+/// body the peer compressed with one that is not compiled in fails to decode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    #[cfg(feature = "lz4")]
+    Lz4,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressConfig {
+    /// what the encoder uses, the decoder reads it from the flag
+    pub algorithm: Compression,
+    /// bodies shorter than this are not compressed
+    pub threshold: usize,
+    /// the largest decompressed body the decoder accepts
+    pub max_size: usize,
+    /// the zstd level, lz4 has none
+    pub level: i32,
+}
+
+impl CompressConfig {
+    pub fn new(algorithm: Compression) -> CompressConfig {
+        CompressConfig { algorithm, threshold: 1024, max_size: 16 * 1024 * 1024, level: 3 }
+    }
+}
+
+#[cfg(feature = "lz4")]
+fn lz4_compress(body: &[u8]) -> RpResult<Vec<u8>> {
+    Ok(lz4_flex::block::compress(body))
+}
+
+#[cfg(feature = "lz4")]
+fn lz4_decompress(data: &[u8], size: usize) -> RpResult<Vec<u8>> {
+    match lz4_flex::block::decompress(data, size) {
+        Ok(body) => Ok(body),
+        Err(err) => fail!((ErrorKind::ParseError, "bad lz4 body", err.to_string())),
+    }
+}
+
+#[cfg(not(feature = "lz4"))]
+fn lz4_decompress(_data: &[u8], _size: usize) -> RpResult<Vec<u8>> {
+    fail!((ErrorKind::ParseError, "compression not enabled", "lz4".to_string()))
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_compress(body: &[u8], level: i32) -> RpResult<Vec<u8>> {
+    Ok(zstd::bulk::compress(body, level)?)
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_decompress(data: &[u8], size: usize) -> RpResult<Vec<u8>> {
+    // the capacity caps the output, a body that claims less than it holds fails
+    match zstd::bulk::decompress(data, size) {
+        Ok(body) => Ok(body),
+        Err(err) => fail!((ErrorKind::ParseError, "bad zstd body", err.to_string())),
+    }
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_decompress(_data: &[u8], _size: usize) -> RpResult<Vec<u8>> {
+    fail!((ErrorKind::ParseError, "compression not enabled", "zstd".to_string()))
+}
+
+/// the flag of `config.algorithm` and `body` compressed with it
+#[cfg_attr(not(any(feature = "lz4", feature = "zstd")), allow(unused_variables))]
+fn compress(config: &CompressConfig, body: &[u8]) -> RpResult<(u8, Vec<u8>)> {
+    match config.algorithm {
+        #[cfg(feature = "lz4")]
+        Compression::Lz4 => Ok((COMPRESS_LZ4, lz4_compress(body)?)),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Ok((COMPRESS_ZSTD, zstd_compress(body, config.level)?)),
+    }
+}
+
+fn decompress(flag: u8, data: &[u8], size: usize) -> RpResult<Vec<u8>> {
+    let body = match flag {
+        COMPRESS_LZ4 => lz4_decompress(data, size)?,
+        COMPRESS_ZSTD => zstd_decompress(data, size)?,
+        _ => fail!((ErrorKind::ParseError, "unknown compression", flag.to_string())),
+    };
+    if body.len() != size {
+        fail!((ErrorKind::ParseError, "decompressed size not match",
+               format!("expect {}, got {}", size, body.len())));
+    }
+    Ok(body)
+}
+
+/// write the flag and `body`, compressed when that is worth it
+pub(crate) fn write_body(buffer: &mut Buffer, body: &[u8], config: &CompressConfig) -> RpResult<()> {
+    if body.len() >= config.threshold {
+        let (flag, packed) = compress(config, body)?;
+        if packed.len() < body.len() {
+            encode_sure_type(buffer, flag)?;
+            encode_varint(buffer, &Value::U64(body.len() as u64))?;
+            encode_varint(buffer, &Value::U64(packed.len() as u64))?;
+            buffer.write_all(&packed)?;
+            return Ok(());
+        }
+    }
+    encode_sure_type(buffer, COMPRESS_NONE)?;
+    buffer.write_all(body)?;
+    Ok(())
+}
+
+fn read_size(buffer: &mut Buffer) -> RpResult<usize> {
+    let size: i64 = decode_varint(buffer)?.into();
+    match usize::try_from(size) {
+        Ok(size) => Ok(size),
+        Err(_) => fail!((ErrorKind::ParseError, "negative length")),
+    }
+}
+
+/// read the flag, the decompressed body or `None` when the body follows as it is
+pub(crate) fn read_body(buffer: &mut Buffer, config: &CompressConfig) -> RpResult<Option<Vec<u8>>> {
+    let flag: &mut [u8; 1] = &mut [0];
+    try_read!(buffer.read_bytes(flag), flag.len());
+    if flag[0] == COMPRESS_NONE {
+        return Ok(None);
+    }
+    let size = read_size(buffer)?;
+    if size > config.max_size {
+        fail!((ErrorKind::BufferOverMaxError, "decompressed proto too large",
+               format!("{} over {}", size, config.max_size)));
+    }
+    let packed_len = read_size(buffer)?;
+    if packed_len > buffer.data_len() {
+        fail!((ErrorKind::NoLeftSpaceError, "must left space to read "));
+    }
+    let mut packed = vec![0; packed_len];
+    try_read!(buffer.read_bytes(&mut packed), packed_len);
+    decompress(flag[0], &packed, size).map(Some)
+}
@@ -0,0 +1,231 @@
+//! Conversion between Rust types and `Value`, implemented for the std types
+//! here and for structs and enums by `#[derive(TunmEncode, TunmDecode)]` with
+//! the `derive` feature.
+//!
+//! Integers decode from any integer variant as long as the number fits, the
+//! wire turns u16 to u64 into varints. A missing struct field decodes from
+//! `Value::Nil`, so `Option` fields may be left out.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::{get_name_by_type, get_type_by_value, ErrorKind, RpError, RpResult, Value};
+
+pub trait TunmEncode {
+    fn to_value(&self) -> Value;
+}
+
+pub trait TunmDecode: Sized {
+    fn from_value(value: Value) -> RpResult<Self>;
+}
+
+#[doc(hidden)]
+pub fn type_mismatch(expect: &str, found: &Value) -> RpError {
+    let detail = format!("expect {}, found {}", expect, get_name_by_type(get_type_by_value(found)));
+    (ErrorKind::TypeNotMatchError, "value type not match", detail).into()
+}
+
+#[doc(hidden)]
+pub fn missing_field(ty: &str, field: &str) -> RpError {
+    (ErrorKind::MissingError, "missing field", format!("{}.{}", ty, field)).into()
+}
+
+#[doc(hidden)]
+pub fn extra_items(ty: &str, expect: usize, found: usize) -> RpError {
+    (ErrorKind::ParseError, "too many items", format!("{} expect {}, found {}", ty, expect, found)).into()
+}
+
+#[doc(hidden)]
+pub fn unknown_variant(ty: &str, variant: &Value) -> RpError {
+    (ErrorKind::TypeNotMatchError, "unknown variant", format!("{} {}", ty, variant)).into()
+}
+
+impl TunmEncode for Value {
+    fn to_value(&self) -> Value {
+        self.clone()
+    }
+}
+
+impl TunmDecode for Value {
+    fn from_value(value: Value) -> RpResult<Self> {
+        Ok(value)
+    }
+}
+
+impl TunmEncode for bool {
+    fn to_value(&self) -> Value {
+        Value::Bool(*self)
+    }
+}
+
+impl TunmDecode for bool {
+    fn from_value(value: Value) -> RpResult<Self> {
+        match value {
+            Value::Bool(val) => Ok(val),
+            other => Err(type_mismatch("bool", &other)),
+        }
+    }
+}
+
+macro_rules! impl_integer {
+    ($($ty:ident => $variant:ident,)*) => {$(
+        impl TunmEncode for $ty {
+            fn to_value(&self) -> Value {
+                Value::$variant(*self)
+            }
+        }
+
+        impl TunmDecode for $ty {
+            fn from_value(value: Value) -> RpResult<Self> {
+                let val: i128 = match value {
+                    Value::U8(val) => val.into(),
+                    Value::I8(val) => val.into(),
+                    Value::U16(val) => val.into(),
+                    Value::I16(val) => val.into(),
+                    Value::U32(val) => val.into(),
+                    Value::I32(val) => val.into(),
+                    Value::U64(val) => val.into(),
+                    Value::I64(val) | Value::Varint(val) => val.into(),
+                    other => return Err(type_mismatch(stringify!($ty), &other)),
+                };
+                match $ty::try_from(val) {
+                    Ok(val) => Ok(val),
+                    Err(_) => Err((ErrorKind::TypeNotMatchError, "number out of range",
+                                   format!("{} for {}", val, stringify!($ty))).into()),
+                }
+            }
+        }
+    )*};
+}
+
+impl_integer! {
+    u8 => U8,
+    i8 => I8,
+    u16 => U16,
+    i16 => I16,
+    u32 => U32,
+    i32 => I32,
+    u64 => U64,
+    i64 => I64,
+}
+
+impl TunmEncode for f32 {
+    fn to_value(&self) -> Value {
+        Value::Float(*self)
+    }
+}
+
+impl TunmDecode for f32 {
+    fn from_value(value: Value) -> RpResult<Self> {
+        match value {
+            Value::Float(val) => Ok(val),
+            Value::Double(val) => Ok(val as f32),
+            other => Err(type_mismatch("float", &other)),
+        }
+    }
+}
+
+impl TunmEncode for f64 {
+    fn to_value(&self) -> Value {
+        Value::Double(*self)
+    }
+}
+
+impl TunmDecode for f64 {
+    fn from_value(value: Value) -> RpResult<Self> {
+        match value {
+            Value::Float(val) => Ok(val as f64),
+            Value::Double(val) => Ok(val),
+            other => Err(type_mismatch("double", &other)),
+        }
+    }
+}
+
+impl TunmEncode for String {
+    fn to_value(&self) -> Value {
+        Value::Str(self.clone())
+    }
+}
+
+impl TunmEncode for str {
+    fn to_value(&self) -> Value {
+        Value::Str(self.to_string())
+    }
+}
+
+impl TunmDecode for String {
+    fn from_value(value: Value) -> RpResult<Self> {
+        match value {
+            Value::Str(val) => Ok(val),
+            other => Err(type_mismatch("str", &other)),
+        }
+    }
+}
+
+impl<T: TunmEncode + ?Sized> TunmEncode for &T {
+    fn to_value(&self) -> Value {
+        (**self).to_value()
+    }
+}
+
+impl<T: TunmEncode + ?Sized> TunmEncode for Box<T> {
+    fn to_value(&self) -> Value {
+        (**self).to_value()
+    }
+}
+
+impl<T: TunmDecode> TunmDecode for Box<T> {
+    fn from_value(value: Value) -> RpResult<Self> {
+        T::from_value(value).map(Box::new)
+    }
+}
+
+/// `None` is `Value::Nil`
+impl<T: TunmEncode> TunmEncode for Option<T> {
+    fn to_value(&self) -> Value {
+        match self {
+            Some(val) => val.to_value(),
+            None => Value::Nil,
+        }
+    }
+}
+
+impl<T: TunmDecode> TunmDecode for Option<T> {
+    fn from_value(value: Value) -> RpResult<Self> {
+        match value {
+            Value::Nil => Ok(None),
+            other => T::from_value(other).map(Some),
+        }
+    }
+}
+
+/// always an array, hold raw data in a `Value::Raw` field
+impl<T: TunmEncode> TunmEncode for Vec<T> {
+    fn to_value(&self) -> Value {
+        Value::Arr(self.iter().map(TunmEncode::to_value).collect())
+    }
+}
+
+impl<T: TunmDecode> TunmDecode for Vec<T> {
+    fn from_value(value: Value) -> RpResult<Self> {
+        match value {
+            Value::Arr(arr) => arr.into_iter().map(T::from_value).collect(),
+            other => Err(type_mismatch("arr", &other)),
+        }
+    }
+}
+
+impl<K: TunmEncode, V: TunmEncode> TunmEncode for HashMap<K, V> {
+    fn to_value(&self) -> Value {
+        Value::Map(self.iter().map(|(k, v)| (k.to_value(), v.to_value())).collect())
+    }
+}
+
+impl<K: TunmDecode + Eq + Hash, V: TunmDecode> TunmDecode for HashMap<K, V> {
+    fn from_value(value: Value) -> RpResult<Self> {
+        match value {
+            Value::Map(map) => map.into_iter().map(|(k, v)| Ok((K::from_value(k)?, V::from_value(v)?))).collect(),
+            other => Err(type_mismatch("map", &other)),
+        }
+    }
+}
@@ -1,7 +1,12 @@
 use std::collections::HashMap;
-use std::io::Read;
-use std::mem;
+use std::io::Write;
 
+use crate::buffer::MAX_SESSION_STRS;
+use crate::checksum::read_frame;
+use crate::compress::read_body;
+use crate::seal::read_sealed;
+#[cfg(feature = "sign")]
+use crate::sign::read_signed;
 use crate::{TYPE_STR_IDX, TYPE_VARINT};
 
 use super::{Value, Buffer, RpResult, ErrorKind};
@@ -9,9 +14,13 @@ use super::{TYPE_NIL, TYPE_BOOL, TYPE_U8, TYPE_I8, TYPE_U16, TYPE_I16, TYPE_U32,
      TYPE_RAW, TYPE_ARR, TYPE_MAP};
 use super::{make_extension_error};
 
+/// arrays and maps nested deeper than this are rejected, every level is one
+/// more recursion of `decode_field`
+pub const MAX_DECODE_DEPTH: usize = 64;
+
 pub fn decode_type(buffer: &mut Buffer) -> RpResult<Value> {
     let data: &mut [u8; 1] = &mut [0];
-    try_read!(buffer.read(data), data.len());
+    try_read!(buffer.read_bytes(data), data.len());
     Ok(Value::from(data[0]))
 }
 
@@ -19,12 +28,10 @@ pub fn decode_bool(buffer: &mut Buffer, pattern: u8) -> RpResult<Value> {
     match pattern {
         TYPE_BOOL => {
             let data: &mut [u8; 1] = &mut [0];
-            try_read!(buffer.read(data), data.len());
-            Ok(Value::from(if data[0] == 1 { true } else { false }))
-        }
-        _ => {
-            unreachable!("not other numbers");
+            try_read!(buffer.read_bytes(data), data.len());
+            Ok(Value::from(data[0] == 1))
         }
+        _ => fail!((ErrorKind::TypeNotMatchError, "must match type")),
     }
 }
 
@@ -32,68 +39,60 @@ pub fn decode_number(buffer: &mut Buffer, pattern: u8) -> RpResult<Value> {
     match pattern {
         TYPE_U8 => {
             let data: &mut [u8; 1] = &mut [0];
-            try_read!(buffer.read(data), data.len());
+            try_read!(buffer.read_bytes(data), data.len());
             Ok(Value::from(data[0]))
         }
         TYPE_I8 => {
             let data: &mut [u8; 1] = &mut [0];
-            try_read!(buffer.read(data), data.len());
+            try_read!(buffer.read_bytes(data), data.len());
             Ok(Value::from(data[0] as i8))
         }
         TYPE_U16 => {
             let data: &mut [u8; 2] = &mut [0, 0];
-            try_read!(buffer.read(data), data.len());
-            let val = unsafe { mem::transmute::<[u8; 2], u16>(*data) };
-            Ok(Value::from(u16::from_le(val)))
+            try_read!(buffer.read_bytes(data), data.len());
+            Ok(Value::from(u16::from_le_bytes(*data)))
         }
         TYPE_I16 => {
             let data: &mut [u8; 2] = &mut [0, 0];
-            try_read!(buffer.read(data), data.len());
-            let val = unsafe { mem::transmute::<[u8; 2], i16>(*data) };
-            Ok(Value::from(i16::from_le(val)))
+            try_read!(buffer.read_bytes(data), data.len());
+            Ok(Value::from(i16::from_le_bytes(*data)))
         }
         TYPE_U32 => {
             let data: &mut [u8; 4] = &mut [0, 0, 0, 0];
-            try_read!(buffer.read(data), data.len());
-            let val = unsafe { mem::transmute::<[u8; 4], u32>(*data) };
-            Ok(Value::from(u32::from_le(val)))
+            try_read!(buffer.read_bytes(data), data.len());
+            Ok(Value::from(u32::from_le_bytes(*data)))
         }
         TYPE_I32 => {
             let data: &mut [u8; 4] = &mut [0, 0, 0, 0];
-            try_read!(buffer.read(data), data.len());
-            let val = unsafe { mem::transmute::<[u8; 4], i32>(*data) };
-            Ok(Value::from(i32::from_le(val)))
+            try_read!(buffer.read_bytes(data), data.len());
+            Ok(Value::from(i32::from_le_bytes(*data)))
         }
         TYPE_U64 => {
             let data: &mut [u8; 8] = &mut [0, 0, 0, 0, 0, 0, 0, 0];
-            try_read!(buffer.read(data), data.len());
-            let val = unsafe { mem::transmute::<[u8; 8], u64>(*data) };
-            Ok(Value::from(u64::from_le(val)))
+            try_read!(buffer.read_bytes(data), data.len());
+            Ok(Value::from(u64::from_le_bytes(*data)))
         }
         TYPE_I64 => {
             let data: &mut [u8; 8] = &mut [0, 0, 0, 0, 0, 0, 0, 0];
-            try_read!(buffer.read(data), data.len());
-            let val = unsafe { mem::transmute::<[u8; 8], i64>(*data) };
-            Ok(Value::from(i64::from_le(val)))
+            try_read!(buffer.read_bytes(data), data.len());
+            Ok(Value::from(i64::from_le_bytes(*data)))
         }
         TYPE_VARINT => {
             decode_varint(buffer)
         }
         TYPE_FLOAT => {
             let data: &mut [u8; 4] = &mut [0, 0, 0, 0];
-            try_read!(buffer.read(data), data.len());
-            let val = unsafe { mem::transmute::<[u8; 4], i32>(*data) };
+            try_read!(buffer.read_bytes(data), data.len());
+            let val = i32::from_le_bytes(*data);
             Ok(Value::from(val as f32 / 1000.0))
         }
         TYPE_DOUBLE => {
             let data: &mut [u8; 8] = &mut [0, 0, 0, 0, 0, 0, 0, 0];
-            try_read!(buffer.read(data), data.len());
-            let val = unsafe { mem::transmute::<[u8; 8], i64>(*data) };
+            try_read!(buffer.read_bytes(data), data.len());
+            let val = i64::from_le_bytes(*data);
             Ok(Value::from(val as f64 / 1000000.0))
         }
-        _ => {
-            unreachable!("not other numbers");
-        }
+        _ => fail!((ErrorKind::TypeNotMatchError, "must match type")),
     }
 }
 
@@ -102,13 +101,13 @@ pub fn decode_varint(buffer: &mut Buffer) -> RpResult<Value> {
     let mut real = 0u64;
     let mut shl_num = 0;
     loop {
-        try_read!(buffer.read(data), data.len());
+        try_read!(buffer.read_bytes(data), data.len());
         let read = (data[0] & 0x7F) as u64;
-        if let Some(sread) = read.checked_shl(shl_num) {
-            real += sread;
-        } else {
+        // the tenth byte only has room for the highest bit
+        if shl_num > 63 || (shl_num == 63 && read > 1) {
             fail!((ErrorKind::ParseError, "too big varint"));
         }
+        real |= read << shl_num;
         shl_num += 7;
         if (data[0] & 0x80) == 0 {
             break;
@@ -123,15 +122,27 @@ pub fn decode_varint(buffer: &mut Buffer) -> RpResult<Value> {
     Ok(Value::Varint(val))
 }
 
+/// read a varint length or index, the data must hold at least `min_size` bytes for each of them
+fn decode_len(buffer: &mut Buffer, min_size: usize) -> RpResult<usize> {
+    let len: i64 = decode_varint(buffer)?.into();
+    if len < 0 {
+        fail!((ErrorKind::ParseError, "negative length"));
+    }
+    if (len as u64).saturating_mul(min_size as u64) > buffer.data_len() as u64 {
+        fail!((ErrorKind::NoLeftSpaceError, "must left space to read "));
+    }
+    Ok(len as usize)
+}
+
 pub fn decode_str_raw(buffer: &mut Buffer, pattern: u8) -> RpResult<Value> {
     match pattern {
         TYPE_STR => {
-            let len: u16 = decode_varint(buffer)?.into();
+            let len = decode_len(buffer, 1)?;
             if len == 0 {
                 return Ok(Value::from(String::new()));
             }
-            let mut rv = vec![0; len as usize];
-            try_read!(buffer.read(&mut rv[..]), len as usize);
+            let mut rv = vec![0; len];
+            try_read!(buffer.read_bytes(&mut rv[..]), len);
             let val = String::from_utf8(rv);
             if val.is_err() {
                 fail!((ErrorKind::StringFormatError, "string format error"));
@@ -139,47 +150,54 @@ pub fn decode_str_raw(buffer: &mut Buffer, pattern: u8) -> RpResult<Value> {
             Ok(Value::from(val.ok().unwrap()))
         }
         TYPE_RAW => {
-            let len: u16 = decode_varint(buffer)?.into();
+            let len = decode_len(buffer, 1)?;
             if len == 0 {
                 return Ok(Value::from(Vec::<u8>::new()))
             }
-            let mut rv = vec![0; len as usize];
-            try_read!(buffer.read(&mut rv[..]), len as usize);
+            let mut rv = vec![0; len];
+            try_read!(buffer.read_bytes(&mut rv[..]), len);
             Ok(Value::from(rv))
         }
-        _ => {
-            unreachable!("not other str");
-        }
+        _ => fail!((ErrorKind::TypeNotMatchError, "must match type")),
     }
 }
 
 pub fn decode_map(buffer: &mut Buffer) -> RpResult<Value> {
+    decode_map_depth(buffer, 0)
+}
+
+fn decode_map_depth(buffer: &mut Buffer, depth: usize) -> RpResult<Value> {
     let mut map = HashMap::<Value, Value>::new();
-    let arr_len: u16 = decode_varint(buffer)?.into();
+    // every key and value takes at least one byte for its type
+    let arr_len = decode_len(buffer, 2)?;
     for _ in 0 .. arr_len {
-        let key = decode_field(buffer)?;
-        let sub_value = decode_field(buffer)?;
+        let key = decode_field_depth(buffer, depth + 1)?;
+        let sub_value = decode_field_depth(buffer, depth + 1)?;
         map.insert(key, sub_value);
     }
     Ok(Value::from(map))
 }
 
 pub fn decode_arr(buffer: &mut Buffer) -> RpResult<Value> {
+    decode_arr_depth(buffer, 0)
+}
+
+fn decode_arr_depth(buffer: &mut Buffer, depth: usize) -> RpResult<Value> {
     let mut arr = Vec::<Value>::new();
-    let arr_len: u16 = decode_varint(buffer)?.into();
+    let arr_len = decode_len(buffer, 1)?;
     for _ in 0 .. arr_len {
-        let sub_value = decode_field(buffer)?;
+        let sub_value = decode_field_depth(buffer, depth + 1)?;
         arr.push(sub_value);
     }
     Ok(Value::from(arr))
 }
 
-fn decode_by_pattern(buffer: &mut Buffer, pattern: &u8) -> RpResult<Value> {
+fn decode_by_pattern(buffer: &mut Buffer, pattern: &u8, depth: usize) -> RpResult<Value> {
     match *pattern {
         TYPE_BOOL => {
             decode_bool(buffer, *pattern)
         }
-        TYPE_U8 | TYPE_I8 | TYPE_U16 | TYPE_I16 | TYPE_U32 | TYPE_I32 => {
+        TYPE_U8 | TYPE_I8 | TYPE_U16 | TYPE_I16 | TYPE_U32 | TYPE_I32 | TYPE_U64 | TYPE_I64 => {
             decode_number(buffer, *pattern)
         }
         TYPE_FLOAT => {
@@ -194,11 +212,17 @@ fn decode_by_pattern(buffer: &mut Buffer, pattern: &u8) -> RpResult<Value> {
             decode_varint(buffer)
         }
         TYPE_STR | TYPE_RAW => decode_str_raw(buffer, *pattern),
-        TYPE_MAP => decode_map(buffer),
-        TYPE_ARR => decode_arr(buffer),
+        TYPE_MAP | TYPE_ARR if depth >= MAX_DECODE_DEPTH => {
+            fail!((ErrorKind::ParseError, "nested too deep"))
+        }
+        TYPE_MAP => decode_map_depth(buffer, depth),
+        TYPE_ARR => decode_arr_depth(buffer, depth),
         TYPE_STR_IDX => {
-            let idx: u16 = decode_varint(buffer)?.into();
-            Ok(Value::from(buffer.get_str(idx)?))
+            let idx = decode_len(buffer, 0)?;
+            match u16::try_from(idx) {
+                Ok(idx) => Ok(Value::from(buffer.get_str(idx)?)),
+                Err(_) => fail!((ErrorKind::StringTableError, "unknown string index", idx.to_string())),
+            }
         },
         // TYPE_AMAP => decode_array!(decode_field(buffer, config), Value::AMap, Value::Map),
         TYPE_NIL => Ok(Value::Nil),
@@ -208,19 +232,156 @@ fn decode_by_pattern(buffer: &mut Buffer, pattern: &u8) -> RpResult<Value> {
 
 
 pub fn decode_field(buffer: &mut Buffer) -> RpResult<Value> {
+    decode_field_depth(buffer, 0)
+}
+
+fn decode_field_depth(buffer: &mut Buffer, depth: usize) -> RpResult<Value> {
     let pattern = decode_type(buffer)?.into();
-    decode_by_pattern(buffer, &pattern)
+    decode_by_pattern(buffer, &pattern, depth)
 }
 
+/// Every message has its own string table, unless the buffer is in session
+/// mode (see `Buffer::set_str_session`): then the strings of the message are
+/// added to the ones kept from earlier messages.
 pub fn decode_proto(buffer: &mut Buffer) -> RpResult<(String, Vec<Value>)> {
+    // a corrupt or partial frame is not decoded, the table stays as it is
+    let left = buffer.data_len();
+    let strs = buffer.str_arr.len();
+    let result = if let Some(mut key) = buffer.get_seal().cloned() {
+        let data = read_sealed(buffer, &mut key);
+        buffer.set_seal(Some(key));
+        decode_proto_frame(buffer, data?)
+    } else if let Some(data) = read_signed_frame(buffer) {
+        let data = data?;
+        decode_proto_frame(buffer, data)
+    } else if let Some(checksum) = buffer.get_checksum() {
+        let data = read_frame(buffer, checksum)?;
+        decode_proto_frame(buffer, data)
+    } else {
+        let result = decode_proto_name(buffer);
+        if matches!(&result, Err(err) if err.kind() == ErrorKind::NoLeftSpaceError) {
+            // a partial proto without a frame, wait for the rest of it and
+            // forget the strings it added so far, they come again
+            buffer.set_rpos(buffer.get_wpos() - left);
+            buffer.truncate_str(strs);
+            return result;
+        }
+        result
+    };
+    if result.is_err() && buffer.is_str_session() {
+        // the table may be half updated, wait for the peer to start over
+        buffer.clear_str();
+    }
+    result
+}
+
+/// the proto of the next signed frame, `None` when `buffer` has no sign keys
+#[cfg(feature = "sign")]
+fn read_signed_frame(buffer: &mut Buffer) -> Option<RpResult<Vec<u8>>> {
+    let keys = buffer.get_sign_keys().cloned()?;
+    Some(read_signed(buffer, &keys))
+}
+
+#[cfg(not(feature = "sign"))]
+fn read_signed_frame(_buffer: &mut Buffer) -> Option<RpResult<Vec<u8>>> {
+    None
+}
+
+fn decode_proto_frame(buffer: &mut Buffer, data: Vec<u8>) -> RpResult<(String, Vec<Value>)> {
+    let mut proto = Buffer::new();
+    proto.write_all(&data)?;
+    proto.set_compress(buffer.get_compress().copied());
+    proto.swap_str_state(buffer);
+    let result = decode_proto_name(&mut proto);
+    proto.swap_str_state(buffer);
+    if result.is_ok() && proto.data_len() != 0 {
+        fail!((ErrorKind::ParseError, "data left in the frame"));
+    }
+    result
+}
+
+fn decode_proto_name(buffer: &mut Buffer) -> RpResult<(String, Vec<Value>)> {
     let name = decode_str_raw(buffer, TYPE_STR)?.into();
+    let data = match buffer.get_compress().copied() {
+        Some(config) => read_body(buffer, &config)?,
+        None => None,
+    };
+    let data = match data {
+        Some(data) => data,
+        None => return decode_proto_body(buffer, name),
+    };
+    let mut body = Buffer::new();
+    body.write_all(&data)?;
+    body.swap_str_state(buffer);
+    let result = decode_proto_body(&mut body, name);
+    body.swap_str_state(buffer);
+    if result.is_ok() && body.data_len() != 0 {
+        fail!((ErrorKind::ParseError, "data left after the compressed proto"));
+    }
+    result
+}
+
+fn decode_proto_body(buffer: &mut Buffer, name: String) -> RpResult<(String, Vec<Value>)> {
+    if buffer.is_str_session() {
+        return decode_proto_session(buffer, name);
+    }
+    buffer.clear_str();
+    decode_dict_version(buffer)?;
     
-    let str_len: u16 = decode_varint(buffer)?.into();
+    let str_len = decode_len(buffer, 1)?;
     for _ in 0..str_len {
         let value = decode_str_raw(buffer, TYPE_STR)?.into();
-        buffer.add_str(value);
+        if buffer.try_add_str(value).is_none() {
+            fail!((ErrorKind::StringTableError, "string table full"));
+        }
     }
 
+    decode_proto_args(buffer, name)
+}
+
+/// check the version of the string dictionary when there is one, see `StrDict`
+fn decode_dict_version(buffer: &mut Buffer) -> RpResult<()> {
+    let expect = match buffer.get_str_dict() {
+        Some(dict) => dict.version(),
+        None => return Ok(()),
+    };
+    let marker: i64 = decode_varint(buffer)?.into();
+    if marker >= 0 {
+        fail!((ErrorKind::StringTableError, "message has no string dictionary",
+               format!("we have version {}", expect)));
+    }
+    if -1 - marker != i64::from(expect) {
+        fail!((ErrorKind::StringTableError, "string dictionary version mismatch",
+               format!("peer has version {}, we have {}", -1 - marker, expect)));
+    }
+    Ok(())
+}
+
+fn decode_proto_session(buffer: &mut Buffer, name: String) -> RpResult<(String, Vec<Value>)> {
+    decode_dict_version(buffer)?;
+
+    let base: i64 = decode_varint(buffer)?.into();
+    if base == 0 {
+        buffer.clear_str();
+    } else if base < 0 || base as u64 != buffer.str_arr.len() as u64 || base as u64 >= MAX_SESSION_STRS as u64 {
+        fail!((ErrorKind::StringTableError, "string table out of sync",
+               format!("peer has {}, we have {}", base, buffer.str_arr.len())));
+    }
+    let str_len = decode_len(buffer, 1)?;
+    for _ in 0..str_len {
+        let value: String = decode_str_raw(buffer, TYPE_STR)?.into();
+        let expect = buffer.str_base() + buffer.str_arr.len();
+        match buffer.try_add_str(value.clone()) {
+            Some(idx) if usize::from(idx) == expect => (),
+            Some(_) => fail!((ErrorKind::StringTableError, "string already in table", value)),
+            None => fail!((ErrorKind::StringTableError, "string table full")),
+        }
+    }
+
+    decode_proto_args(buffer, name)
+}
+
+fn decode_proto_args(buffer: &mut Buffer, name: String) -> RpResult<(String, Vec<Value>)> {
     let sub_value = decode_field(buffer)?;
     match sub_value {
         Value::Arr(val) => Ok((name, val)),
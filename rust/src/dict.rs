@@ -0,0 +1,66 @@
+//! Pre-shared string dictionary: strings both peers know at build time, such
+//! as map keys, are never sent.
+//!
+//! With a dictionary set on a `Buffer` (see `Buffer::set_str_dict`) the
+//! `TYPE_STR_IDX` indices `0..dict.len()` are the dictionary and the string
+//! table of a message starts after it. `encode_proto` writes the dictionary
+//! version right after the proto name as the varint `-1 - version`, a negative
+//! number where the string table count would be, so a peer without a
+//! dictionary fails on it and a peer with another version refuses it with
+//! `ErrorKind::StringTableError`.
+
+use std::collections::HashMap;
+
+use crate::{ErrorKind, RpResult};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrDict {
+    version: u32,
+    strs: Vec<String>,
+    map: HashMap<String, usize>,
+}
+
+impl StrDict {
+    /// A dictionary of `strs` in order, change `version` whenever they change.
+    pub fn new<I, S>(version: u32, strs: I) -> RpResult<StrDict>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut dict = StrDict { version, strs: Vec::new(), map: HashMap::new() };
+        for value in strs {
+            let value = value.into();
+            if dict.map.contains_key(&value) {
+                fail!((ErrorKind::StringTableError, "string twice in dictionary", value));
+            }
+            dict.map.insert(value.clone(), dict.strs.len());
+            dict.strs.push(value);
+        }
+        Ok(dict)
+    }
+
+    /// One string per line, empty lines are skipped.
+    pub fn from_lines(version: u32, text: &str) -> RpResult<StrDict> {
+        StrDict::new(version, text.lines().filter(|line| !line.is_empty()))
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn len(&self) -> usize {
+        self.strs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strs.is_empty()
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&str> {
+        self.strs.get(idx).map(String::as_str)
+    }
+
+    pub fn index_of(&self, value: &str) -> Option<usize> {
+        self.map.get(value).copied()
+    }
+}
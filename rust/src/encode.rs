@@ -1,7 +1,13 @@
 use std::io::Write;
-use std::mem;
 
-use crate::{get_type_by_value, Buffer, RpResult, Value, TYPE_STR_IDX, TYPE_VARINT, TYPE_FLOAT, TYPE_DOUBLE};
+use crate::buffer::MAX_SESSION_STRS;
+use crate::checksum::write_frame;
+use crate::compress::write_body;
+use crate::pool::scratch;
+use crate::seal::write_sealed;
+#[cfg(feature = "sign")]
+use crate::sign::write_signed;
+use crate::{get_type_by_value, Buffer, BufferPool, RpResult, Value, TYPE_STR, TYPE_STR_IDX, TYPE_VARINT, TYPE_FLOAT, TYPE_DOUBLE};
 
 #[inline(always)]
 fn append_and_align(buffer: &mut Buffer, val: &[u8]) -> RpResult<()> {
@@ -9,19 +15,19 @@ fn append_and_align(buffer: &mut Buffer, val: &[u8]) -> RpResult<()> {
         0 => 0,
         val => 2 - val,
     };
-    buffer.write(val)?;
+    buffer.write_all(val)?;
     Ok(())
 }
 
 #[inline(always)]
 pub fn encode_sure_type(buffer: &mut Buffer, value: u8) -> RpResult<()> {
-    buffer.write(unsafe { &mem::transmute::<u8, [u8; 1]>(value as u8) })?;
+    buffer.write_all(&[value])?;
     Ok(())
 }
 
 #[inline(always)]
 pub fn encode_type(buffer: &mut Buffer, value: &Value) -> RpResult<()> {
-    buffer.write(unsafe { &mem::transmute::<u8, [u8; 1]>(get_type_by_value(value) as u8) })?;
+    buffer.write_all(&[get_type_by_value(value)])?;
     Ok(())
 }
 
@@ -29,7 +35,7 @@ pub fn encode_type(buffer: &mut Buffer, value: &Value) -> RpResult<()> {
 pub fn encode_bool(buffer: &mut Buffer, value: &Value) -> RpResult<()> {
     match *value {
         Value::Bool(val) => {
-            buffer.write(unsafe { &mem::transmute::<u8, [u8; 1]>(if val { 1 } else { 0 }) })?;
+            buffer.write_all(&[u8::from(val)])?;
         }
         _ => unreachable!("encode_number only"),
     }
@@ -40,36 +46,36 @@ pub fn encode_bool(buffer: &mut Buffer, value: &Value) -> RpResult<()> {
 pub fn encode_number(buffer: &mut Buffer, value: &Value) -> RpResult<()> {
     match *value {
         Value::U8(val) => {
-            buffer.write(unsafe { &mem::transmute::<u8, [u8; 1]>(val) })?;
+            buffer.write_all(&val.to_le_bytes())?;
         }
         Value::I8(val) => {
-            buffer.write(unsafe { &mem::transmute::<i8, [u8; 1]>(val) })?;
+            buffer.write_all(&val.to_le_bytes())?;
         }
         Value::U16(val) => {
-            buffer.write(unsafe { &mem::transmute::<u16, [u8; 2]>(val.to_le()) })?;
+            buffer.write_all(&val.to_le_bytes())?;
         }
         Value::I16(val) => {
-            buffer.write(unsafe { &mem::transmute::<i16, [u8; 2]>(val.to_le()) })?;
+            buffer.write_all(&val.to_le_bytes())?;
         }
         Value::U32(val) => {
-            buffer.write(unsafe { &mem::transmute::<u32, [u8; 4]>(val.to_le()) })?;
+            buffer.write_all(&val.to_le_bytes())?;
         }
         Value::I32(val) => {
-            buffer.write(unsafe { &mem::transmute::<i32, [u8; 4]>(val.to_le()) })?;
+            buffer.write_all(&val.to_le_bytes())?;
         }
         Value::U64(val) => {
-            buffer.write(unsafe { &mem::transmute::<u64, [u8; 8]>(val.to_le()) })?;
+            buffer.write_all(&val.to_le_bytes())?;
         }
         Value::I64(val) => {
-            buffer.write(unsafe { &mem::transmute::<i64, [u8; 8]>(val.to_le()) })?;
+            buffer.write_all(&val.to_le_bytes())?;
         }
         Value::Float(val) => {
             let val = (val * 1000.0) as i32;
-            buffer.write(unsafe { &mem::transmute::<i32, [u8; 4]>(val.to_le()) })?;
+            buffer.write_all(&val.to_le_bytes())?;
         }
         Value::Double(val) => {
             let val = (val * 1000000.0) as i64;
-            buffer.write(unsafe { &mem::transmute::<i64, [u8; 8]>(val.to_le()) })?;
+            buffer.write_all(&val.to_le_bytes())?;
         }
         _ => unreachable!("encode_number only"),
     }
@@ -101,18 +107,23 @@ pub fn encode_varint(buffer: &mut Buffer, value: &Value) -> RpResult<()> {
             val as i64
         }
         Value::I64(val) => {
-            val as i64
+            val
         }
         Value::Varint(val) => {
-            val as i64
+            val
+        }
+        // rounded, a decoded float then encodes to the same number again
+        Value::Float(val) if buffer.is_canonical() => {
+            (val * 1000.0).round() as i64
+        }
+        Value::Double(val) if buffer.is_canonical() => {
+            (val * 1000000.0).round() as i64
         }
         Value::Float(val) => {
-            let val = (val * 1000.0) as i64;
-            val as i64
+            (val * 1000.0) as i64
         }
         Value::Double(val) => {
-            let val = (val * 1000000.0) as i64;
-            val as i64
+            (val * 1000000.0) as i64
         }
         _ => unreachable!("encode_number only"),
     };
@@ -123,12 +134,12 @@ pub fn encode_varint(buffer: &mut Buffer, value: &Value) -> RpResult<()> {
     };
     loop {
         let data = (real & 0x7F) as u8;
-        real = real >> 7;
+        real >>= 7;
         if real == 0 {
-            buffer.write(&[data])?;
+            buffer.write_all(&[data])?;
             break;
         } else {
-            buffer.write(&[data | 0x80])?;
+            buffer.write_all(&[data | 0x80])?;
         }
     }
     Ok(())
@@ -136,9 +147,16 @@ pub fn encode_varint(buffer: &mut Buffer, value: &Value) -> RpResult<()> {
 
 #[inline(always)]
 pub fn encode_str_idx(buffer: &mut Buffer, pattern: &str) -> RpResult<()> {
-    let idx = buffer.add_str(pattern.to_string());
+    let idx = match buffer.try_add_str(pattern.to_string()) {
+        Some(idx) => idx,
+        // the table is full, the string goes as it is
+        None => {
+            encode_sure_type(buffer, TYPE_STR)?;
+            return encode_str_raw(buffer, &Value::Str(pattern.to_string()));
+        }
+    };
     encode_sure_type(buffer, TYPE_STR_IDX)?;
-    encode_varint(buffer, &Value::U16(idx))?;
+    encode_varint(buffer, &Value::U64(idx as u64))?;
     Ok(())
 }
 
@@ -146,22 +164,99 @@ pub fn encode_str_idx(buffer: &mut Buffer, pattern: &str) -> RpResult<()> {
 pub fn encode_str_raw(buffer: &mut Buffer, value: &Value) -> RpResult<()> {
     match *value {
         Value::Str(ref val) => {
-            encode_varint(buffer, &Value::U16(val.as_bytes().len() as u16))?;
-            append_and_align(buffer, &val.as_bytes()[..])?;
+            encode_varint(buffer, &Value::U64(val.len() as u64))?;
+            append_and_align(buffer, val.as_bytes())?;
         }
         Value::Raw(ref val) => {
-            encode_varint(buffer, &Value::U16(val.len() as u16))?;
-            append_and_align(buffer, &val[..])?;
+            encode_varint(buffer, &Value::U64(val.len() as u64))?;
+            append_and_align(buffer, val)?;
         }
         _ => unreachable!("encode_str_raw only"),
     }
     Ok(())
 }
 
+/// Bytes that order map keys the same way every time, strings are written
+/// out instead of going to the string table. Integers the wire turns into
+/// varints are keyed as one, as that is what decoding gives back.
+fn canonical_key(out: &mut Vec<u8>, value: &Value) {
+    let varint = match *value {
+        Value::U16(val) => Some(val as i64),
+        Value::I16(val) => Some(val as i64),
+        Value::U32(val) => Some(val as i64),
+        Value::I32(val) => Some(val as i64),
+        Value::U64(val) => Some(val as i64),
+        Value::I64(val) | Value::Varint(val) => Some(val),
+        _ => None,
+    };
+    if let Some(val) = varint {
+        out.push(TYPE_VARINT);
+        out.extend_from_slice(&val.to_be_bytes());
+        return;
+    }
+    out.push(get_type_by_value(value));
+    match value {
+        Value::Nil => {}
+        Value::Bool(val) => out.push(u8::from(*val)),
+        Value::U8(val) => out.push(*val),
+        Value::I8(val) => out.push(*val as u8),
+        // the bits tell apart keys that round to the same number
+        Value::Float(val) => {
+            out.extend_from_slice(&((val * 1000.0).round() as i64).to_be_bytes());
+            out.extend_from_slice(&val.to_bits().to_be_bytes());
+        }
+        Value::Double(val) => {
+            out.extend_from_slice(&((val * 1000000.0).round() as i64).to_be_bytes());
+            out.extend_from_slice(&val.to_bits().to_be_bytes());
+        }
+        Value::Str(val) => {
+            out.extend_from_slice(&(val.len() as u64).to_be_bytes());
+            out.extend_from_slice(val.as_bytes());
+        }
+        Value::Raw(val) => {
+            out.extend_from_slice(&(val.len() as u64).to_be_bytes());
+            out.extend_from_slice(val);
+        }
+        Value::Arr(val) => {
+            out.extend_from_slice(&(val.len() as u64).to_be_bytes());
+            for sub in val {
+                canonical_key(out, sub);
+            }
+        }
+        Value::Map(val) => {
+            let mut entries: Vec<Vec<u8>> = val.iter().map(|(name, sub_value)| {
+                let mut entry = Vec::new();
+                canonical_key(&mut entry, name);
+                canonical_key(&mut entry, sub_value);
+                entry
+            }).collect();
+            entries.sort();
+            out.extend_from_slice(&(val.len() as u64).to_be_bytes());
+            for entry in entries {
+                out.extend_from_slice(&entry);
+            }
+        }
+        _ => unreachable!("varints are keyed above"),
+    }
+}
+
 pub fn encode_map(buffer: &mut Buffer, value: &Value) -> RpResult<()> {
     match *value {
+        Value::Map(ref val) if buffer.is_canonical() => {
+            encode_varint(buffer, &Value::U64(val.len() as u64))?;
+            let mut entries: Vec<(Vec<u8>, &Value, &Value)> = val.iter().map(|(name, sub_value)| {
+                let mut key = Vec::new();
+                canonical_key(&mut key, name);
+                (key, name, sub_value)
+            }).collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            for (_, name, sub_value) in entries {
+                encode_field(buffer, name)?;
+                encode_field(buffer, sub_value)?;
+            }
+        }
         Value::Map(ref val) => {
-            encode_varint(buffer, &Value::from(val.len() as u16))?;
+            encode_varint(buffer, &Value::U64(val.len() as u64))?;
             for (name, sub_value) in val {
                 encode_field(buffer, name)?;
                 encode_field(buffer, sub_value)?;
@@ -173,7 +268,7 @@ pub fn encode_map(buffer: &mut Buffer, value: &Value) -> RpResult<()> {
 }
 
 pub fn encode_field(buffer: &mut Buffer, value: &Value) -> RpResult<()> {
-    match &*value {
+    match value {
         Value::Bool(_) => {
             encode_type(buffer, value)?;
             encode_bool(buffer, value)?;
@@ -210,7 +305,7 @@ pub fn encode_field(buffer: &mut Buffer, value: &Value) -> RpResult<()> {
         }
         Value::Arr(ref val) => {
             encode_type(buffer, value)?;
-            encode_varint(buffer, &Value::from(val.len() as u16))?;
+            encode_varint(buffer, &Value::U64(val.len() as u64))?;
             for v in val {
                 encode_field(buffer, v)?;
             }
@@ -226,12 +321,97 @@ pub fn encode_field(buffer: &mut Buffer, value: &Value) -> RpResult<()> {
     Ok(())
 }
 
+/// the version of the string dictionary as a negative number, see `StrDict`
+fn encode_dict_version(buffer: &mut Buffer) -> RpResult<()> {
+    if let Some(version) = buffer.get_str_dict().map(|dict| dict.version()) {
+        encode_varint(buffer, &Value::Varint(-1 - i64::from(version)))?;
+    }
+    Ok(())
+}
+
+/// In session mode (see `Buffer::set_str_session`) the string table is
+/// written as the number of strings the peer already has, 0 to start over,
+/// followed by the new strings only. Nothing is written when it fails.
+#[allow(clippy::ptr_arg)]
 pub fn encode_proto(buffer: &mut Buffer, name: &String, infos: Vec<Value>) -> RpResult<()> {
-    let mut sub_buffer = Buffer::new();
+    encode_proto_in(buffer, name, infos, None)
+}
+
+/// `encode_proto` that takes the buffers it needs while encoding from `pool`
+/// instead of allocating new ones.
+pub fn encode_proto_with_pool(buffer: &mut Buffer, name: &str, infos: Vec<Value>, pool: &BufferPool) -> RpResult<()> {
+    encode_proto_in(buffer, name, infos, Some(pool))
+}
+
+fn encode_proto_in(buffer: &mut Buffer, name: &str, infos: Vec<Value>, pool: Option<&BufferPool>) -> RpResult<()> {
+    // writing may move the unread data to the front, keep the length instead of `wpos`
+    let len = buffer.data_len();
+    let strs = buffer.str_arr.len();
+    let framed = buffer.get_seal().is_some() || buffer.is_signed() || buffer.get_checksum().is_some();
+    let result = if framed {
+        encode_proto_frame(buffer, name, infos, pool)
+    } else {
+        encode_proto_name(buffer, name, infos, pool)
+    };
+    if result.is_err() {
+        buffer.set_wpos(buffer.get_rpos() + len);
+        // the peer never sees the strings of this message, forget them again
+        buffer.truncate_str(strs);
+    }
+    result
+}
+
+fn encode_proto_frame(buffer: &mut Buffer, name: &str, infos: Vec<Value>, pool: Option<&BufferPool>) -> RpResult<()> {
+    let mut proto = scratch(pool);
+    proto.set_compress(buffer.get_compress().copied());
+    proto.set_canonical(buffer.is_canonical());
+    proto.swap_str_state(buffer);
+    let result = encode_proto_name(&mut proto, name, infos, pool);
+    proto.swap_str_state(buffer);
+    result?;
+    if let Some(mut key) = buffer.get_seal().cloned() {
+        // the counter only moves on once the frame is written
+        write_sealed(buffer, proto.get_write_data(), &mut key)?;
+        buffer.set_seal(Some(key));
+        return Ok(());
+    }
+    #[cfg(feature = "sign")]
+    if let Some(keys) = buffer.get_sign_keys().cloned() {
+        return write_signed(buffer, proto.get_write_data(), &keys);
+    }
+    match buffer.get_checksum() {
+        Some(checksum) => write_frame(buffer, proto.get_write_data(), checksum),
+        None => Ok(()),
+    }
+}
+
+fn encode_proto_name(buffer: &mut Buffer, name: &str, infos: Vec<Value>, pool: Option<&BufferPool>) -> RpResult<()> {
+    encode_str_raw(buffer, &Value::Str(name.to_string()))?;
+    let config = match buffer.get_compress() {
+        Some(config) => *config,
+        None => return encode_proto_body(buffer, infos, pool),
+    };
+    // the body goes to its own buffer to be compressed as a whole
+    let mut body = scratch(pool);
+    body.set_canonical(buffer.is_canonical());
+    body.swap_str_state(buffer);
+    let result = encode_proto_body(&mut body, infos, pool);
+    body.swap_str_state(buffer);
+    result?;
+    write_body(buffer, body.get_write_data(), &config)
+}
+
+fn encode_proto_body(buffer: &mut Buffer, infos: Vec<Value>, pool: Option<&BufferPool>) -> RpResult<()> {
+    if buffer.is_str_session() {
+        return encode_proto_session(buffer, infos, pool);
+    }
+    let mut sub_buffer = scratch(pool);
+    sub_buffer.set_canonical(buffer.is_canonical());
+    sub_buffer.set_str_dict(buffer.get_str_dict().cloned());
     encode_field(&mut sub_buffer, &Value::from(infos))?;
 
-    encode_str_raw(buffer, &Value::Str(name.clone()))?;
-    encode_varint(buffer, &Value::U16(sub_buffer.str_arr.len() as u16))?;
+    encode_dict_version(buffer)?;
+    encode_varint(buffer, &Value::U64(sub_buffer.str_arr.len() as u64))?;
     for v in &sub_buffer.str_arr {
         encode_str_raw(buffer, &Value::Str(v.to_string()))?;
     }
@@ -239,3 +419,31 @@ pub fn encode_proto(buffer: &mut Buffer, name: &String, infos: Vec<Value>) -> Rp
     buffer.extend(&sub_buffer)?;
     Ok(())
 }
+
+fn encode_proto_session(buffer: &mut Buffer, infos: Vec<Value>, pool: Option<&BufferPool>) -> RpResult<()> {
+    if buffer.str_base() + buffer.str_arr.len() >= MAX_SESSION_STRS {
+        buffer.clear_str();
+    }
+    let base = buffer.str_arr.len();
+    // encode against the session table, it comes back with the new strings
+    let mut sub_buffer = scratch(pool);
+    sub_buffer.set_canonical(buffer.is_canonical());
+    sub_buffer.set_str_dict(buffer.get_str_dict().cloned());
+    std::mem::swap(&mut sub_buffer.str_arr, &mut buffer.str_arr);
+    std::mem::swap(&mut sub_buffer.str_map, &mut buffer.str_map);
+    let result = encode_field(&mut sub_buffer, &Value::from(infos));
+    std::mem::swap(&mut sub_buffer.str_arr, &mut buffer.str_arr);
+    std::mem::swap(&mut sub_buffer.str_map, &mut buffer.str_map);
+    result?;
+
+    encode_dict_version(buffer)?;
+    encode_varint(buffer, &Value::U64(base as u64))?;
+    encode_varint(buffer, &Value::U64((buffer.str_arr.len() - base) as u64))?;
+    for i in base..buffer.str_arr.len() {
+        let v = Value::Str(buffer.str_arr[i].clone());
+        encode_str_raw(buffer, &v)?;
+    }
+
+    buffer.extend(&sub_buffer)?;
+    Ok(())
+}
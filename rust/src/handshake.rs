@@ -0,0 +1,224 @@
+//! Key exchange that gives both peers the `SealKey`s of a connection, with
+//! the `handshake` feature.
+//!
+//! The client knows the server's static X25519 public key beforehand. It
+//! sends `tunm_hello` with the handshake version, the cipher and its
+//! ephemeral public key, the server answers `tunm_welcome` with its own
+//! ephemeral public key and a confirmation. Both are plain tunm protos.
+//! The keys come from HKDF-SHA256 over the ephemeral-ephemeral and the
+//! ephemeral-static shared secrets, salted with a transcript hash of the
+//! server static key and everything sent, so only the holder of the static
+//! secret can confirm and a changed hello gives other keys.
+
+use rand_core::OsRng;
+use sha2::{Digest, Sha256};
+use x25519_dalek::{EphemeralSecret, PublicKey, SharedSecret, StaticSecret};
+
+use crate::{decode_proto, encode_proto, Buffer, Cipher, ErrorKind, RpResult, SealKey, Value};
+
+pub const HANDSHAKE_VERSION: u8 = 1;
+pub const HELLO_PROTO: &str = "tunm_hello";
+pub const WELCOME_PROTO: &str = "tunm_welcome";
+
+/// The keys of both directions for one side of the connection.
+#[derive(Debug, Clone)]
+pub struct SessionKeys {
+    pub send: SealKey,
+    pub recv: SealKey,
+}
+
+impl SessionKeys {
+    /// seal what `send` encodes and open what `recv` decodes
+    pub fn install(self, send: &mut Buffer, recv: &mut Buffer) {
+        send.set_seal(Some(self.send));
+        recv.set_seal(Some(self.recv));
+    }
+}
+
+fn cipher_name(cipher: Cipher) -> &'static str {
+    match cipher {
+        #[cfg(feature = "chacha20poly1305")]
+        Cipher::ChaCha20Poly1305 => "chacha20poly1305",
+        #[cfg(feature = "aes-gcm")]
+        Cipher::Aes256Gcm => "aes-256-gcm",
+    }
+}
+
+fn cipher_by_name(name: &str) -> RpResult<Cipher> {
+    match name {
+        #[cfg(feature = "chacha20poly1305")]
+        "chacha20poly1305" => Ok(Cipher::ChaCha20Poly1305),
+        #[cfg(feature = "aes-gcm")]
+        "aes-256-gcm" => Ok(Cipher::Aes256Gcm),
+        _ => fail!((ErrorKind::ParseError, "unknown cipher", name.to_string())),
+    }
+}
+
+fn public_key(value: &Value) -> RpResult<PublicKey> {
+    match value {
+        Value::Raw(bytes) if bytes.len() == 32 => {
+            let mut key = [0; 32];
+            key.copy_from_slice(bytes);
+            Ok(PublicKey::from(key))
+        }
+        _ => fail!((ErrorKind::ParseError, "bad handshake key")),
+    }
+}
+
+fn contributory(shared: SharedSecret) -> RpResult<SharedSecret> {
+    // a low order point from the peer gives a secret anyone knows
+    if !shared.was_contributory() {
+        fail!((ErrorKind::AuthenticationFailed, "weak handshake key"));
+    }
+    Ok(shared)
+}
+
+/// read the next proto and check it is `name`
+fn read_proto(buffer: &mut Buffer, name: &str) -> RpResult<Vec<Value>> {
+    let (got, args) = decode_proto(buffer)?;
+    if got != name {
+        fail!((ErrorKind::ParseError, "unexpected handshake proto", got));
+    }
+    Ok(args)
+}
+
+/// the confirmation and the client to server and server to client keys
+fn derive(server: &PublicKey, cipher: Cipher, client_eph: &PublicKey, server_eph: &PublicKey,
+          eph_eph: SharedSecret, eph_static: SharedSecret) -> ([u8; 32], [u8; 32], [u8; 32]) {
+    let mut transcript = Sha256::new();
+    transcript.update(b"tunm handshake");
+    transcript.update([HANDSHAKE_VERSION]);
+    transcript.update(server.as_bytes());
+    transcript.update(cipher_name(cipher));
+    transcript.update(client_eph.as_bytes());
+    transcript.update(server_eph.as_bytes());
+    let salt = transcript.finalize();
+
+    let mut ikm = [0; 64];
+    ikm[..32].copy_from_slice(eph_eph.as_bytes());
+    ikm[32..].copy_from_slice(eph_static.as_bytes());
+    let hkdf = hkdf::Hkdf::<Sha256>::new(Some(&salt), &ikm);
+    let (mut confirm, mut c2s, mut s2c) = ([0; 32], [0; 32], [0; 32]);
+    // 32 bytes are always a valid HKDF-SHA256 output length
+    hkdf.expand(b"tunm confirm", &mut confirm).unwrap();
+    hkdf.expand(b"tunm client to server", &mut c2s).unwrap();
+    hkdf.expand(b"tunm server to client", &mut s2c).unwrap();
+    (confirm, c2s, s2c)
+}
+
+/// compare without stopping at the first difference
+fn same_bytes(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+enum ClientState {
+    Start,
+    // the ephemeral secret takes part in two exchanges, `EphemeralSecret`
+    // only allows one
+    Hello(StaticSecret, PublicKey),
+    Done,
+}
+
+/// The client side: `hello` writes the first proto, `welcome` reads the
+/// server's answer and gives the keys.
+pub struct ClientHandshake {
+    cipher: Cipher,
+    server: PublicKey,
+    state: ClientState,
+}
+
+impl ClientHandshake {
+    /// `server` is the static public key of the server the client trusts
+    pub fn new(cipher: Cipher, server: [u8; 32]) -> ClientHandshake {
+        ClientHandshake { cipher, server: PublicKey::from(server), state: ClientState::Start }
+    }
+
+    pub fn hello(&mut self, buffer: &mut Buffer) -> RpResult<()> {
+        if !matches!(self.state, ClientState::Start) {
+            fail!((ErrorKind::ParseError, "handshake already started"));
+        }
+        let secret = StaticSecret::random_from_rng(OsRng);
+        let public = PublicKey::from(&secret);
+        encode_proto(buffer, &HELLO_PROTO.to_string(), vec![Value::U8(HANDSHAKE_VERSION),
+                                               Value::from(cipher_name(self.cipher)),
+                                               Value::Raw(public.as_bytes().to_vec())])?;
+        self.state = ClientState::Hello(secret, public);
+        Ok(())
+    }
+
+    /// Fails with `ErrorKind::AuthenticationFailed` when the server does not
+    /// hold the static secret. A welcome that is not complete yet fails with
+    /// `ErrorKind::NoLeftSpaceError` and can be read again once the rest
+    /// came, any other error ends the handshake.
+    pub fn welcome(&mut self, buffer: &mut Buffer) -> RpResult<SessionKeys> {
+        if !matches!(self.state, ClientState::Hello(..)) {
+            fail!((ErrorKind::ParseError, "handshake not started"));
+        }
+        let args = match read_proto(buffer, WELCOME_PROTO) {
+            Err(err) if err.kind() == ErrorKind::NoLeftSpaceError => return Err(err),
+            result => result,
+        };
+        let (secret, public) = match std::mem::replace(&mut self.state, ClientState::Done) {
+            ClientState::Hello(secret, public) => (secret, public),
+            _ => unreachable!(),
+        };
+        let args = args?;
+        let (server_eph, confirm) = match &args[..] {
+            [server_eph, Value::Raw(confirm)] => (public_key(server_eph)?, confirm),
+            _ => fail!((ErrorKind::ParseError, "bad handshake proto", WELCOME_PROTO.to_string())),
+        };
+        let eph_eph = contributory(secret.diffie_hellman(&server_eph))?;
+        let eph_static = contributory(secret.diffie_hellman(&self.server))?;
+        let (expect, c2s, s2c) = derive(&self.server, self.cipher, &public, &server_eph, eph_eph, eph_static);
+        if !same_bytes(confirm, &expect) {
+            fail!((ErrorKind::AuthenticationFailed, "server not verified"));
+        }
+        Ok(SessionKeys { send: SealKey::new(self.cipher, c2s), recv: SealKey::new(self.cipher, s2c) })
+    }
+}
+
+/// The server side, one for all connections: `accept` reads a hello and
+/// writes the welcome.
+pub struct ServerHandshake {
+    secret: StaticSecret,
+    public: PublicKey,
+}
+
+impl ServerHandshake {
+    pub fn new(secret: [u8; 32]) -> ServerHandshake {
+        let secret = StaticSecret::from(secret);
+        ServerHandshake { public: PublicKey::from(&secret), secret }
+    }
+
+    /// a server with a new random static key
+    pub fn generate() -> ServerHandshake {
+        ServerHandshake::new(StaticSecret::random_from_rng(OsRng).to_bytes())
+    }
+
+    /// what the clients must know beforehand
+    pub fn public_key(&self) -> [u8; 32] {
+        self.public.to_bytes()
+    }
+
+    pub fn accept(&self, input: &mut Buffer, output: &mut Buffer) -> RpResult<SessionKeys> {
+        let args = read_proto(input, HELLO_PROTO)?;
+        let (version, cipher, client_eph) = match &args[..] {
+            [Value::U8(version), Value::Str(cipher), client_eph] => (*version, cipher, public_key(client_eph)?),
+            _ => fail!((ErrorKind::ParseError, "bad handshake proto", HELLO_PROTO.to_string())),
+        };
+        if version != HANDSHAKE_VERSION {
+            fail!((ErrorKind::ParseError, "handshake version not match",
+                   format!("got {}, expect {}", version, HANDSHAKE_VERSION)));
+        }
+        let cipher = cipher_by_name(cipher)?;
+
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let public = PublicKey::from(&secret);
+        let eph_eph = contributory(secret.diffie_hellman(&client_eph))?;
+        let eph_static = contributory(self.secret.diffie_hellman(&client_eph))?;
+        let (confirm, c2s, s2c) = derive(&self.public, cipher, &client_eph, &public, eph_eph, eph_static);
+        encode_proto(output, &WELCOME_PROTO.to_string(), vec![Value::Raw(public.as_bytes().to_vec()),
+                                                 Value::Raw(confirm.to_vec())])?;
+        Ok(SessionKeys { send: SealKey::new(cipher, s2c), recv: SealKey::new(cipher, c2s) })
+    }
+}
@@ -2,11 +2,52 @@
 pub mod macros;
 pub mod values;
 pub mod buffer;
+pub mod pool;
+#[cfg(feature = "bytes")]
+pub mod buf;
+pub mod dict;
+pub mod compress;
+pub mod checksum;
+pub mod seal;
+#[cfg(feature = "sign")]
+pub mod sign;
 pub mod encode;
 pub mod decode;
+#[cfg(feature = "codegen")]
+pub mod codegen;
+pub mod text;
+pub mod convert;
+pub mod path;
+pub mod patch;
+pub mod merge;
+#[cfg(feature = "handshake")]
+pub mod handshake;
+#[cfg(feature = "tokio")]
+pub mod async_io;
+#[cfg(any(feature = "proptest", feature = "arbitrary"))]
+pub mod strategy;
 
 pub use values::*;
-pub use buffer::Buffer;
-pub use encode::{encode_proto, encode_field, encode_number, encode_varint, encode_map, encode_type, encode_sure_type, 
+pub use buffer::{Buffer, BufferConfig};
+pub use pool::{BufferPool, PooledBuffer};
+pub use dict::StrDict;
+pub use compress::{Compression, CompressConfig};
+pub use checksum::Checksum;
+pub use seal::{Cipher, SealKey};
+#[cfg(feature = "sign")]
+pub use sign::SignKeys;
+pub use text::parse_value;
+pub use convert::{TunmEncode, TunmDecode};
+pub use patch::{Patch, PatchOp};
+pub use merge::{ArrayMerge, ConflictMerge, MergeOptions};
+#[cfg(feature = "bytes")]
+pub use buf::{encode_proto_buf, decode_proto_buf};
+#[cfg(feature = "tokio")]
+pub use async_io::{read_proto, write_proto, write_proto_with};
+#[cfg(feature = "handshake")]
+pub use handshake::{ClientHandshake, ServerHandshake, SessionKeys};
+#[cfg(feature = "derive")]
+pub use tunm_derive::{TunmEncode, TunmDecode};
+pub use encode::{encode_proto, encode_proto_with_pool, encode_field, encode_number, encode_varint, encode_map, encode_type, encode_sure_type, 
                  encode_str_raw};
 pub use decode::{decode_proto, decode_field, decode_number, decode_varint, decode_map, decode_type, decode_str_raw};
@@ -15,3 +15,105 @@ macro_rules! try_read {
         }
     )
 }
+
+/// Build a `Value` from a json like literal, the variant follows the Rust type
+/// of every leaf: `1u16` is `U16`, an unsuffixed `1` is `I32`, `2.5` is
+/// `Double` and `2.5f32` `Float`, `"a"` is `Str` and `b"a"` `Raw`. Map keys
+/// must be a single token, wrap longer expressions in parentheses.
+///
+/// ```
+/// # #[macro_use] extern crate tunm_proto;
+/// # fn main() {
+/// let id = 7u32;
+/// let value = value!({"name": "tunm", "id": id, "tags": ["a", "b"], "extra": nil});
+/// # }
+/// ```
+#[macro_export]
+macro_rules! value {
+    (nil) => {
+        $crate::Value::Nil
+    };
+    ([]) => {
+        $crate::Value::Arr(::std::vec::Vec::new())
+    };
+    ([ $($tt:tt)+ ]) => {
+        $crate::Value::Arr($crate::__value_arr!([] $($tt)+))
+    };
+    ({}) => {
+        $crate::Value::Map(::std::collections::HashMap::new())
+    };
+    ({ $($tt:tt)+ }) => {{
+        let mut map = ::std::collections::HashMap::new();
+        $crate::__value_map!(map $($tt)+);
+        $crate::Value::Map(map)
+    }};
+    ($other:expr) => {
+        $crate::Value::from($other)
+    };
+}
+
+/// The argument list of `encode_proto`, every argument as in `value!`.
+#[macro_export]
+macro_rules! tunm_args {
+    ($($tt:tt)*) => {
+        $crate::__value_arr!([] $($tt)*)
+    };
+}
+
+/// A `(name, args)` pair as `decode_proto` returns it.
+///
+/// ```
+/// # #[macro_use] extern crate tunm_proto;
+/// # fn main() {
+/// let (name, args) = proto!("cmd_test_op", ["tunm", {"id": 1u16}]);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! proto {
+    ($name:expr, [ $($tt:tt)* ] $(,)?) => {
+        (::std::string::String::from($name), $crate::__value_arr!([] $($tt)*))
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __value_arr {
+    ([ $($elems:expr,)* ]) => {{
+        let arr: ::std::vec::Vec<$crate::Value> = ::std::vec![$($elems,)*];
+        arr
+    }};
+    ([ $($elems:expr,)* ] nil $(, $($rest:tt)*)?) => {
+        $crate::__value_arr!([ $($elems,)* $crate::Value::Nil, ] $($($rest)*)?)
+    };
+    ([ $($elems:expr,)* ] [ $($arr:tt)* ] $(, $($rest:tt)*)?) => {
+        $crate::__value_arr!([ $($elems,)* $crate::value!([ $($arr)* ]), ] $($($rest)*)?)
+    };
+    ([ $($elems:expr,)* ] { $($map:tt)* } $(, $($rest:tt)*)?) => {
+        $crate::__value_arr!([ $($elems,)* $crate::value!({ $($map)* }), ] $($($rest)*)?)
+    };
+    ([ $($elems:expr,)* ] $next:expr $(, $($rest:tt)*)?) => {
+        $crate::__value_arr!([ $($elems,)* $crate::value!($next), ] $($($rest)*)?)
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __value_map {
+    ($map:ident) => {};
+    ($map:ident $key:tt : nil $(, $($rest:tt)*)?) => {
+        $map.insert($crate::value!($key), $crate::Value::Nil);
+        $crate::__value_map!($map $($($rest)*)?);
+    };
+    ($map:ident $key:tt : [ $($arr:tt)* ] $(, $($rest:tt)*)?) => {
+        $map.insert($crate::value!($key), $crate::value!([ $($arr)* ]));
+        $crate::__value_map!($map $($($rest)*)?);
+    };
+    ($map:ident $key:tt : { $($sub:tt)* } $(, $($rest:tt)*)?) => {
+        $map.insert($crate::value!($key), $crate::value!({ $($sub)* }));
+        $crate::__value_map!($map $($($rest)*)?);
+    };
+    ($map:ident $key:tt : $val:expr $(, $($rest:tt)*)?) => {
+        $map.insert($crate::value!($key), $crate::value!($val));
+        $crate::__value_map!($map $($($rest)*)?);
+    };
+}
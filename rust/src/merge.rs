@@ -0,0 +1,144 @@
+//! Deep merge of `Value` maps, for layering config such as defaults, per
+//! server overrides and live patches.
+//!
+//! `merge` walks both trees and merges maps key by key, `MergeOptions` says
+//! what happens to arrays, to values of different types and to `Nil`.
+//! `merge_patch` is the JSON merge patch of RFC 7386: maps merge, `Nil`
+//! deletes a key and everything else replaces.
+
+use std::collections::HashMap;
+
+use crate::{get_name_by_type, get_type_by_value, ErrorKind, RpResult, Value};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArrayMerge {
+    /// the new array replaces the old one
+    #[default]
+    Replace,
+    /// the new items go after the old ones
+    Append,
+    /// items at the same index merge, the longer array keeps its tail
+    ByIndex,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictMerge {
+    /// the new value replaces an old value of another type
+    #[default]
+    Override,
+    /// fail without changing anything
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeOptions {
+    pub arrays: ArrayMerge,
+    pub conflicts: ConflictMerge,
+    /// a `Nil` in the new map deletes the key instead of storing `Nil`
+    pub nil_deletes: bool,
+}
+
+impl MergeOptions {
+    /// RFC 7386 merge patch, `Nil` deletes and arrays are replaced
+    pub fn merge_patch() -> MergeOptions {
+        MergeOptions { arrays: ArrayMerge::Replace, conflicts: ConflictMerge::Override, nil_deletes: true }
+    }
+}
+
+fn is_integer(value: &Value) -> bool {
+    matches!(value, Value::U8(_) | Value::I8(_) | Value::U16(_) | Value::I16(_) | Value::U32(_)
+             | Value::I32(_) | Value::U64(_) | Value::I64(_) | Value::Varint(_))
+}
+
+/// integers of any width are one type, so are float and double
+fn same_type(old: &Value, new: &Value) -> bool {
+    match (old, new) {
+        (Value::Float(_) | Value::Double(_), Value::Float(_) | Value::Double(_)) => true,
+        _ => is_integer(old) && is_integer(new) || std::mem::discriminant(old) == std::mem::discriminant(new),
+    }
+}
+
+fn pointer(path: &[Value]) -> String {
+    path.iter().map(|key| match key {
+        Value::Str(key) => format!("/{}", key.replace('~', "~0").replace('/', "~1")),
+        key => format!("/{}", key),
+    }).collect()
+}
+
+/// the first place `new` can not merge into `old` without `Override`
+fn find_conflict(old: &Value, new: &Value, options: &MergeOptions, path: &mut Vec<Value>) -> Option<String> {
+    let nested = |path: &mut Vec<Value>, step: Value, old_val: &Value, new_val: &Value| {
+        path.push(step);
+        let conflict = find_conflict(old_val, new_val, options, path);
+        path.pop();
+        conflict
+    };
+    match (old, new) {
+        (Value::Nil, _) => None,
+        (_, Value::Nil) if options.nil_deletes => None,
+        (Value::Map(old_map), Value::Map(new_map)) => new_map.iter().find_map(|(key, new_val)| {
+            old_map.get(key).and_then(|old_val| nested(path, key.clone(), old_val, new_val))
+        }),
+        (Value::Arr(old_arr), Value::Arr(new_arr)) if options.arrays == ArrayMerge::ByIndex => {
+            old_arr.iter().zip(new_arr).enumerate().find_map(|(i, (old_val, new_val))| {
+                nested(path, Value::Varint(i as i64), old_val, new_val)
+            })
+        }
+        _ if same_type(old, new) => None,
+        _ => Some(format!("{} over {} at {}", get_name_by_type(get_type_by_value(new)),
+                          get_name_by_type(get_type_by_value(old)), pointer(path))),
+    }
+}
+
+fn merge_value(old: &mut Value, new: Value, options: &MergeOptions) {
+    match (old, new) {
+        (Value::Map(old_map), Value::Map(new_map)) => merge_map(old_map, new_map, options),
+        (Value::Arr(old_arr), Value::Arr(new_arr)) if options.arrays == ArrayMerge::Append => old_arr.extend(new_arr),
+        (Value::Arr(old_arr), Value::Arr(new_arr)) if options.arrays == ArrayMerge::ByIndex => {
+            let mut new_items = new_arr.into_iter();
+            for (old_val, new_val) in old_arr.iter_mut().zip(new_items.by_ref()) {
+                merge_value(old_val, new_val, options);
+            }
+            old_arr.extend(new_items);
+        }
+        // a map replacing something else still drops its nils
+        (old, Value::Map(new_map)) if options.nil_deletes => {
+            let mut map = HashMap::new();
+            merge_map(&mut map, new_map, options);
+            *old = Value::Map(map);
+        }
+        (old, new) => *old = new,
+    }
+}
+
+fn merge_map(old_map: &mut HashMap<Value, Value>, new_map: HashMap<Value, Value>, options: &MergeOptions) {
+    for (key, new_val) in new_map {
+        if options.nil_deletes && matches!(new_val, Value::Nil) {
+            old_map.remove(&key);
+            continue;
+        }
+        merge_value(old_map.entry(key).or_insert(Value::Nil), new_val, options);
+    }
+}
+
+impl Value {
+    /// Merge `other` into this value. Maps merge key by key at every depth,
+    /// arrays and values of another type follow `options`. With
+    /// `ConflictMerge::Error` nothing changes when the merge fails.
+    pub fn merge(&mut self, other: Value, options: &MergeOptions) -> RpResult<()> {
+        if options.conflicts == ConflictMerge::Error {
+            if let Some(detail) = find_conflict(self, &other, options, &mut Vec::new()) {
+                fail!((ErrorKind::TypeNotMatchError, "merge type conflict", detail));
+            }
+        }
+        merge_value(self, other, options);
+        Ok(())
+    }
+
+    /// Apply an RFC 7386 merge patch: a map patch merges into a map (or
+    /// replaces anything else) and deletes the keys set to `Nil`, any other
+    /// patch replaces this value.
+    pub fn merge_patch(&mut self, patch: Value) {
+        merge_value(self, patch, &MergeOptions::merge_patch());
+    }
+}
@@ -0,0 +1,315 @@
+//! Structural diff of two `Value` trees, to send the changes of a state map
+//! instead of the whole map.
+//!
+//! `Value::diff(&old, &new)` gives a `Patch` and `old.apply(&patch)` turns
+//! `old` into `new`. A path is the list of steps from the root, the key for a
+//! map and the index for an array. A patch is itself a `Value`, an array of
+//! ops, so it is sent with `encode_proto` like any other argument:
+//!
+//! - `["set", path, value]` stores `value` at `path`, inserting a map key
+//! - `["remove", path]` removes the map key or array item at `path`
+//! - `["splice", path, index, remove, [items]]` replaces `remove` items of the
+//!   array at `path` from `index` on with `items`
+//!
+//! The wire turns u16 to u64 into varints and truncates floats, diff the
+//! decoded values on both sides so they agree on what changed.
+
+use std::collections::HashMap;
+
+use crate::convert::type_mismatch;
+use crate::{ErrorKind, RpResult, TunmDecode, TunmEncode, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchOp {
+    Set { path: Vec<Value>, value: Value },
+    Remove { path: Vec<Value> },
+    Splice { path: Vec<Value>, index: usize, remove: usize, insert: Vec<Value> },
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Patch {
+    pub ops: Vec<PatchOp>,
+}
+
+impl Patch {
+    pub fn new() -> Patch {
+        Patch { ops: Vec::new() }
+    }
+
+    /// true when the two values were the same
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+/// like `==` but the variants must match too and nan equals nan, so a change
+/// from `U8(1)` to `Varint(1)` still gets into the patch
+fn same(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Float(l), Value::Float(r)) => l.to_bits() == r.to_bits(),
+        (Value::Double(l), Value::Double(r)) => l.to_bits() == r.to_bits(),
+        (Value::Arr(l), Value::Arr(r)) => l.len() == r.len() && l.iter().zip(r).all(|(l, r)| same(l, r)),
+        (Value::Map(l), Value::Map(r)) => {
+            l.len() == r.len() && l.iter().all(|(k, v)| {
+                r.get_key_value(k).is_some_and(|(rk, rv)| same(k, rk) && same(v, rv))
+            })
+        }
+        _ => std::mem::discriminant(left) == std::mem::discriminant(right) && left == right,
+    }
+}
+
+fn child_path(path: &[Value], step: Value) -> Vec<Value> {
+    let mut path = path.to_vec();
+    path.push(step);
+    path
+}
+
+fn diff_into(path: &mut Vec<Value>, old: &Value, new: &Value, ops: &mut Vec<PatchOp>) {
+    match (old, new) {
+        (Value::Map(old_map), Value::Map(new_map)) => {
+            for (key, old_val) in old_map {
+                match new_map.get_key_value(key) {
+                    Some((new_key, new_val)) if same(key, new_key) => {
+                        path.push(key.clone());
+                        diff_into(path, old_val, new_val, ops);
+                        path.pop();
+                    }
+                    _ => ops.push(PatchOp::Remove { path: child_path(path, key.clone()) }),
+                }
+            }
+            for (key, new_val) in new_map {
+                if !old_map.get_key_value(key).is_some_and(|(old_key, _)| same(old_key, key)) {
+                    ops.push(PatchOp::Set { path: child_path(path, key.clone()), value: new_val.clone() });
+                }
+            }
+        }
+        (Value::Arr(old_arr), Value::Arr(new_arr)) => {
+            let prefix = old_arr.iter().zip(new_arr).take_while(|(l, r)| same(l, r)).count();
+            let suffix = old_arr[prefix..].iter().rev().zip(new_arr[prefix..].iter().rev())
+                .take_while(|(l, r)| same(l, r)).count();
+            let old_mid = &old_arr[prefix..old_arr.len() - suffix];
+            let new_mid = &new_arr[prefix..new_arr.len() - suffix];
+            // items both sides still have are diffed in place, the rest is one splice
+            let common = old_mid.len().min(new_mid.len());
+            for (i, (old_val, new_val)) in old_mid.iter().zip(new_mid).enumerate() {
+                path.push(Value::Varint((prefix + i) as i64));
+                diff_into(path, old_val, new_val, ops);
+                path.pop();
+            }
+            if old_mid.len() != new_mid.len() {
+                ops.push(PatchOp::Splice {
+                    path: path.clone(),
+                    index: prefix + common,
+                    remove: old_mid.len() - common,
+                    insert: new_mid[common..].to_vec(),
+                });
+            }
+        }
+        _ => {
+            if !same(old, new) {
+                ops.push(PatchOp::Set { path: path.clone(), value: new.clone() });
+            }
+        }
+    }
+}
+
+fn path_text(path: &[Value]) -> String {
+    Value::Arr(path.to_vec()).to_string()
+}
+
+fn arr_index(step: &Value, path: &[Value]) -> RpResult<usize> {
+    match u64::from_value(step.clone()).ok().and_then(|idx| usize::try_from(idx).ok()) {
+        Some(idx) => Ok(idx),
+        None => fail!((ErrorKind::TypeNotMatchError, "patch path step is not an array index", path_text(path))),
+    }
+}
+
+/// the value under the key `step`, an integer step matches keys of any width and
+/// a nan key matches itself
+fn map_entry<'a>(map: &'a mut HashMap<Value, Value>, step: &Value) -> Option<&'a mut Value> {
+    if map.contains_key(step) {
+        return map.get_mut(step);
+    }
+    map.iter_mut().find(|(key, _)| *key == step || same(key, step)).map(|(_, val)| val)
+}
+
+fn map_remove(map: &mut HashMap<Value, Value>, step: &Value) -> bool {
+    if map.remove(step).is_some() {
+        return true;
+    }
+    // a nan key never equals itself, so `remove` can not find it
+    let mut found = false;
+    map.retain(|key, _| {
+        let hit = !found && (key == step || same(key, step));
+        found |= hit;
+        !hit
+    });
+    found
+}
+
+fn resolve<'a>(root: &'a mut Value, steps: &[Value], path: &[Value]) -> RpResult<&'a mut Value> {
+    let mut cur = root;
+    for step in steps {
+        cur = match cur {
+            Value::Map(map) => match map_entry(map, step) {
+                Some(val) => val,
+                None => fail!((ErrorKind::MissingError, "patch path not found", path_text(path))),
+            },
+            Value::Arr(arr) => {
+                let idx = arr_index(step, path)?;
+                match arr.get_mut(idx) {
+                    Some(val) => val,
+                    None => fail!((ErrorKind::MissingError, "patch path not found", path_text(path))),
+                }
+            }
+            _ => fail!((ErrorKind::TypeNotMatchError, "patch path runs into a scalar", path_text(path))),
+        };
+    }
+    Ok(cur)
+}
+
+fn apply_op(root: &mut Value, op: &PatchOp) -> RpResult<()> {
+    match op {
+        PatchOp::Set { path, value } => {
+            let (step, parent) = match path.split_last() {
+                Some((step, parent)) => (step, parent),
+                None => {
+                    *root = value.clone();
+                    return Ok(());
+                }
+            };
+            match resolve(root, parent, path)? {
+                Value::Map(map) => {
+                    // the exact key, a new key must not overwrite another width of the same number
+                    map.insert(step.clone(), value.clone());
+                }
+                Value::Arr(arr) => {
+                    let idx = arr_index(step, path)?;
+                    match arr.get_mut(idx) {
+                        Some(val) => *val = value.clone(),
+                        None => fail!((ErrorKind::MissingError, "patch path not found", path_text(path))),
+                    }
+                }
+                _ => fail!((ErrorKind::TypeNotMatchError, "patch path runs into a scalar", path_text(path))),
+            }
+        }
+        PatchOp::Remove { path } => {
+            let (step, parent) = match path.split_last() {
+                Some((step, parent)) => (step, parent),
+                None => fail!((ErrorKind::MissingError, "patch can not remove the root")),
+            };
+            let removed = match resolve(root, parent, path)? {
+                Value::Map(map) => map_remove(map, step),
+                Value::Arr(arr) => {
+                    let idx = arr_index(step, path)?;
+                    if idx < arr.len() {
+                        arr.remove(idx);
+                        true
+                    } else {
+                        false
+                    }
+                }
+                _ => fail!((ErrorKind::TypeNotMatchError, "patch path runs into a scalar", path_text(path))),
+            };
+            if !removed {
+                fail!((ErrorKind::MissingError, "patch path not found", path_text(path)));
+            }
+        }
+        PatchOp::Splice { path, index, remove, insert } => {
+            let arr = match resolve(root, path, path)? {
+                Value::Arr(arr) => arr,
+                other => return Err(type_mismatch("arr", other)),
+            };
+            match index.checked_add(*remove) {
+                Some(end) if end <= arr.len() => {
+                    arr.splice(*index..end, insert.iter().cloned());
+                }
+                _ => fail!((ErrorKind::MissingError, "patch splice out of range",
+                            format!("{}..+{} of {} at {}", index, remove, arr.len(), path_text(path)))),
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Value {
+    /// The ops that turn `old` into `new`, empty when they are the same.
+    pub fn diff(old: &Value, new: &Value) -> Patch {
+        let mut ops = Vec::new();
+        diff_into(&mut Vec::new(), old, new, &mut ops);
+        Patch { ops }
+    }
+
+    /// Apply the ops of `patch` in order. Fails when a path does not match
+    /// this value, the ops before the failing one stay applied.
+    pub fn apply(&mut self, patch: &Patch) -> RpResult<()> {
+        for op in &patch.ops {
+            apply_op(self, op)?;
+        }
+        Ok(())
+    }
+}
+
+impl TunmEncode for PatchOp {
+    fn to_value(&self) -> Value {
+        match self {
+            PatchOp::Set { path, value } => {
+                Value::Arr(vec![Value::from("set"), Value::Arr(path.clone()), value.clone()])
+            }
+            PatchOp::Remove { path } => Value::Arr(vec![Value::from("remove"), Value::Arr(path.clone())]),
+            PatchOp::Splice { path, index, remove, insert } => Value::Arr(vec![
+                Value::from("splice"),
+                Value::Arr(path.clone()),
+                Value::Varint(*index as i64),
+                Value::Varint(*remove as i64),
+                Value::Arr(insert.clone()),
+            ]),
+        }
+    }
+}
+
+fn next_item(items: &mut std::vec::IntoIter<Value>, what: &str) -> RpResult<Value> {
+    match items.next() {
+        Some(item) => Ok(item),
+        None => fail!((ErrorKind::MissingError, "missing item in patch op", what.to_string())),
+    }
+}
+
+impl TunmDecode for PatchOp {
+    fn from_value(value: Value) -> RpResult<Self> {
+        let mut items = Vec::<Value>::from_value(value)?.into_iter();
+        let op = String::from_value(next_item(&mut items, "op")?)?;
+        let path = Vec::<Value>::from_value(next_item(&mut items, "path")?)?;
+        let op = match op.as_str() {
+            "set" => PatchOp::Set { path, value: next_item(&mut items, "value")? },
+            "remove" => PatchOp::Remove { path },
+            "splice" => {
+                let index = u64::from_value(next_item(&mut items, "index")?)?;
+                let remove = u64::from_value(next_item(&mut items, "remove")?)?;
+                let insert = Vec::<Value>::from_value(next_item(&mut items, "insert")?)?;
+                match (usize::try_from(index), usize::try_from(remove)) {
+                    (Ok(index), Ok(remove)) => PatchOp::Splice { path, index, remove, insert },
+                    _ => fail!((ErrorKind::TypeNotMatchError, "patch splice out of range")),
+                }
+            }
+            _ => fail!((ErrorKind::TypeNotMatchError, "unknown patch op", op)),
+        };
+        if items.next().is_some() {
+            fail!((ErrorKind::TypeNotMatchError, "too many items in patch op"));
+        }
+        Ok(op)
+    }
+}
+
+/// an array of ops
+impl TunmEncode for Patch {
+    fn to_value(&self) -> Value {
+        self.ops.to_value()
+    }
+}
+
+impl TunmDecode for Patch {
+    fn from_value(value: Value) -> RpResult<Self> {
+        Ok(Patch { ops: Vec::from_value(value)? })
+    }
+}
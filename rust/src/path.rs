@@ -0,0 +1,192 @@
+//! Reading and changing nested `Value` trees without matching every level.
+//!
+//! Paths are JSON pointers (RFC 6901): `""` is the value itself, `"/a/0/b"`
+//! walks map key `"a"`, array index 0 and map key `"b"`, `~1` stands for `/`
+//! and `~0` for `~` inside a key. A map step matches a `Value::Str` key, or
+//! an integer key of any width when the step is a number.
+
+use std::collections::HashMap;
+use std::ops::{Index, IndexMut};
+
+use crate::{ErrorKind, RpResult, Value};
+
+static NIL: Value = Value::Nil;
+
+fn split_pointer(path: &str) -> Option<Vec<String>> {
+    if path.is_empty() {
+        return Some(Vec::new());
+    }
+    let rest = path.strip_prefix('/')?;
+    Some(rest.split('/').map(|token| token.replace("~1", "/").replace("~0", "~")).collect())
+}
+
+/// the key in `map` the path step `token` refers to
+fn find_key(map: &HashMap<Value, Value>, token: &str) -> Option<Value> {
+    let key = Value::Str(token.to_string());
+    if map.contains_key(&key) {
+        return Some(key);
+    }
+    map.get_key_value(&Value::Varint(token.parse().ok()?)).map(|(key, _)| key.clone())
+}
+
+fn arr_index(token: &str) -> Option<usize> {
+    // RFC 6901 has no leading zeros or signs
+    if token.is_empty() || (token.len() > 1 && token.starts_with('0')) || !token.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+impl Value {
+    /// The value under the map key `key`, `None` when this is not a map or
+    /// the key is missing.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Map(map) => map.get(&Value::Str(key.to_string())),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        match self {
+            Value::Map(map) => map.get_mut(&Value::Str(key.to_string())),
+            _ => None,
+        }
+    }
+
+    /// The array item at `idx`, `None` when this is not an array or it is too short.
+    pub fn get_index(&self, idx: usize) -> Option<&Value> {
+        match self {
+            Value::Arr(arr) => arr.get(idx),
+            _ => None,
+        }
+    }
+
+    pub fn get_index_mut(&mut self, idx: usize) -> Option<&mut Value> {
+        match self {
+            Value::Arr(arr) => arr.get_mut(idx),
+            _ => None,
+        }
+    }
+
+    /// Look up a JSON pointer such as `"/server/code_type"`.
+    pub fn pointer(&self, path: &str) -> Option<&Value> {
+        let mut cur = self;
+        for token in split_pointer(path)? {
+            cur = match cur {
+                Value::Map(map) => map.get(&find_key(map, &token)?)?,
+                Value::Arr(arr) => arr.get(arr_index(&token)?)?,
+                _ => return None,
+            };
+        }
+        Some(cur)
+    }
+
+    pub fn pointer_mut(&mut self, path: &str) -> Option<&mut Value> {
+        let mut cur = self;
+        for token in split_pointer(path)? {
+            cur = match cur {
+                Value::Map(map) => {
+                    let key = find_key(map, &token)?;
+                    map.get_mut(&key)?
+                }
+                Value::Arr(arr) => arr.get_mut(arr_index(&token)?)?,
+                _ => return None,
+            };
+        }
+        Some(cur)
+    }
+
+    /// Store `value` at the JSON pointer `path` and return what was there.
+    ///
+    /// Missing map keys on the way are created as empty maps and `Nil` turns
+    /// into a map. In an array the last step may be one past the end or `-`
+    /// to append. Fails when a step runs into a scalar or past an array.
+    pub fn set_path(&mut self, path: &str, value: Value) -> RpResult<Option<Value>> {
+        let tokens = match split_pointer(path) {
+            Some(tokens) => tokens,
+            None => fail!((ErrorKind::ParseError, "path must be empty or start with '/'", path.to_string())),
+        };
+        let mut cur = self;
+        for (i, token) in tokens.iter().enumerate() {
+            let last = i + 1 == tokens.len();
+            if let Value::Nil = cur {
+                *cur = Value::Map(HashMap::new());
+            }
+            cur = match cur {
+                Value::Map(map) => {
+                    let key = find_key(map, token).unwrap_or_else(|| Value::Str(token.clone()));
+                    if last {
+                        return Ok(map.insert(key, value));
+                    }
+                    map.entry(key).or_insert_with(|| Value::Map(HashMap::new()))
+                }
+                Value::Arr(arr) => {
+                    let idx = if token == "-" { Some(arr.len()) } else { arr_index(token) };
+                    match idx {
+                        Some(idx) if last && idx == arr.len() => {
+                            arr.push(value);
+                            return Ok(None);
+                        }
+                        Some(idx) if idx < arr.len() => {
+                            if last {
+                                return Ok(Some(std::mem::replace(&mut arr[idx], value)));
+                            }
+                            &mut arr[idx]
+                        }
+                        _ => fail!((ErrorKind::MissingError, "array index out of range",
+                                    format!("{} in {}", token, path))),
+                    }
+                }
+                _ => fail!((ErrorKind::TypeNotMatchError, "path runs into a scalar",
+                            format!("{} in {}", token, path))),
+            };
+        }
+        Ok(Some(std::mem::replace(cur, value)))
+    }
+}
+
+/// `value["key"]` is `Value::Nil` when the key is missing or this is not a map
+impl Index<&str> for Value {
+    type Output = Value;
+
+    fn index(&self, key: &str) -> &Value {
+        self.get(key).unwrap_or(&NIL)
+    }
+}
+
+/// `value[0]` is `Value::Nil` when the index is out of range or this is not an array
+impl Index<usize> for Value {
+    type Output = Value;
+
+    fn index(&self, idx: usize) -> &Value {
+        self.get_index(idx).unwrap_or(&NIL)
+    }
+}
+
+/// Inserts `Nil` for a missing key and turns `Nil` into a map first, panics
+/// on any other variant.
+impl IndexMut<&str> for Value {
+    fn index_mut(&mut self, key: &str) -> &mut Value {
+        if let Value::Nil = self {
+            *self = Value::Map(HashMap::new());
+        }
+        match self {
+            Value::Map(map) => map.entry(Value::Str(key.to_string())).or_insert(Value::Nil),
+            other => panic!("cannot index {:?} with a key", other),
+        }
+    }
+}
+
+/// Panics when this is not an array or the index is out of range.
+impl IndexMut<usize> for Value {
+    fn index_mut(&mut self, idx: usize) -> &mut Value {
+        match self {
+            Value::Arr(arr) => {
+                let len = arr.len();
+                arr.get_mut(idx).unwrap_or_else(|| panic!("index {} out of range for array of {}", idx, len))
+            }
+            other => panic!("cannot index {:?} with a number", other),
+        }
+    }
+}
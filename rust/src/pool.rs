@@ -0,0 +1,139 @@
+//! A pool of `Buffer`s shared between threads, so busy servers reuse the
+//! allocations instead of making new ones for every message.
+//!
+//! `BufferPool::get` hands out a `PooledBuffer` that goes back to the pool
+//! when it is dropped, cleared of its data, strings and settings. The pool
+//! keeps at most `max_buffers` idle buffers and never keeps one that grew
+//! past `max_capacity`. `encode_proto_with_pool` takes the buffers it needs
+//! while encoding from a pool.
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+use crate::Buffer;
+
+struct PoolInner {
+    idle: Mutex<Vec<Buffer>>,
+    max_buffers: usize,
+    max_capacity: usize,
+}
+
+#[derive(Clone)]
+pub struct BufferPool {
+    inner: Arc<PoolInner>,
+}
+
+impl BufferPool {
+    /// keep up to `max_buffers` idle buffers of at most 64k each
+    pub fn new(max_buffers: usize) -> BufferPool {
+        BufferPool::with_limits(max_buffers, 64 * 1024)
+    }
+
+    pub fn with_limits(max_buffers: usize, max_capacity: usize) -> BufferPool {
+        BufferPool {
+            inner: Arc::new(PoolInner { idle: Mutex::new(Vec::new()), max_buffers, max_capacity }),
+        }
+    }
+
+    /// an idle buffer, or a new one when there is none
+    pub fn get(&self) -> PooledBuffer {
+        let buffer = self.inner.idle().pop().unwrap_or_default();
+        PooledBuffer { buffer: Some(buffer), pool: Some(self.inner.clone()) }
+    }
+
+    /// the number of idle buffers
+    pub fn idle_len(&self) -> usize {
+        self.inner.idle().len()
+    }
+
+    pub fn max_buffers(&self) -> usize {
+        self.inner.max_buffers
+    }
+
+    pub fn max_capacity(&self) -> usize {
+        self.inner.max_capacity
+    }
+}
+
+impl PoolInner {
+    fn idle(&self) -> std::sync::MutexGuard<'_, Vec<Buffer>> {
+        // a panic while holding the lock leaves only whole buffers behind
+        self.idle.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn put(&self, mut buffer: Buffer) {
+        if buffer.len() > self.max_capacity {
+            return;
+        }
+        buffer.reset();
+        let mut idle = self.idle();
+        if idle.len() < self.max_buffers {
+            idle.push(buffer);
+        }
+    }
+}
+
+impl fmt::Debug for BufferPool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BufferPool")
+            .field("idle", &self.idle_len())
+            .field("max_buffers", &self.inner.max_buffers)
+            .field("max_capacity", &self.inner.max_capacity)
+            .finish()
+    }
+}
+
+/// A `Buffer` that goes back to its pool when dropped.
+pub struct PooledBuffer {
+    buffer: Option<Buffer>,
+    pool: Option<Arc<PoolInner>>,
+}
+
+impl PooledBuffer {
+    /// a buffer that is simply dropped, for code that may run without a pool
+    pub(crate) fn unpooled(buffer: Buffer) -> PooledBuffer {
+        PooledBuffer { buffer: Some(buffer), pool: None }
+    }
+
+    /// take the buffer out, it does not go back to the pool
+    pub fn into_inner(mut self) -> Buffer {
+        self.buffer.take().unwrap()
+    }
+}
+
+/// a buffer from `pool`, or a new one without it
+pub(crate) fn scratch(pool: Option<&BufferPool>) -> PooledBuffer {
+    match pool {
+        Some(pool) => pool.get(),
+        None => PooledBuffer::unpooled(Buffer::new()),
+    }
+}
+
+impl Deref for PooledBuffer {
+    type Target = Buffer;
+
+    fn deref(&self) -> &Buffer {
+        self.buffer.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledBuffer {
+    fn deref_mut(&mut self) -> &mut Buffer {
+        self.buffer.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        if let (Some(buffer), Some(pool)) = (self.buffer.take(), self.pool.as_ref()) {
+            pool.put(buffer);
+        }
+    }
+}
+
+impl fmt::Debug for PooledBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
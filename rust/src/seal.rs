@@ -0,0 +1,181 @@
+//! Optional authenticated encryption of every proto, ChaCha20-Poly1305 with
+//! the `chacha20poly1305` feature and AES-256-GCM with the `aes-gcm` feature.
+//!
+//! With a `SealKey` set on a `Buffer` (see `Buffer::set_seal`) each proto
+//! `encode_proto` writes becomes a sealed frame: the varint length of the
+//! ciphertext, the nonce counter as 8 little endian bytes and the ciphertext
+//! with its 16 bytes tag. The length and counter are authenticated too. A key
+//! seals one direction only, the sender counts up from 0 and the receiver
+//! only accepts the very next counter, so a replayed, dropped or reordered
+//! frame fails with `ErrorKind::AuthenticationFailed` like a forged one. A
+//! frame that is not complete yet is left in the buffer, the whole frame is
+//! consumed otherwise.
+
+use std::fmt;
+use std::io::Write;
+
+use crate::{decode_varint, encode_varint, Buffer, ErrorKind, RpResult, Value};
+
+/// the size of the tag after the ciphertext
+pub const SEAL_TAG_SIZE: usize = 16;
+
+/// `ChaCha20Poly1305` comes with the `chacha20poly1305` feature and
+/// `Aes256Gcm` with the `aes-gcm` feature
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cipher {
+    #[cfg(feature = "chacha20poly1305")]
+    ChaCha20Poly1305,
+    #[cfg(feature = "aes-gcm")]
+    Aes256Gcm,
+}
+
+/// The key of one direction and the counter of the next frame.
+#[derive(Clone)]
+pub struct SealKey {
+    cipher: Cipher,
+    #[cfg_attr(not(any(feature = "chacha20poly1305", feature = "aes-gcm")), allow(dead_code))]
+    key: [u8; 32],
+    nonce: u64,
+}
+
+// without a cipher there is no key to build
+#[cfg_attr(not(any(feature = "chacha20poly1305", feature = "aes-gcm")), allow(dead_code, unused_variables))]
+impl SealKey {
+    pub fn new(cipher: Cipher, key: [u8; 32]) -> SealKey {
+        SealKey { cipher, key, nonce: 0 }
+    }
+
+    pub fn cipher(&self) -> Cipher {
+        self.cipher
+    }
+
+    /// the counter the next frame is sealed with or expected to carry
+    pub fn nonce(&self) -> u64 {
+        self.nonce
+    }
+
+    fn nonce_bytes(&self) -> [u8; 12] {
+        let mut nonce = [0; 12];
+        nonce[4..].copy_from_slice(&self.nonce.to_le_bytes());
+        nonce
+    }
+
+    fn seal(&self, data: &[u8], aad: &[u8]) -> RpResult<Vec<u8>> {
+        match self.cipher {
+            #[cfg(feature = "chacha20poly1305")]
+            Cipher::ChaCha20Poly1305 => chacha20_seal(&self.key, &self.nonce_bytes(), data, aad),
+            #[cfg(feature = "aes-gcm")]
+            Cipher::Aes256Gcm => aes_gcm_seal(&self.key, &self.nonce_bytes(), data, aad),
+        }
+    }
+
+    fn open(&self, data: &[u8], aad: &[u8]) -> RpResult<Vec<u8>> {
+        match self.cipher {
+            #[cfg(feature = "chacha20poly1305")]
+            Cipher::ChaCha20Poly1305 => chacha20_open(&self.key, &self.nonce_bytes(), data, aad),
+            #[cfg(feature = "aes-gcm")]
+            Cipher::Aes256Gcm => aes_gcm_open(&self.key, &self.nonce_bytes(), data, aad),
+        }
+    }
+}
+
+impl fmt::Debug for SealKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // never print the key
+        f.debug_struct("SealKey").field("cipher", &self.cipher).field("nonce", &self.nonce).finish()
+    }
+}
+
+#[cfg(feature = "chacha20poly1305")]
+fn chacha20_seal(key: &[u8; 32], nonce: &[u8; 12], msg: &[u8], aad: &[u8]) -> RpResult<Vec<u8>> {
+    use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+    let cipher = chacha20poly1305::ChaCha20Poly1305::new(key.into());
+    match cipher.encrypt(nonce.into(), Payload { msg, aad }) {
+        Ok(data) => Ok(data),
+        Err(_) => fail!((ErrorKind::AuthenticationFailed, "seal failed")),
+    }
+}
+
+#[cfg(feature = "chacha20poly1305")]
+fn chacha20_open(key: &[u8; 32], nonce: &[u8; 12], msg: &[u8], aad: &[u8]) -> RpResult<Vec<u8>> {
+    use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+    let cipher = chacha20poly1305::ChaCha20Poly1305::new(key.into());
+    match cipher.decrypt(nonce.into(), Payload { msg, aad }) {
+        Ok(data) => Ok(data),
+        Err(_) => fail!((ErrorKind::AuthenticationFailed, "authentication failed")),
+    }
+}
+
+#[cfg(feature = "aes-gcm")]
+fn aes_gcm_seal(key: &[u8; 32], nonce: &[u8; 12], msg: &[u8], aad: &[u8]) -> RpResult<Vec<u8>> {
+    use aes_gcm::aead::{Aead, KeyInit, Payload};
+    let cipher = aes_gcm::Aes256Gcm::new(key.into());
+    match cipher.encrypt(nonce.into(), Payload { msg, aad }) {
+        Ok(data) => Ok(data),
+        Err(_) => fail!((ErrorKind::AuthenticationFailed, "seal failed")),
+    }
+}
+
+#[cfg(feature = "aes-gcm")]
+fn aes_gcm_open(key: &[u8; 32], nonce: &[u8; 12], msg: &[u8], aad: &[u8]) -> RpResult<Vec<u8>> {
+    use aes_gcm::aead::{Aead, KeyInit, Payload};
+    let cipher = aes_gcm::Aes256Gcm::new(key.into());
+    match cipher.decrypt(nonce.into(), Payload { msg, aad }) {
+        Ok(data) => Ok(data),
+        Err(_) => fail!((ErrorKind::AuthenticationFailed, "authentication failed")),
+    }
+}
+
+/// seal `proto` with the next counter of `key` and write the frame
+pub(crate) fn write_sealed(buffer: &mut Buffer, proto: &[u8], key: &mut SealKey) -> RpResult<()> {
+    if key.nonce == u64::MAX {
+        fail!((ErrorKind::AuthenticationFailed, "nonce exhausted"));
+    }
+    // the length varint and the counter are authenticated with the ciphertext
+    let start = buffer.data_len();
+    encode_varint(buffer, &Value::U64((proto.len() + SEAL_TAG_SIZE) as u64))?;
+    buffer.write_all(&key.nonce.to_le_bytes())?;
+    let data = key.seal(proto, &buffer.get_write_data()[start..])?;
+    buffer.write_all(&data)?;
+    key.nonce += 1;
+    Ok(())
+}
+
+/// read a frame and return the proto in it once it is authentic and in order
+pub(crate) fn read_sealed(buffer: &mut Buffer, key: &mut SealKey) -> RpResult<Vec<u8>> {
+    let rpos = buffer.get_rpos();
+    let len: i64 = match decode_varint(buffer) {
+        Ok(len) => len.into(),
+        Err(err) => {
+            buffer.set_rpos(rpos);
+            return Err(err);
+        }
+    };
+    let len = match usize::try_from(len) {
+        Ok(len) if len >= SEAL_TAG_SIZE => len,
+        _ => fail!((ErrorKind::AuthenticationFailed, "bad sealed length")),
+    };
+    if buffer.data_len() < len.saturating_add(8) {
+        // wait for the rest of the frame
+        buffer.set_rpos(rpos);
+        fail!((ErrorKind::NoLeftSpaceError, "must left space to read "));
+    }
+    let mut nonce = [0; 8];
+    try_read!(buffer.read_bytes(&mut nonce), nonce.len());
+    let nonce = u64::from_le_bytes(nonce);
+    let head_end = buffer.get_rpos();
+    let mut data = vec![0; len];
+    try_read!(buffer.read_bytes(&mut data), len);
+
+    if nonce < key.nonce {
+        fail!((ErrorKind::AuthenticationFailed, "replayed nonce",
+               format!("got {}, expect {}", nonce, key.nonce)));
+    }
+    if nonce > key.nonce {
+        fail!((ErrorKind::AuthenticationFailed, "nonce out of order",
+               format!("got {}, expect {}", nonce, key.nonce)));
+    }
+    let proto = key.open(&data, &buffer.get_data()[rpos..head_end])?;
+    key.nonce += 1;
+    Ok(proto)
+}
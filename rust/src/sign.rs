@@ -0,0 +1,145 @@
+//! Optional HMAC-SHA256 signing of every proto with the `sign` feature, for
+//! traffic between servers.
+//!
+//! With `SignKeys` set on a `Buffer` (see `Buffer::set_sign_keys`) each proto
+//! `encode_proto` writes becomes a signed frame: the varint length of the
+//! proto, the varint id of the key, the proto and the 32 bytes tag over all
+//! of them. `decode_proto` fails with `ErrorKind::AuthenticationFailed` when
+//! the key id is unknown or the tag does not match. Signed protos are always
+//! encoded canonically (see `Buffer::set_canonical`), so a proto that is
+//! decoded and encoded again with the same key gives the same frame.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+
+use crate::{decode_varint, encode_varint, Buffer, ErrorKind, RpResult, Value};
+
+/// the size of the tag after the proto
+pub const SIGN_TAG_SIZE: usize = 32;
+
+/// The key new protos are signed with and the older ones still accepted,
+/// so keys can rotate: add the new key everywhere first, then make it the
+/// current one, then remove the old one.
+#[derive(Clone)]
+pub struct SignKeys {
+    current: u32,
+    keys: HashMap<u32, Vec<u8>>,
+}
+
+impl SignKeys {
+    pub fn new<K: Into<Vec<u8>>>(id: u32, key: K) -> SignKeys {
+        let mut keys = HashMap::new();
+        keys.insert(id, key.into());
+        SignKeys { current: id, keys }
+    }
+
+    /// accept protos signed with `key`, replacing an older key of the same id
+    pub fn add<K: Into<Vec<u8>>>(&mut self, id: u32, key: K) {
+        self.keys.insert(id, key.into());
+    }
+
+    /// stop accepting `id`, the current key can not be removed
+    pub fn remove(&mut self, id: u32) -> RpResult<()> {
+        if id == self.current {
+            fail!((ErrorKind::ParseError, "current key can not be removed", id.to_string()));
+        }
+        self.keys.remove(&id);
+        Ok(())
+    }
+
+    /// sign new protos with the key of `id`, it must have been added
+    pub fn set_current(&mut self, id: u32) -> RpResult<()> {
+        if !self.keys.contains_key(&id) {
+            fail!((ErrorKind::MissingError, "unknown key id", id.to_string()));
+        }
+        self.current = id;
+        Ok(())
+    }
+
+    pub fn current(&self) -> u32 {
+        self.current
+    }
+
+    pub fn contains(&self, id: u32) -> bool {
+        self.keys.contains_key(&id)
+    }
+
+    fn key(&self, id: u32) -> RpResult<&[u8]> {
+        match self.keys.get(&id) {
+            Some(key) => Ok(key),
+            None => fail!((ErrorKind::AuthenticationFailed, "unknown key id", id.to_string())),
+        }
+    }
+}
+
+impl fmt::Debug for SignKeys {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // never print the keys
+        let mut ids: Vec<&u32> = self.keys.keys().collect();
+        ids.sort();
+        f.debug_struct("SignKeys").field("current", &self.current).field("ids", &ids).finish()
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> RpResult<hmac::Hmac<sha2::Sha256>> {
+    use hmac::Mac;
+    // any key length is fine for HMAC
+    let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(key).unwrap();
+    mac.update(data);
+    Ok(mac)
+}
+
+fn sign(key: &[u8], data: &[u8]) -> RpResult<Vec<u8>> {
+    use hmac::Mac;
+    Ok(hmac_sha256(key, data)?.finalize().into_bytes().to_vec())
+}
+
+fn verify(key: &[u8], data: &[u8], tag: &[u8]) -> RpResult<bool> {
+    use hmac::Mac;
+    // compares in constant time
+    Ok(hmac_sha256(key, data)?.verify_slice(tag).is_ok())
+}
+
+/// write `proto` as a frame signed with the current key
+pub(crate) fn write_signed(buffer: &mut Buffer, proto: &[u8], keys: &SignKeys) -> RpResult<()> {
+    let start = buffer.data_len();
+    encode_varint(buffer, &Value::U64(proto.len() as u64))?;
+    encode_varint(buffer, &Value::U32(keys.current))?;
+    buffer.write_all(proto)?;
+    let tag = sign(keys.key(keys.current)?, &buffer.get_write_data()[start..])?;
+    buffer.write_all(&tag)?;
+    Ok(())
+}
+
+/// read a frame and return the proto in it once the tag matches
+pub(crate) fn read_signed(buffer: &mut Buffer, keys: &SignKeys) -> RpResult<Vec<u8>> {
+    let rpos = buffer.get_rpos();
+    let head = decode_varint(buffer).and_then(|len| Ok((len, decode_varint(buffer)?)));
+    let (len, id): (i64, i64) = match head {
+        Ok((len, id)) => (len.into(), id.into()),
+        Err(err) => {
+            buffer.set_rpos(rpos);
+            return Err(err);
+        }
+    };
+    let (len, id) = match (usize::try_from(len), u32::try_from(id)) {
+        (Ok(len), Ok(id)) => (len, id),
+        _ => fail!((ErrorKind::AuthenticationFailed, "bad signed frame")),
+    };
+    if buffer.data_len() < len.saturating_add(SIGN_TAG_SIZE) {
+        // wait for the rest of the frame
+        buffer.set_rpos(rpos);
+        fail!((ErrorKind::NoLeftSpaceError, "must left space to read "));
+    }
+    let start = buffer.get_rpos();
+    let mut proto = vec![0; len];
+    try_read!(buffer.read_bytes(&mut proto), len);
+    let mut tag = [0; SIGN_TAG_SIZE];
+    try_read!(buffer.read_bytes(&mut tag), tag.len());
+
+    if !verify(keys.key(id)?, &buffer.get_data()[rpos..start + len], &tag)? {
+        fail!((ErrorKind::AuthenticationFailed, "bad signature", format!("key id {}", id)));
+    }
+    Ok(proto)
+}
@@ -0,0 +1,112 @@
+//! Random `Value` generation for property and fuzz tests, with the
+//! `proptest` feature for proptest strategies and the `arbitrary` feature
+//! for an `Arbitrary` impl.
+
+#[cfg(feature = "proptest")]
+pub use self::prop::{value_leaf_strategy, value_strategy};
+
+#[cfg(feature = "proptest")]
+mod prop {
+    use proptest::collection::{hash_map, vec};
+    use proptest::prelude::*;
+
+    use crate::Value;
+
+    /// strings and raw data around the old u16 length limit
+    fn long_bytes() -> impl Strategy<Value = Value> {
+        (any::<u8>(), 65_530usize..65_600, any::<bool>()).prop_map(|(b, len, is_str)| {
+            if is_str {
+                Value::Str(char::from(b & 0x7F).to_string().repeat(len))
+            } else {
+                Value::Raw(vec![b; len])
+            }
+        })
+    }
+
+    /// every non collection variant, with the integer limits weighted in
+    pub fn value_leaf_strategy() -> impl Strategy<Value = Value> {
+        prop_oneof![
+            4 => Just(Value::Nil),
+            4 => any::<bool>().prop_map(Value::Bool),
+            4 => any::<u8>().prop_map(Value::U8),
+            4 => any::<i8>().prop_map(Value::I8),
+            4 => any::<u16>().prop_map(Value::U16),
+            4 => any::<i16>().prop_map(Value::I16),
+            4 => any::<u32>().prop_map(Value::U32),
+            4 => any::<i32>().prop_map(Value::I32),
+            4 => any::<u64>().prop_map(Value::U64),
+            4 => any::<i64>().prop_map(Value::I64),
+            4 => any::<i64>().prop_map(Value::Varint),
+            2 => prop_oneof![Just(i64::MIN), Just(i64::MAX), Just(-1), Just(0)].prop_map(Value::Varint),
+            4 => any::<f32>().prop_map(Value::Float),
+            4 => any::<f64>().prop_map(Value::Double),
+            8 => any::<String>().prop_map(Value::Str),
+            4 => vec(any::<u8>(), 0..64).prop_map(Value::Raw),
+            1 => long_bytes(),
+        ]
+    }
+
+    /// nested arrays and maps of `value_leaf_strategy`, including empty ones
+    pub fn value_strategy() -> impl Strategy<Value = Value> {
+        value_leaf_strategy().prop_recursive(4, 64, 8, |inner| {
+            prop_oneof![
+                vec(inner.clone(), 0..8).prop_map(Value::Arr),
+                hash_map(value_leaf_strategy(), inner, 0..8).prop_map(Value::Map),
+            ]
+        })
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impl {
+    use std::collections::HashMap;
+
+    use arbitrary::{Arbitrary, Result, Unstructured};
+
+    use crate::Value;
+
+    const MAX_DEPTH: usize = 8;
+
+    fn arbitrary_value(u: &mut Unstructured, depth: usize) -> Result<Value> {
+        let kinds = if depth >= MAX_DEPTH { 15 } else { 17 };
+        Ok(match u.choose_index(kinds)? {
+            0 => Value::Nil,
+            1 => Value::Bool(u.arbitrary()?),
+            2 => Value::U8(u.arbitrary()?),
+            3 => Value::I8(u.arbitrary()?),
+            4 => Value::U16(u.arbitrary()?),
+            5 => Value::I16(u.arbitrary()?),
+            6 => Value::U32(u.arbitrary()?),
+            7 => Value::I32(u.arbitrary()?),
+            8 => Value::U64(u.arbitrary()?),
+            9 => Value::I64(u.arbitrary()?),
+            10 => Value::Varint(u.arbitrary()?),
+            11 => Value::Float(u.arbitrary()?),
+            12 => Value::Double(u.arbitrary()?),
+            13 => Value::Str(u.arbitrary()?),
+            14 => Value::Raw(u.arbitrary()?),
+            15 => {
+                let len = u.arbitrary_len::<u8>()?;
+                let mut arr = Vec::with_capacity(len);
+                for _ in 0..len {
+                    arr.push(arbitrary_value(u, depth + 1)?);
+                }
+                Value::Arr(arr)
+            }
+            _ => {
+                let len = u.arbitrary_len::<[u8; 2]>()?;
+                let mut map = HashMap::with_capacity(len);
+                for _ in 0..len {
+                    map.insert(arbitrary_value(u, MAX_DEPTH)?, arbitrary_value(u, depth + 1)?);
+                }
+                Value::Map(map)
+            }
+        })
+    }
+
+    impl<'a> Arbitrary<'a> for Value {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            arbitrary_value(u, 0)
+        }
+    }
+}
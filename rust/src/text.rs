@@ -0,0 +1,413 @@
+//! A text notation for `Value` that keeps the tunm types, for config files,
+//! tests and logs. `Display` writes it (`{:#}` with indentation) and
+//! `parse_value` / `str::parse` read it back.
+//!
+//! ```text
+//! # comments start with '#'
+//! {
+//!     "name": "tunm",             # str
+//!     "data": b"\x00\x01ab",      # raw, printable ascii or \xNN escapes
+//!     "count": 5,                 # varint, every bare integer
+//!     "port": u16(8080),          # u8 i8 u16 i16 u32 i32 u64 i64 varint
+//!     "scale": [2.5f, 2.5],       # float with the `f` suffix, double without
+//!     "limits": [float(inf), double(nan)],
+//!     u8(1): [nil, true, false],  # map keys can be any value
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+use crate::decode::MAX_DECODE_DEPTH;
+use crate::{ErrorKind, RpError, RpResult, Value};
+
+const INDENT: &str = "    ";
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_value(f, self, if f.alternate() { Some(0) } else { None })
+    }
+}
+
+impl FromStr for Value {
+    type Err = RpError;
+
+    fn from_str(text: &str) -> RpResult<Value> {
+        parse_value(text)
+    }
+}
+
+fn write_float<W: Write>(out: &mut W, ty: &str, val: f64, text: String, suffix: &str) -> fmt::Result {
+    if val.is_finite() {
+        out.write_str(&text)?;
+        if !text.contains(['.', 'e', 'E']) {
+            out.write_str(".0")?;
+        }
+        out.write_str(suffix)
+    } else if val.is_nan() {
+        write!(out, "{}(nan)", ty)
+    } else if val > 0.0 {
+        write!(out, "{}(inf)", ty)
+    } else {
+        write!(out, "{}(-inf)", ty)
+    }
+}
+
+fn write_str<W: Write>(out: &mut W, val: &str) -> fmt::Result {
+    out.write_char('"')?;
+    for c in val.chars() {
+        match c {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            '\t' => out.write_str("\\t")?,
+            c if c.is_control() => write!(out, "\\u{{{:x}}}", c as u32)?,
+            c => out.write_char(c)?,
+        }
+    }
+    out.write_char('"')
+}
+
+fn write_raw<W: Write>(out: &mut W, val: &[u8]) -> fmt::Result {
+    out.write_str("b\"")?;
+    for &b in val {
+        match b {
+            b'"' => out.write_str("\\\"")?,
+            b'\\' => out.write_str("\\\\")?,
+            0x20..=0x7E => out.write_char(b as char)?,
+            _ => write!(out, "\\x{:02x}", b)?,
+        }
+    }
+    out.write_char('"')
+}
+
+fn write_newline<W: Write>(out: &mut W, indent: Option<usize>) -> fmt::Result {
+    if let Some(level) = indent {
+        out.write_char('\n')?;
+        for _ in 0..level {
+            out.write_str(INDENT)?;
+        }
+    }
+    Ok(())
+}
+
+/// `indent` is the current level when pretty printing, `None` for one line
+fn write_value<W: Write>(out: &mut W, value: &Value, indent: Option<usize>) -> fmt::Result {
+    let inner = indent.map(|level| level + 1);
+    let sep = if indent.is_some() { "," } else { ", " };
+    match *value {
+        Value::Nil => out.write_str("nil"),
+        Value::Bool(val) => write!(out, "{}", val),
+        Value::U8(val) => write!(out, "u8({})", val),
+        Value::I8(val) => write!(out, "i8({})", val),
+        Value::U16(val) => write!(out, "u16({})", val),
+        Value::I16(val) => write!(out, "i16({})", val),
+        Value::U32(val) => write!(out, "u32({})", val),
+        Value::I32(val) => write!(out, "i32({})", val),
+        Value::U64(val) => write!(out, "u64({})", val),
+        Value::I64(val) => write!(out, "i64({})", val),
+        Value::Varint(val) => write!(out, "{}", val),
+        Value::Float(val) => write_float(out, "float", val as f64, format!("{:?}", val), "f"),
+        Value::Double(val) => write_float(out, "double", val, format!("{:?}", val), ""),
+        Value::Str(ref val) => write_str(out, val),
+        Value::Raw(ref val) => write_raw(out, val),
+        Value::Arr(ref val) => {
+            if val.is_empty() {
+                return out.write_str("[]");
+            }
+            out.write_char('[')?;
+            for (i, sub) in val.iter().enumerate() {
+                if i > 0 {
+                    out.write_str(sep)?;
+                }
+                write_newline(out, inner)?;
+                write_value(out, sub, inner)?;
+            }
+            write_newline(out, indent)?;
+            out.write_char(']')
+        }
+        Value::Map(ref val) => {
+            if val.is_empty() {
+                return out.write_str("{}");
+            }
+            // keys in text order so the output is stable
+            let mut entries: Vec<(String, &Value)> = val.iter().map(|(k, v)| {
+                let mut key = String::new();
+                let _ = write_value(&mut key, k, None);
+                (key, v)
+            }).collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            out.write_char('{')?;
+            for (i, (key, sub)) in entries.iter().enumerate() {
+                if i > 0 {
+                    out.write_str(sep)?;
+                }
+                write_newline(out, inner)?;
+                write!(out, "{}: ", key)?;
+                write_value(out, sub, inner)?;
+            }
+            write_newline(out, indent)?;
+            out.write_char('}')
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn fail<T>(&self, pos: usize, detail: String) -> RpResult<T> {
+        let before = &self.text[..pos];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+        fail!((ErrorKind::ParseError, "value text error", format!("line {}, column {}: {}", line, column, detail)))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_space(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '#' {
+                while !matches!(self.peek(), None | Some('\n')) {
+                    self.bump();
+                }
+            } else if c.is_whitespace() {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn expect(&mut self, want: char) -> RpResult<()> {
+        self.skip_space();
+        match self.peek() {
+            Some(c) if c == want => {
+                self.bump();
+                Ok(())
+            }
+            Some(c) => self.fail(self.pos, format!("expect '{}', found '{}'", want, c)),
+            None => self.fail(self.pos, format!("expect '{}', found end of text", want)),
+        }
+    }
+
+    fn word(&mut self) -> &'a str {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '+' | '-')) {
+            self.bump();
+        }
+        &self.text[start..self.pos]
+    }
+
+    fn value(&mut self, depth: usize) -> RpResult<Value> {
+        self.skip_space();
+        let start = self.pos;
+        match self.peek() {
+            None => self.fail(start, "expect a value, found end of text".to_string()),
+            Some('[') | Some('{') if depth >= MAX_DECODE_DEPTH => self.fail(start, "nested too deep".to_string()),
+            Some('[') => {
+                self.bump();
+                let mut arr = Vec::new();
+                while self.next_item(']', arr.is_empty())? {
+                    arr.push(self.value(depth + 1)?);
+                }
+                Ok(Value::Arr(arr))
+            }
+            Some('{') => {
+                self.bump();
+                let mut map = HashMap::new();
+                while self.next_item('}', map.is_empty())? {
+                    self.skip_space();
+                    let key_pos = self.pos;
+                    let key = self.value(depth + 1)?;
+                    self.expect(':')?;
+                    let sub = self.value(depth + 1)?;
+                    if map.contains_key(&key) {
+                        return self.fail(key_pos, format!("duplicate key {}", key));
+                    }
+                    map.insert(key, sub);
+                }
+                Ok(Value::Map(map))
+            }
+            Some('"') => Ok(Value::Str(self.string()?)),
+            Some('b') if self.text[self.pos..].starts_with("b\"") => {
+                self.bump();
+                Ok(Value::Raw(self.raw()?))
+            }
+            Some(_) => {
+                let word = self.word();
+                if word.is_empty() {
+                    let c = self.peek().unwrap();
+                    return self.fail(start, format!("unexpected '{}'", c));
+                }
+                self.skip_space();
+                if self.peek() == Some('(') {
+                    self.bump();
+                    self.skip_space();
+                    let arg_pos = self.pos;
+                    let arg = self.word();
+                    let value = self.typed(word, arg).or_else(|detail| self.fail(arg_pos, detail))?;
+                    self.expect(')')?;
+                    return Ok(value);
+                }
+                self.plain(word).or_else(|detail| self.fail(start, detail))
+            }
+        }
+    }
+
+    /// skip the separator before the next list item, false at the closing `end`
+    fn next_item(&mut self, end: char, first: bool) -> RpResult<bool> {
+        self.skip_space();
+        if !first && self.peek() != Some(end) {
+            self.expect(',')?;
+            self.skip_space();
+        }
+        if self.peek() == Some(end) {
+            self.bump();
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn plain(&self, word: &str) -> Result<Value, String> {
+        match word {
+            "nil" => return Ok(Value::Nil),
+            "true" => return Ok(Value::Bool(true)),
+            "false" => return Ok(Value::Bool(false)),
+            _ => {}
+        }
+        if !word.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+') {
+            return Err(format!("unknown value {:?}", word));
+        }
+        if let Some(float) = word.strip_suffix('f') {
+            return parse_num(float, "float").map(Value::Float);
+        }
+        if word.contains(['.', 'e', 'E']) {
+            return parse_num(word, "double").map(Value::Double);
+        }
+        parse_num(word, "varint").map(Value::Varint)
+    }
+
+    fn typed(&self, ty: &str, arg: &str) -> Result<Value, String> {
+        Ok(match ty {
+            "u8" => Value::U8(parse_num(arg, ty)?),
+            "i8" => Value::I8(parse_num(arg, ty)?),
+            "u16" => Value::U16(parse_num(arg, ty)?),
+            "i16" => Value::I16(parse_num(arg, ty)?),
+            "u32" => Value::U32(parse_num(arg, ty)?),
+            "i32" => Value::I32(parse_num(arg, ty)?),
+            "u64" => Value::U64(parse_num(arg, ty)?),
+            "i64" => Value::I64(parse_num(arg, ty)?),
+            "varint" => Value::Varint(parse_num(arg, ty)?),
+            "float" => Value::Float(parse_num(arg, ty).or_else(|err| match arg.strip_suffix('f') {
+                Some(num) => parse_num(num, ty),
+                None => Err(err),
+            })?),
+            "double" => Value::Double(parse_num(arg, ty)?),
+            _ => return Err(format!("unknown type {:?}", ty)),
+        })
+    }
+
+    fn escape(&mut self, start: usize) -> RpResult<char> {
+        Ok(match self.bump() {
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('0') => '\0',
+            Some('\\') => '\\',
+            Some('"') => '"',
+            Some('\'') => '\'',
+            Some('u') if self.peek() == Some('{') => {
+                self.bump();
+                let hex_start = self.pos;
+                while matches!(self.peek(), Some(c) if c.is_ascii_hexdigit()) {
+                    self.bump();
+                }
+                let hex = &self.text[hex_start..self.pos];
+                self.expect('}')?;
+                match u32::from_str_radix(hex, 16).ok().and_then(char::from_u32) {
+                    Some(c) => c,
+                    None => return self.fail(start, format!("bad unicode escape {:?}", hex)),
+                }
+            }
+            _ => return self.fail(start, "unknown escape".to_string()),
+        })
+    }
+
+    fn string(&mut self) -> RpResult<String> {
+        let open = self.pos;
+        self.bump();
+        let mut val = String::new();
+        loop {
+            let start = self.pos;
+            match self.bump() {
+                None => return self.fail(open, "unterminated string".to_string()),
+                Some('"') => return Ok(val),
+                Some('\\') => val.push(self.escape(start)?),
+                Some(c) => val.push(c),
+            }
+        }
+    }
+
+    fn raw(&mut self) -> RpResult<Vec<u8>> {
+        let open = self.pos - 1;
+        self.bump();
+        let mut val = Vec::new();
+        loop {
+            let start = self.pos;
+            match self.bump() {
+                None => return self.fail(open, "unterminated raw string".to_string()),
+                Some('"') => return Ok(val),
+                Some('\\') if self.peek() == Some('x') => {
+                    self.bump();
+                    let hex = self.text.get(self.pos..self.pos + 2).unwrap_or_default();
+                    match u8::from_str_radix(hex, 16) {
+                        Ok(b) if hex.len() == 2 => {
+                            self.pos += 2;
+                            val.push(b);
+                        }
+                        _ => return self.fail(start, "expect two hex digits after \\x".to_string()),
+                    }
+                }
+                Some('\\') => {
+                    let c = self.escape(start)?;
+                    if !c.is_ascii() {
+                        return self.fail(start, "raw string escapes must be ascii".to_string());
+                    }
+                    val.push(c as u8);
+                }
+                Some(c) if c.is_ascii() => val.push(c as u8),
+                Some(_) => return self.fail(start, "raw strings take ascii only, use \\xNN".to_string()),
+            }
+        }
+    }
+}
+
+fn parse_num<T: FromStr>(text: &str, ty: &str) -> Result<T, String> {
+    let text = text.strip_prefix('+').unwrap_or(text);
+    text.parse().map_err(|_| format!("bad {} {:?}", ty, text))
+}
+
+/// Parse one value in the text notation, the error detail has the line and
+/// column where it went wrong.
+pub fn parse_value(text: &str) -> RpResult<Value> {
+    let mut parser = Parser { text, pos: 0 };
+    let value = parser.value(0)?;
+    parser.skip_space();
+    if parser.pos != text.len() {
+        return parser.fail(parser.pos, "unexpected text after the value".to_string());
+    }
+    Ok(value)
+}
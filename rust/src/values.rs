@@ -4,6 +4,8 @@ use std::fmt;
 use std::io;
 use std::hash::Hash;
 
+use crate::buffer::OverMaxSize;
+
 
 pub const TYPE_NIL: u8 = 0;
 pub const TYPE_BOOL: u8 = 1;
@@ -24,24 +26,24 @@ pub const TYPE_RAW: u8 = 15;
 pub const TYPE_ARR: u8 = 16;
 pub const TYPE_MAP: u8 = 17;
 
-pub const STR_TYPE_NIL: &'static str = "nil";
-pub const STR_TYPE_BOOL: &'static str = "bool";
-pub const STR_TYPE_U8: &'static str = "u8";
-pub const STR_TYPE_I8: &'static str = "i8";
-pub const STR_TYPE_U16: &'static str = "u16";
-pub const STR_TYPE_I16: &'static str = "i16";
-pub const STR_TYPE_U32: &'static str = "u32";
-pub const STR_TYPE_I32: &'static str = "i32";
-pub const STR_TYPE_U64: &'static str = "u64";
-pub const STR_TYPE_I64: &'static str = "i64";
-pub const STR_TYPE_VARINT: &'static str = "varint";
-pub const STR_TYPE_FLOAT: &'static str = "float";
-pub const STR_TYPE_DOUBLE: &'static str = "double";
-pub const STR_TYPE_STR: &'static str = "str";
-pub const STR_TYPE_STR_IDX: &'static str = "str_idx";
-pub const STR_TYPE_RAW: &'static str = "raw";
-pub const STR_TYPE_ARR: &'static str = "arr";
-pub const STR_TYPE_MAP: &'static str = "map";
+pub const STR_TYPE_NIL: &str = "nil";
+pub const STR_TYPE_BOOL: &str = "bool";
+pub const STR_TYPE_U8: &str = "u8";
+pub const STR_TYPE_I8: &str = "i8";
+pub const STR_TYPE_U16: &str = "u16";
+pub const STR_TYPE_I16: &str = "i16";
+pub const STR_TYPE_U32: &str = "u32";
+pub const STR_TYPE_I32: &str = "i32";
+pub const STR_TYPE_U64: &str = "u64";
+pub const STR_TYPE_I64: &str = "i64";
+pub const STR_TYPE_VARINT: &str = "varint";
+pub const STR_TYPE_FLOAT: &str = "float";
+pub const STR_TYPE_DOUBLE: &str = "double";
+pub const STR_TYPE_STR: &str = "str";
+pub const STR_TYPE_STR_IDX: &str = "str_idx";
+pub const STR_TYPE_RAW: &str = "raw";
+pub const STR_TYPE_ARR: &str = "arr";
+pub const STR_TYPE_MAP: &str = "map";
 
 #[derive(Clone)]
 pub enum Value {
@@ -85,7 +87,7 @@ impl PartialEq for Value {
             (Self::Varint(l0), Self::U32(r0)) => l0 == &(*r0 as i64),
             (Self::Varint(l0), Self::I32(r0)) => l0 == &(*r0 as i64),
             (Self::Varint(l0), Self::U64(r0)) => l0 == &(*r0 as i64),
-            (Self::Varint(l0), Self::I64(r0)) => l0 == &(*r0 as i64),
+            (Self::Varint(l0), Self::I64(r0)) => l0 == r0,
 
             (Self::U8(r0), Self::Varint(l0)) => l0 == &(*r0 as i64),
             (Self::I8(r0), Self::Varint(l0)) => l0 == &(*r0 as i64),
@@ -94,7 +96,7 @@ impl PartialEq for Value {
             (Self::U32(r0), Self::Varint(l0)) => l0 == &(*r0 as i64),
             (Self::I32(r0), Self::Varint(l0)) => l0 == &(*r0 as i64),
             (Self::U64(r0), Self::Varint(l0)) => l0 == &(*r0 as i64),
-            (Self::I64(r0), Self::Varint(l0)) => l0 == &(*r0 as i64),
+            (Self::I64(r0), Self::Varint(l0)) => l0 == r0,
 
             (Self::Float(l0), Self::Float(r0)) => l0 == r0,
             (Self::Double(l0), Self::Double(r0)) => l0 == r0,
@@ -111,9 +113,35 @@ impl Eq for Value {
 }
 
 
+/// Hashes the payload so map keys spread out, every integer width hashes as
+/// its `i64` since a varint equals the same number of any width.
 impl Hash for Value {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        core::mem::discriminant(self).hash(state);
+        match *self {
+            Value::U8(val) => (val as i64).hash(state),
+            Value::I8(val) => (val as i64).hash(state),
+            Value::U16(val) => (val as i64).hash(state),
+            Value::I16(val) => (val as i64).hash(state),
+            Value::U32(val) => (val as i64).hash(state),
+            Value::I32(val) => (val as i64).hash(state),
+            Value::U64(val) => (val as i64).hash(state),
+            Value::I64(val) | Value::Varint(val) => val.hash(state),
+            _ => {
+                core::mem::discriminant(self).hash(state);
+                match *self {
+                    Value::Bool(val) => val.hash(state),
+                    // 0.0 and -0.0 are equal
+                    Value::Float(val) => (val + 0.0).to_bits().hash(state),
+                    Value::Double(val) => (val + 0.0).to_bits().hash(state),
+                    Value::Str(ref val) => val.hash(state),
+                    Value::Raw(ref val) => val.hash(state),
+                    Value::Arr(ref val) => val.hash(state),
+                    // the entries have no order, equal maps have the same length
+                    Value::Map(ref val) => val.len().hash(state),
+                    _ => {}
+                }
+            }
+        }
     }
 }
 
@@ -134,9 +162,9 @@ impl fmt::Debug for Value {
             Value::Float(val) => write!(fmt, "float({:?})", val),
             Value::Double(val) => write!(fmt, "double({:?})", val),
             Value::Str(ref val) => write!(fmt, "str({:?})", val),
-            Value::Raw(ref val) => write!(fmt, "str({:?})", val),
+            Value::Raw(ref val) => write!(fmt, "raw({:?})", val),
             Value::Arr(ref val) => write!(fmt, "arr({:?})", val),
-            Value::Map(ref val) => write!(fmt, "str({:?})", val),
+            Value::Map(ref val) => write!(fmt, "map({:?})", val),
         }
     }
 }
@@ -213,6 +241,24 @@ impl From<String> for Value {
     }
 }
 
+impl From<&str> for Value {
+    fn from(val: &str) -> Value {
+        Value::Str(val.to_string())
+    }
+}
+
+impl From<&[u8]> for Value {
+    fn from(val: &[u8]) -> Value {
+        Value::Raw(val.to_vec())
+    }
+}
+
+impl<const N: usize> From<&[u8; N]> for Value {
+    fn from(val: &[u8; N]) -> Value {
+        Value::Raw(val.to_vec())
+    }
+}
+
 impl From<Vec<u8>> for Value {
     fn from(val: Vec<u8>) -> Value {
         Value::Raw(val)
@@ -231,6 +277,7 @@ impl From<HashMap<Value, Value>> for Value {
     }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<bool> for Value {
     fn into(self) -> bool {
         match self {
@@ -243,6 +290,7 @@ impl Into<bool> for Value {
     }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<u8> for Value {
     fn into(self) -> u8 {
         match self {
@@ -253,6 +301,7 @@ impl Into<u8> for Value {
     }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<i8> for Value {
     fn into(self) -> i8 {
         match self {
@@ -263,6 +312,7 @@ impl Into<i8> for Value {
     }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<u16> for Value {
     fn into(self) -> u16 {
         match self {
@@ -273,6 +323,7 @@ impl Into<u16> for Value {
     }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<i16> for Value {
     fn into(self) -> i16 {
         match self {
@@ -283,6 +334,7 @@ impl Into<i16> for Value {
     }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<u32> for Value {
     fn into(self) -> u32 {
         match self {
@@ -293,6 +345,7 @@ impl Into<u32> for Value {
     }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<i32> for Value {
     fn into(self) -> i32 {
         match self {
@@ -304,6 +357,7 @@ impl Into<i32> for Value {
 }
 
 
+#[allow(clippy::from_over_into)]
 impl Into<u64> for Value {
     fn into(self) -> u64 {
         match self {
@@ -314,37 +368,41 @@ impl Into<u64> for Value {
     }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<i64> for Value {
     fn into(self) -> i64 {
         match self {
             Value::I64(val) => val,
-            Value::Varint(val) => val as i64,
+            Value::Varint(val) => val,
             _ => panic!("into error type {}", get_name_by_type(get_type_by_value(&self))),
         }
     }
 }
 
 
+#[allow(clippy::from_over_into)]
 impl Into<f32> for Value {
     fn into(self) -> f32 {
         match self {
             Value::Float(val) => val,
-            Value::Varint(val) => (val as f32  / 1000.0),
+            Value::Varint(val) => val as f32 / 1000.0,
             _ => panic!("into error type {}", get_name_by_type(get_type_by_value(&self))),
         }
     }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<f64> for Value {
     fn into(self) -> f64 {
         match self {
             Value::Double(val) => val,
-            Value::Varint(val) => (val as f64  / 1000000.0),
+            Value::Varint(val) => val as f64 / 1000000.0,
             _ => panic!("into error type {}", get_name_by_type(get_type_by_value(&self))),
         }
     }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<String> for Value {
     fn into(self) -> String {
         match self {
@@ -354,6 +412,7 @@ impl Into<String> for Value {
     }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<Vec<u8>> for Value {
     fn into(self) -> Vec<u8> {
         match self {
@@ -363,6 +422,7 @@ impl Into<Vec<u8>> for Value {
     }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<Vec<Value>> for Value {
     fn into(self) -> Vec<Value> {
         match self {
@@ -372,6 +432,7 @@ impl Into<Vec<Value>> for Value {
     }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<HashMap<Value, Value>> for Value {
     fn into(self) -> HashMap<Value, Value> {
         match self {
@@ -420,6 +481,7 @@ pub fn get_type_by_name(name: &str) -> u8 {
         STR_TYPE_FLOAT => TYPE_FLOAT,
         STR_TYPE_DOUBLE => TYPE_DOUBLE,
         STR_TYPE_STR => TYPE_STR,
+        STR_TYPE_STR_IDX => TYPE_STR_IDX,
         STR_TYPE_RAW => TYPE_RAW,
         STR_TYPE_ARR => TYPE_ARR,
         STR_TYPE_MAP => TYPE_MAP,
@@ -443,12 +505,38 @@ pub fn get_name_by_type(index: u8) -> &'static str {
         TYPE_FLOAT => STR_TYPE_FLOAT,
         TYPE_DOUBLE => STR_TYPE_DOUBLE,
         TYPE_STR => STR_TYPE_STR,
+        TYPE_STR_IDX => STR_TYPE_STR_IDX,
         TYPE_RAW => STR_TYPE_RAW,
         TYPE_ARR => STR_TYPE_ARR,
         TYPE_MAP => STR_TYPE_MAP,
         _ => STR_TYPE_NIL,
     }
 }
+
+/// The value `decode_field(encode_field(value))` gives back: u16 to u64 become
+/// varint, float keeps 3 and double 6 decimals, map keys are normalized too.
+pub fn normalize(value: &Value) -> Value {
+    match *value {
+        Value::U16(val) => Value::Varint(val as i64),
+        Value::I16(val) => Value::Varint(val as i64),
+        Value::U32(val) => Value::Varint(val as i64),
+        Value::I32(val) => Value::Varint(val as i64),
+        Value::U64(val) => Value::Varint(val as i64),
+        Value::I64(val) => Value::Varint(val),
+        Value::Float(val) => Value::Float(((val * 1000.0) as i64) as f32 / 1000.0),
+        Value::Double(val) => Value::Double(((val * 1000000.0) as i64) as f64 / 1000000.0),
+        Value::Arr(ref val) => Value::Arr(val.iter().map(normalize).collect()),
+        Value::Map(ref val) => {
+            let mut map = HashMap::with_capacity(val.len());
+            for (k, v) in val {
+                map.insert(normalize(k), normalize(v));
+            }
+            Value::Map(map)
+        }
+        _ => value.clone(),
+    }
+}
+
 /// An enum of all error kinds.
 #[derive(PartialEq, Eq, Copy, Clone, Debug)]
 pub enum ErrorKind {
@@ -464,6 +552,12 @@ pub enum ErrorKind {
     MissingError,
     /// string format must be utf-8
     StringFormatError,
+    /// the session string table does not match the peer's, reset both sides
+    StringTableError,
+    /// the checksum of a frame does not match its data
+    ChecksumMismatch,
+    /// a sealed frame is forged, replayed or out of order
+    AuthenticationFailed,
     /// This kind is returned if the redis error is one that is
     /// not native to the system.  This is usually the case if
     /// the cause is another error.
@@ -495,12 +589,12 @@ pub type RpResult<T> = Result<T, RpError>;
 impl PartialEq for RpError {
     fn eq(&self, other: &RpError) -> bool {
         match (&self.repr, &other.repr) {
-            (&ErrorRepr::WithDescription(kind_a, _), &ErrorRepr::WithDescription(kind_b, _)) => {
+            (ErrorRepr::WithDescription(kind_a, _), ErrorRepr::WithDescription(kind_b, _)) => {
                 kind_a == kind_b
             }
-            (&ErrorRepr::WithDescriptionAndDetail(kind_a, _, _),
-             &ErrorRepr::WithDescriptionAndDetail(kind_b, _, _)) => kind_a == kind_b,
-            (&ErrorRepr::ExtensionError(ref a, _), &ErrorRepr::ExtensionError(ref b, _)) => {
+            (ErrorRepr::WithDescriptionAndDetail(kind_a, _, _),
+             ErrorRepr::WithDescriptionAndDetail(kind_b, _, _)) => kind_a == kind_b,
+            (ErrorRepr::ExtensionError(a, _), ErrorRepr::ExtensionError(b, _)) => {
                 *a == *b
             }
             _ => false,
@@ -510,6 +604,9 @@ impl PartialEq for RpError {
 
 impl From<io::Error> for RpError {
     fn from(err: io::Error) -> RpError {
+        if let Some(over) = err.get_ref().and_then(|inner| inner.downcast_ref::<OverMaxSize>()) {
+            return RpError::from((ErrorKind::BufferOverMaxError, "buffer over max size", over.to_string()));
+        }
         RpError { repr: ErrorRepr::IoError(err) }
     }
 }
@@ -582,6 +679,9 @@ impl RpError {
             ErrorKind::ParseError => "parse error",
             ErrorKind::MissingError => "missing error",
             ErrorKind::StringFormatError => "string format error",
+            ErrorKind::StringTableError => "string table error",
+            ErrorKind::ChecksumMismatch => "checksum mismatch",
+            ErrorKind::AuthenticationFailed => "authentication failed",
             ErrorKind::IoError => "I/O error",
             ErrorKind::ExtensionError => "extension error",
         }
@@ -589,16 +689,13 @@ impl RpError {
 
     /// Indicates that this failure is an IO failure.
     pub fn is_io_error(&self) -> bool {
-        match self.kind() {
-            ErrorKind::IoError => true,
-            _ => false,
-        }
+        matches!(self.kind(), ErrorKind::IoError)
     }
 
     /// Returns the extension error code
     pub fn extension_error_code(&self) -> Option<&str> {
         match self.repr {
-            ErrorRepr::ExtensionError(ref code, _) => Some(&code),
+            ErrorRepr::ExtensionError(ref code, _) => Some(code),
             _ => None,
         }
     }
@@ -606,8 +703,8 @@ impl RpError {
     /// Returns the extension error detail
     pub fn extension_error_detail(&self) -> Option<&str> {
         match self.repr {
-            ErrorRepr::ExtensionError(_, ref detail) => Some(&detail),
-            ErrorRepr::WithDescriptionAndDetail(_, _, ref detail) => Some(&detail),
+            ErrorRepr::ExtensionError(_, ref detail) => Some(detail),
+            ErrorRepr::WithDescriptionAndDetail(_, _, ref detail) => Some(detail),
             _ => None,
         }
     }
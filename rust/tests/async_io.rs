@@ -0,0 +1,131 @@
+#[macro_use]
+extern crate tunm_proto as tunm;
+
+use tokio::io::{AsyncWriteExt, BufReader};
+use tunm::{Buffer, BufferConfig, ErrorKind, Value};
+
+#[tokio::test]
+async fn test_proto_round_trip() {
+    let (mut client, mut server) = tokio::io::duplex(16);
+    let write = async {
+        tunm::write_proto(&mut client, "cmd_first", tunm_args![1u32, "text"]).await.unwrap();
+        tunm::write_proto(&mut client, "cmd_second", tunm_args![vec![7u8; 100]]).await.unwrap();
+    };
+    let read = async {
+        let mut buffer = Buffer::new();
+        let first = tunm::read_proto(&mut server, &mut buffer).await.unwrap();
+        let second = tunm::read_proto(&mut server, &mut buffer).await.unwrap();
+        (first, second)
+    };
+    let (_, (first, second)) = tokio::join!(write, read);
+    assert_eq!(first, ("cmd_first".to_string(), vec![Value::U32(1), Value::from("text")]));
+    assert_eq!(second, ("cmd_second".to_string(), vec![Value::Raw(vec![7; 100])]));
+}
+
+#[tokio::test]
+async fn test_one_byte_at_a_time() {
+    let mut data = vec![];
+    tunm::write_proto(&mut data, "cmd_slow", tunm_args![1u32]).await.unwrap();
+    tunm::write_proto(&mut data, "cmd_next", tunm_args![2u32]).await.unwrap();
+
+    let mut reader = BufReader::with_capacity(1, &data[..]);
+    let mut buffer = Buffer::new();
+    let (name, _) = tunm::read_proto(&mut reader, &mut buffer).await.unwrap();
+    assert_eq!(name, "cmd_slow");
+    let (name, args) = tunm::read_proto(&mut reader, &mut buffer).await.unwrap();
+    assert_eq!(name, "cmd_next");
+    assert_eq!(args, vec![Value::U32(2)]);
+
+    let err = tunm::read_proto(&mut reader, &mut buffer).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::IoError);
+}
+
+#[tokio::test]
+async fn test_read_proto_cancelled() {
+    let mut data = vec![];
+    tunm::write_proto(&mut data, "cmd_cancel", tunm_args![vec![7u8; 100]]).await.unwrap();
+    let (mut client, mut server) = tokio::io::duplex(256);
+    client.write_all(&data[..50]).await.unwrap();
+
+    // the read takes what is there, then waits and is dropped
+    let mut buffer = Buffer::new();
+    tokio::select! {
+        biased;
+        _ = tunm::read_proto(&mut server, &mut buffer) => panic!("half a proto read"),
+        _ = async {} => {}
+    }
+    assert_eq!(buffer.get_write_data(), &data[..50]);
+
+    client.write_all(&data[50..]).await.unwrap();
+    let (name, args) = tunm::read_proto(&mut server, &mut buffer).await.unwrap();
+    assert_eq!(name, "cmd_cancel");
+    assert_eq!(args, vec![Value::Raw(vec![7; 100])]);
+
+    // an end in the middle leaves the proto too
+    client.write_all(&data[..50]).await.unwrap();
+    drop(client);
+    let err = tunm::read_proto(&mut server, &mut buffer).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::IoError);
+    assert_eq!(buffer.get_write_data(), &data[..50]);
+}
+
+#[tokio::test]
+#[cfg(feature = "crc32c")]
+async fn test_with_buffer_settings() {
+    use std::io::Write;
+    use tunm::Checksum;
+    let framed = || {
+        let mut buffer = Buffer::new();
+        buffer.set_checksum(Some(Checksum::Crc32c));
+        buffer
+    };
+    let mut data = vec![];
+    let mut buffer = framed();
+    buffer.write_all(&[1, 2, 3]).unwrap();
+    tunm::write_proto_with(&mut data, &mut buffer, "cmd_framed", tunm_args!["text"]).await.unwrap();
+    // the data already in the buffer is not sent
+    assert_eq!(buffer.get_write_data(), &[1, 2, 3]);
+
+    let (name, _) = tunm::read_proto(&mut &data[..], &mut framed()).await.unwrap();
+    assert_eq!(name, "cmd_framed");
+    // a buffer without the checksum reads the frame as a proto and fails
+    assert!(tunm::read_proto(&mut &data[..], &mut Buffer::new()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_bad_length() {
+    let mut data = vec![];
+    tunm::write_proto(&mut data, "cmd_short", tunm_args![]).await.unwrap();
+    // a length past the proto leaves data in it, the varint is zigzag
+    data[0] += 2;
+    data.push(0);
+    data.push(0x02);
+    let mut buffer = Buffer::new();
+    let err = tunm::read_proto(&mut &data[..], &mut buffer).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ParseError);
+    assert_eq!(buffer.get_write_data(), &[0x02]);
+
+    // a length short of the proto, it can not read past its frame
+    let mut data = vec![];
+    tunm::write_proto(&mut data, "cmd_long", tunm_args![1u32]).await.unwrap();
+    data[0] -= 2;
+    let mut buffer = Buffer::new();
+    let err = tunm::read_proto(&mut &data[..], &mut buffer).await.unwrap_err();
+    assert_eq!(err.to_string(), "proto longer than its frame");
+    assert_eq!(buffer.get_write_data(), &data[data.len() - 1..]);
+    data.pop();
+    let err = tunm::read_proto(&mut &data[..], &mut Buffer::new()).await.unwrap_err();
+    assert_eq!(err.to_string(), "proto longer than its frame");
+
+    let mut buffer = Buffer::with_config(BufferConfig { max_size: 100, ..BufferConfig::new() });
+    let mut head = Buffer::new();
+    tunm::encode_varint(&mut head, &Value::U64(1000)).unwrap();
+    let err = tunm::read_proto(&mut head.get_write_data(), &mut buffer).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BufferOverMaxError);
+
+    let (mut client, mut server) = tokio::io::duplex(16);
+    client.write_all(&[0x80]).await.unwrap();
+    drop(client);
+    let err = tunm::read_proto(&mut server, &mut Buffer::new()).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::IoError);
+}
@@ -0,0 +1,84 @@
+#[macro_use]
+extern crate tunm_proto as tunm;
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use tunm::{Buffer, BufferConfig, Value};
+
+fn args(i: u32) -> Vec<Value> {
+    tunm_args![{"name": "server", "seq": i}, [1, 2, 3]]
+}
+
+#[test]
+fn test_buffer_buf_mut() {
+    let mut buffer = Buffer::new();
+    buffer.put_u32(0x1234_5678);
+    buffer.put_slice(b"tunm");
+    buffer.put_bytes(7, 4096);
+    assert_eq!(buffer.remaining(), 4 + 4 + 4096);
+
+    assert_eq!(buffer.get_u32(), 0x1234_5678);
+    let mut name = [0; 4];
+    buffer.copy_to_slice(&mut name);
+    assert_eq!(&name, b"tunm");
+    assert_eq!(buffer.chunk().len(), 4096);
+    buffer.advance(4096);
+    assert!(!buffer.has_remaining());
+}
+
+#[test]
+fn test_chunk_mut_in_max_size() {
+    let mut buffer = Buffer::with_config(BufferConfig { max_size: 100, ..BufferConfig::new() });
+    buffer.put_slice(&[1; 60]);
+    assert_eq!(buffer.remaining_mut(), 40);
+    assert_eq!(buffer.chunk_mut().len(), 40);
+    buffer.put_bytes(2, 40);
+    assert_eq!(buffer.chunk_mut().len(), 0);
+    assert!(!buffer.has_remaining_mut());
+}
+
+#[test]
+fn test_buffer_bytes_without_copy() {
+    let mut source = Buffer::new();
+    tunm::encode_proto(&mut source, &"cmd_test".to_string(), args(1)).unwrap();
+    let mut bytes = BytesMut::with_capacity(1024);
+    bytes.put(source.get_write_data());
+    let ptr = bytes.as_ptr();
+
+    let mut buffer = Buffer::from(bytes);
+    assert_eq!(buffer.get_write_data().as_ptr(), ptr);
+    assert_eq!(tunm::decode_proto(&mut buffer).unwrap().1, args(1));
+
+    // the unread part is all that comes back
+    tunm::encode_proto(&mut buffer, &"cmd_test".to_string(), args(2)).unwrap();
+    buffer.advance(3);
+    let start = buffer.get_write_data().as_ptr();
+    let bytes = BytesMut::from(buffer);
+    assert_eq!(bytes.as_ptr(), start);
+    let bytes = Bytes::from(Buffer::from(bytes));
+    assert_eq!(bytes.len(), source.data_len() - 3);
+}
+
+#[test]
+#[cfg(feature = "crc32c")]
+fn test_encode_decode_buf() {
+    use tunm::{Checksum, ErrorKind};
+    let mut writer = Buffer::new();
+    writer.set_checksum(Some(Checksum::Crc32c));
+    let mut out = BytesMut::new();
+    for i in 0..3 {
+        tunm::encode_proto_buf(&mut writer, &mut out, "cmd_test", args(i)).unwrap();
+    }
+    assert!(writer.is_empty());
+
+    // the frames arrive as two pieces that cut one in half
+    let mut data = out.freeze();
+    let first = data.split_to(data.len() / 2);
+    let mut reader = Buffer::new();
+    reader.set_checksum(Some(Checksum::Crc32c));
+    let mut piece = first.chain(Bytes::new());
+    assert_eq!(tunm::decode_proto_buf(&mut reader, &mut piece).unwrap().1, args(0));
+    assert_eq!(tunm::decode_proto(&mut reader).unwrap_err().kind(), ErrorKind::NoLeftSpaceError);
+    assert!(!piece.has_remaining());
+    assert_eq!(tunm::decode_proto_buf(&mut reader, &mut data).unwrap().1, args(1));
+    assert_eq!(tunm::decode_proto(&mut reader).unwrap().1, args(2));
+}
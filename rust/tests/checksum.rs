@@ -0,0 +1,138 @@
+#[macro_use]
+extern crate tunm_proto as tunm;
+
+use std::io::Write;
+
+use tunm::{Buffer, Checksum, ErrorKind, Value};
+
+fn args() -> Vec<Value> {
+    tunm_args![{"name": "server", "code_type": "tunm", "port": 8080u16}, [1, 2, 3], 2.5]
+}
+
+fn buffer_with(checksum: Checksum) -> Buffer {
+    let mut buffer = Buffer::new();
+    buffer.set_checksum(Some(checksum));
+    buffer
+}
+
+fn frame(checksum: Checksum) -> Vec<u8> {
+    let mut buffer = buffer_with(checksum);
+    tunm::encode_proto(&mut buffer, &"cmd_state".to_string(), args()).unwrap();
+    buffer.drain_all_collect()
+}
+
+fn plain_len() -> usize {
+    let mut plain = Buffer::new();
+    tunm::encode_proto(&mut plain, &"cmd_state".to_string(), args()).unwrap();
+    plain.data_len()
+}
+
+/// the bytes of the zigzag varint length in front of a proto
+fn head_len(len: usize) -> usize {
+    let mut head = Buffer::new();
+    tunm::encode_varint(&mut head, &Value::U64(len as u64)).unwrap();
+    head.data_len()
+}
+
+fn decode(checksum: Checksum, data: &[u8]) -> tunm::RpResult<(String, Vec<Value>)> {
+    let mut buffer = buffer_with(checksum);
+    buffer.write_all(data).unwrap();
+    tunm::decode_proto(&mut buffer)
+}
+
+#[test]
+fn test_checksum_round_trip() {
+    for checksum in [Checksum::Crc32c, Checksum::XxHash64] {
+        let data = frame(checksum);
+        let len = plain_len();
+        assert_eq!(data.len(), head_len(len) + len + checksum.size());
+        assert_eq!(decode(checksum, &data).unwrap(), ("cmd_state".to_string(), args()));
+        assert_eq!(decode(checksum, &[data.clone(), data].concat()).unwrap().1, args());
+    }
+}
+
+#[test]
+fn test_single_byte_corruption() {
+    for checksum in [Checksum::Crc32c, Checksum::XxHash64] {
+        let data = frame(checksum);
+        let head = head_len(plain_len());
+        for at in 0..data.len() {
+            for bit in 0..8 {
+                let mut bad = data.clone();
+                bad[at] ^= 1 << bit;
+                let err = decode(checksum, &bad).unwrap_err();
+                // a broken length makes the frame look cut or too long
+                if at >= head {
+                    assert_eq!(err.kind(), ErrorKind::ChecksumMismatch, "byte {} bit {}", at, bit);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_frame_after_mismatch() {
+    let checksum = Checksum::Crc32c;
+    let good = frame(checksum);
+    let mut bad = good.clone();
+    bad[5] ^= 0xFF;
+    let mut buffer = buffer_with(checksum);
+    buffer.write_all(&bad).unwrap();
+    buffer.write_all(&good).unwrap();
+    assert_eq!(tunm::decode_proto(&mut buffer).unwrap_err().kind(), ErrorKind::ChecksumMismatch);
+    assert_eq!(tunm::decode_proto(&mut buffer).unwrap().1, args());
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn test_partial_frame_waits() {
+    let checksum = Checksum::XxHash64;
+    let data = frame(checksum);
+    let mut buffer = buffer_with(checksum);
+    for (i, byte) in data.iter().enumerate() {
+        if i > 0 {
+            assert_eq!(tunm::decode_proto(&mut buffer).unwrap_err().kind(), ErrorKind::NoLeftSpaceError);
+            assert_eq!(buffer.data_len(), i);
+        }
+        buffer.write_all(&[*byte]).unwrap();
+    }
+    assert_eq!(tunm::decode_proto(&mut buffer).unwrap().1, args());
+}
+
+#[test]
+#[cfg(feature = "lz4")]
+fn test_checksum_with_compress_and_session() {
+    use tunm::{CompressConfig, Compression};
+    let make = || {
+        let mut buffer = buffer_with(Checksum::Crc32c);
+        buffer.set_compress(Some(CompressConfig { threshold: 0, ..CompressConfig::new(Compression::Lz4) }));
+        buffer.set_str_session(true);
+        buffer
+    };
+    let (mut writer, mut reader) = (make(), make());
+    for _ in 0..3 {
+        tunm::encode_proto(&mut writer, &"cmd_state".to_string(), args()).unwrap();
+        reader.write_all(&writer.drain_all_collect()).unwrap();
+        assert_eq!(tunm::decode_proto(&mut reader).unwrap().1, args());
+    }
+}
+
+#[test]
+fn test_frame_over_max_size_keeps_session() {
+    let config = tunm::BufferConfig { initial_capacity: 16, max_size: 128, ..tunm::BufferConfig::new() };
+    let mut writer = Buffer::with_config(config);
+    writer.set_checksum(Some(Checksum::Crc32c));
+    writer.set_str_session(true);
+    let mut reader = buffer_with(Checksum::Crc32c);
+    reader.set_str_session(true);
+    tunm::encode_proto(&mut writer, &"cmd_state".to_string(), args()).unwrap();
+    reader.write_all(&writer.drain_all_collect()).unwrap();
+    assert_eq!(tunm::decode_proto(&mut reader).unwrap().1, args());
+
+    let err = tunm::encode_proto(&mut writer, &"cmd_state".to_string(), tunm_args!["x".repeat(200)]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BufferOverMaxError);
+    assert!(writer.is_empty());
+    tunm::encode_proto(&mut writer, &"cmd_state".to_string(), tunm_args!["tunm", "json"]).unwrap();
+    reader.write_all(&writer.drain_all_collect()).unwrap();
+    assert_eq!(tunm::decode_proto(&mut reader).unwrap().1, tunm_args!["tunm", "json"]);
+}
@@ -0,0 +1,121 @@
+//! The `tunm` binary, needs the `cli` feature which the dev-dependency turns on.
+
+extern crate serde_json;
+extern crate tunm_proto as tunm;
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+use serde_json::Value as Json;
+use tunm::{Buffer, Value};
+
+fn run(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tunm"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn sample() -> Vec<u8> {
+    let mut buffer = Buffer::new();
+    let args = vec![
+        Value::Str("hello".to_string()),
+        Value::U16(5),
+        Value::Arr(vec![Value::Str("hello".to_string()), Value::Raw(vec![1, 2, 3])]),
+        Value::Map(vec![(Value::Str("k".to_string()), Value::Double(2.5))].into_iter().collect()),
+    ];
+    tunm::encode_proto(&mut buffer, &"cmd_login".to_string(), args).unwrap();
+    buffer.get_write_data().to_vec()
+}
+
+#[test]
+fn test_dump() {
+    let out = stdout(&run(&["dump"], &sample()));
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines[0], "000000  12 63 6d 64 5f 6c 6f 67  name \"cmd_login\"");
+    assert_eq!(lines[2], "00000a  04                       string table, 2 entries");
+    assert_eq!(lines[3], "00000b  0a 68 65 6c 6c 6f          #0 \"hello\"");
+    assert!(out.contains("  [0] str_idx #0 \"hello\""), "{}", out);
+    assert!(out.contains("    [1] raw 3 bytes"), "{}", out);
+    assert!(out.contains("    value 0 double(2.5)"), "{}", out);
+    assert_eq!(lines.last().unwrap(), &format!("{} bytes", sample().len()));
+}
+
+#[test]
+fn test_json_round_trip() {
+    let json = stdout(&run(&["to-json"], &sample()));
+    let parsed: Json = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed["proto"], "cmd_login");
+    assert_eq!(parsed["args"][1], serde_json::json!({ "varint": 5 }));
+
+    let bytes = run(&["from-json"], json.as_bytes());
+    assert_eq!(stdout(&run(&["to-json"], &bytes.stdout)), json);
+
+    let hex = stdout(&run(&["from-json", "--hex"], json.as_bytes()));
+    assert_eq!(stdout(&run(&["to-json", "--hex"], hex.as_bytes())), json);
+}
+
+#[test]
+fn test_plain_json() {
+    let json = stdout(&run(&["to-json", "--plain"], &sample()));
+    let parsed: Json = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed["args"], serde_json::json!(["hello", 5, ["hello", "010203"], { "k": 2.5 }]));
+
+    let bytes = run(&["from-json", "--plain"], br#"{"proto": "cmd_plain", "args": [1, "a", {"b": null}]}"#);
+    let mut buffer = Buffer::new();
+    buffer.write_all(&bytes.stdout).unwrap();
+    let (name, args) = tunm::decode_proto(&mut buffer).unwrap();
+    assert_eq!(name, "cmd_plain");
+    assert_eq!(args, vec![Value::Varint(1), Value::Str("a".to_string()),
+                          Value::Map(vec![(Value::Str("b".to_string()), Value::Nil)].into_iter().collect())]);
+}
+
+#[test]
+fn test_validate() {
+    let out = stdout(&run(&["validate"], &sample()));
+    assert_eq!(out, format!("-: ok, {} bytes\n", sample().len()));
+
+    let mut truncated = sample();
+    truncated.truncate(20);
+    let output = run(&["validate"], &truncated);
+    assert!(!output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "-: error at 0x14: must left space to read \n");
+
+    let mut trailing = sample();
+    trailing.push(0);
+    let output = run(&["validate"], &trailing);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("trailing bytes after proto"));
+}
+
+#[test]
+fn test_stats() {
+    let out = stdout(&run(&["stats"], &sample()));
+    let row = |kind: &str| out.lines().find(|line| line.starts_with(&format!("{} ", kind))).unwrap().to_string();
+    let cols = |line: String| line.split_whitespace().skip(1).take(2).map(|c| c.parse().unwrap()).collect::<Vec<usize>>();
+    assert_eq!(cols(row("str_idx")), vec![3, 6]);
+    assert_eq!(cols(row("arr")), vec![2, 4]);
+    assert_eq!(cols(row("raw")), vec![1, 5]);
+    assert_eq!(cols(row("total")), vec![1, sample().len()]);
+}
+
+#[test]
+fn test_bad_input() {
+    let output = run(&["from-json"], br#"{"proto": "cmd", "args": [{"u8": 300}]}"#);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("out of range"));
+
+    let output = run(&["to-json", "--hex"], b"zz");
+    assert!(!output.status.success());
+    assert_eq!(run(&["nope"], b"").status.code(), Some(2));
+}
@@ -0,0 +1,59 @@
+extern crate tunm_proto as tunm;
+use tunm::codegen::{self, Lang, ProtoSchema};
+use tunm::ErrorKind;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// compare against `tests/codegen/protos.<ext>`, run with `TUNM_UPDATE_SNAPSHOTS=1` to rewrite them
+fn check_snapshot(lang: Lang) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/codegen");
+    let schema = ProtoSchema::parse(&fs::read_to_string(dir.join("protos.tunm")).unwrap()).unwrap();
+    let generated = codegen::generate(&schema, lang);
+    let path = dir.join(format!("protos.{}", lang.extension()));
+    if env::var_os("TUNM_UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&path, &generated).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_default();
+    assert!(expected == generated, "snapshot {} changed, generated:\n{}", path.display(), generated);
+}
+
+#[test]
+fn test_codegen_gdscript() {
+    check_snapshot(Lang::GdScript);
+}
+
+#[test]
+fn test_codegen_javascript() {
+    check_snapshot(Lang::JavaScript);
+}
+
+#[test]
+fn test_codegen_python() {
+    check_snapshot(Lang::Python);
+}
+
+#[test]
+fn test_codegen_csharp() {
+    check_snapshot(Lang::CSharp);
+}
+
+#[test]
+fn test_schema_parse() {
+    let schema = ProtoSchema::parse("cmd_a(name: str, id: u16) # tail\n\n  cmd_b( )\n").unwrap();
+    assert_eq!(schema.protos.len(), 2);
+    assert_eq!(schema.protos[0].name, "cmd_a");
+    assert_eq!(schema.protos[0].args[1].name, "id");
+    assert_eq!(schema.protos[0].args[1].pattern, tunm::TYPE_U16);
+    assert!(schema.protos[1].args.is_empty());
+
+    for bad in ["cmd_a", "cmd_a(id: u16", "cmd_a(id)", "cmd_a(id: u128)", "cmd_a(id: str_idx)",
+                "cmd a()", "cmd_a(1d: u8)", "cmd_a(a: u8, a: u8)", "cmd_a()\ncmd_a()"] {
+        let err = ProtoSchema::parse(bad).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ParseError, "{}", bad);
+    }
+    let err = ProtoSchema::parse("cmd_a()\n\ncmd_b(x: what)").unwrap_err();
+    assert_eq!(err.extension_error_detail(), Some("line 3: unknown type `what`"));
+}
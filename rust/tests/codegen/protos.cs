@@ -0,0 +1,111 @@
+// Code generated by tunm_codegen. DO NOT EDIT.
+using System;
+using System.Collections;
+using System.Collections.Generic;
+
+namespace proto.tunm {
+
+    public interface ITunmHandler {
+        void on_cmd_internal_auth(string server, string key);
+        void on_cmd_agent_identity(ushort code_type, uint code_id);
+        void on_cmd_login(Dictionary<Object, Object> info);
+        void on_msg_enter_game(Dictionary<Object, Object> info);
+        void on_cmd_enter_server(Dictionary<Object, Object> server);
+        void on_cmd_check_heart();
+        void on_cmd_all_types(object a, bool b, byte c, sbyte d, ushort e, short f, uint g, int h, ulong i, long j, long k, float l, double m, string n, byte[] o, ArrayList p, Dictionary<Object, Object> q);
+    }
+
+    public class TunmProtos {
+        public const string CMD_INTERNAL_AUTH = "cmd_internal_auth";
+        public const string CMD_AGENT_IDENTITY = "cmd_agent_identity";
+        public const string CMD_LOGIN = "cmd_login";
+        public const string MSG_ENTER_GAME = "msg_enter_game";
+        public const string CMD_ENTER_SERVER = "cmd_enter_server";
+        public const string CMD_CHECK_HEART = "cmd_check_heart";
+        public const string CMD_ALL_TYPES = "cmd_all_types";
+
+        public static void send_cmd_internal_auth(ref TunmBuffer buffer, string server, string key) {
+            var infos = new ArrayList { server, key };
+            TunmEncode.encode_proto(ref buffer, CMD_INTERNAL_AUTH, infos);
+        }
+
+        public static void send_cmd_agent_identity(ref TunmBuffer buffer, ushort code_type, uint code_id) {
+            var infos = new ArrayList { code_type, code_id };
+            TunmEncode.encode_proto(ref buffer, CMD_AGENT_IDENTITY, infos);
+        }
+
+        public static void send_cmd_login(ref TunmBuffer buffer, Dictionary<Object, Object> info) {
+            var infos = new ArrayList { info };
+            TunmEncode.encode_proto(ref buffer, CMD_LOGIN, infos);
+        }
+
+        public static void send_msg_enter_game(ref TunmBuffer buffer, Dictionary<Object, Object> info) {
+            var infos = new ArrayList { info };
+            TunmEncode.encode_proto(ref buffer, MSG_ENTER_GAME, infos);
+        }
+
+        public static void send_cmd_enter_server(ref TunmBuffer buffer, Dictionary<Object, Object> server) {
+            var infos = new ArrayList { server };
+            TunmEncode.encode_proto(ref buffer, CMD_ENTER_SERVER, infos);
+        }
+
+        public static void send_cmd_check_heart(ref TunmBuffer buffer) {
+            var infos = new ArrayList();
+            TunmEncode.encode_proto(ref buffer, CMD_CHECK_HEART, infos);
+        }
+
+        public static void send_cmd_all_types(ref TunmBuffer buffer, object a, bool b, byte c, sbyte d, ushort e, short f, uint g, int h, ulong i, long j, long k, float l, double m, string n, byte[] o, ArrayList p, Dictionary<Object, Object> q) {
+            var infos = new ArrayList { a, b, c, (long)d, e, f, g, h, i, j, k, l, m, n, o, p, q };
+            TunmEncode.encode_proto(ref buffer, CMD_ALL_TYPES, infos);
+        }
+
+        // call `handler.on_<proto>(args...)`, return false if the proto is unknown
+        public static bool dispatch(string name, ArrayList args, ITunmHandler handler) {
+            switch (name) {
+                case CMD_INTERNAL_AUTH:
+                    if (args.Count != 2) {
+                        return false;
+                    }
+                    handler.on_cmd_internal_auth((string)args[0], (string)args[1]);
+                    return true;
+                case CMD_AGENT_IDENTITY:
+                    if (args.Count != 2) {
+                        return false;
+                    }
+                    handler.on_cmd_agent_identity(Convert.ToUInt16(args[0]), Convert.ToUInt32(args[1]));
+                    return true;
+                case CMD_LOGIN:
+                    if (args.Count != 1) {
+                        return false;
+                    }
+                    handler.on_cmd_login((Dictionary<Object, Object>)args[0]);
+                    return true;
+                case MSG_ENTER_GAME:
+                    if (args.Count != 1) {
+                        return false;
+                    }
+                    handler.on_msg_enter_game((Dictionary<Object, Object>)args[0]);
+                    return true;
+                case CMD_ENTER_SERVER:
+                    if (args.Count != 1) {
+                        return false;
+                    }
+                    handler.on_cmd_enter_server((Dictionary<Object, Object>)args[0]);
+                    return true;
+                case CMD_CHECK_HEART:
+                    if (args.Count != 0) {
+                        return false;
+                    }
+                    handler.on_cmd_check_heart();
+                    return true;
+                case CMD_ALL_TYPES:
+                    if (args.Count != 17) {
+                        return false;
+                    }
+                    handler.on_cmd_all_types(args[0], Convert.ToBoolean(args[1]), Convert.ToByte(args[2]), Convert.ToSByte(args[3]), Convert.ToUInt16(args[4]), Convert.ToInt16(args[5]), Convert.ToUInt32(args[6]), Convert.ToInt32(args[7]), Convert.ToUInt64(args[8]), Convert.ToInt64(args[9]), Convert.ToInt64(args[10]), Convert.ToSingle(args[11]), Convert.ToDouble(args[12]), (string)args[13], (byte[])args[14], (ArrayList)args[15], (Dictionary<Object, Object>)args[16]);
+                    return true;
+            }
+            return false;
+        }
+    }
+}
@@ -0,0 +1,72 @@
+# Code generated by tunm_codegen. DO NOT EDIT.
+
+const Core = preload("res://proto/core.gd")
+
+const CMD_INTERNAL_AUTH = "cmd_internal_auth"
+const CMD_AGENT_IDENTITY = "cmd_agent_identity"
+const CMD_LOGIN = "cmd_login"
+const MSG_ENTER_GAME = "msg_enter_game"
+const CMD_ENTER_SERVER = "cmd_enter_server"
+const CMD_CHECK_HEART = "cmd_check_heart"
+const CMD_ALL_TYPES = "cmd_all_types"
+
+static func send_cmd_internal_auth(buffer: Buffer, server: String, key: String) -> void:
+	Core.PBPacker.encode_proto(buffer, CMD_INTERNAL_AUTH, [server, key])
+
+static func send_cmd_agent_identity(buffer: Buffer, code_type: int, code_id: int) -> void:
+	Core.PBPacker.encode_proto(buffer, CMD_AGENT_IDENTITY, [code_type, code_id])
+
+static func send_cmd_login(buffer: Buffer, info: Dictionary) -> void:
+	Core.PBPacker.encode_proto(buffer, CMD_LOGIN, [info])
+
+static func send_msg_enter_game(buffer: Buffer, info: Dictionary) -> void:
+	Core.PBPacker.encode_proto(buffer, MSG_ENTER_GAME, [info])
+
+static func send_cmd_enter_server(buffer: Buffer, server: Dictionary) -> void:
+	Core.PBPacker.encode_proto(buffer, CMD_ENTER_SERVER, [server])
+
+static func send_cmd_check_heart(buffer: Buffer) -> void:
+	Core.PBPacker.encode_proto(buffer, CMD_CHECK_HEART, [])
+
+static func send_cmd_all_types(buffer: Buffer, a, b: bool, c: int, d: int, e: int, f: int, g: int, h: int, i: int, j: int, k: int, l: float, m: float, n: String, o: PoolByteArray, p: Array, q: Dictionary) -> void:
+	Core.PBPacker.encode_proto(buffer, CMD_ALL_TYPES, [a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, p, q])
+
+# call `handler.on_<proto>(args...)`, return false if the proto is unknown
+static func dispatch(name: String, args: Array, handler: Object) -> bool:
+	match name:
+		CMD_INTERNAL_AUTH:
+			if len(args) != 2 or not handler.has_method("on_cmd_internal_auth"):
+				return false
+			handler.on_cmd_internal_auth(args[0], args[1])
+			return true
+		CMD_AGENT_IDENTITY:
+			if len(args) != 2 or not handler.has_method("on_cmd_agent_identity"):
+				return false
+			handler.on_cmd_agent_identity(args[0], args[1])
+			return true
+		CMD_LOGIN:
+			if len(args) != 1 or not handler.has_method("on_cmd_login"):
+				return false
+			handler.on_cmd_login(args[0])
+			return true
+		MSG_ENTER_GAME:
+			if len(args) != 1 or not handler.has_method("on_msg_enter_game"):
+				return false
+			handler.on_msg_enter_game(args[0])
+			return true
+		CMD_ENTER_SERVER:
+			if len(args) != 1 or not handler.has_method("on_cmd_enter_server"):
+				return false
+			handler.on_cmd_enter_server(args[0])
+			return true
+		CMD_CHECK_HEART:
+			if len(args) != 0 or not handler.has_method("on_cmd_check_heart"):
+				return false
+			handler.on_cmd_check_heart()
+			return true
+		CMD_ALL_TYPES:
+			if len(args) != 17 or not handler.has_method("on_cmd_all_types"):
+				return false
+			handler.on_cmd_all_types(args[0], args[1], args[2], args[3], args[4], args[5], args[6], args[7], args[8], args[9], args[10], args[11], args[12], args[13], args[14], args[15], args[16])
+			return true
+	return false
@@ -0,0 +1,140 @@
+// Code generated by tunm_codegen. DO NOT EDIT.
+
+var TunmProtos = {
+    CMD_INTERNAL_AUTH: "cmd_internal_auth",
+    CMD_AGENT_IDENTITY: "cmd_agent_identity",
+    CMD_LOGIN: "cmd_login",
+    MSG_ENTER_GAME: "msg_enter_game",
+    CMD_ENTER_SERVER: "cmd_enter_server",
+    CMD_CHECK_HEART: "cmd_check_heart",
+    CMD_ALL_TYPES: "cmd_all_types",
+};
+
+/**
+ * @param {RtProto} rt
+ * @param {ByteBuffer} buffer
+ * @param {string} server
+ * @param {string} key
+ */
+TunmProtos.send_cmd_internal_auth = function (rt, buffer, server, key) {
+    return rt.encode_proto(buffer, TunmProtos.CMD_INTERNAL_AUTH, [server, key])
+}
+
+/**
+ * @param {RtProto} rt
+ * @param {ByteBuffer} buffer
+ * @param {number} code_type
+ * @param {number} code_id
+ */
+TunmProtos.send_cmd_agent_identity = function (rt, buffer, code_type, code_id) {
+    return rt.encode_proto(buffer, TunmProtos.CMD_AGENT_IDENTITY, [code_type, code_id])
+}
+
+/**
+ * @param {RtProto} rt
+ * @param {ByteBuffer} buffer
+ * @param {Object} info
+ */
+TunmProtos.send_cmd_login = function (rt, buffer, info) {
+    return rt.encode_proto(buffer, TunmProtos.CMD_LOGIN, [info])
+}
+
+/**
+ * @param {RtProto} rt
+ * @param {ByteBuffer} buffer
+ * @param {Object} info
+ */
+TunmProtos.send_msg_enter_game = function (rt, buffer, info) {
+    return rt.encode_proto(buffer, TunmProtos.MSG_ENTER_GAME, [info])
+}
+
+/**
+ * @param {RtProto} rt
+ * @param {ByteBuffer} buffer
+ * @param {Object} server
+ */
+TunmProtos.send_cmd_enter_server = function (rt, buffer, server) {
+    return rt.encode_proto(buffer, TunmProtos.CMD_ENTER_SERVER, [server])
+}
+
+/**
+ * @param {RtProto} rt
+ * @param {ByteBuffer} buffer
+ */
+TunmProtos.send_cmd_check_heart = function (rt, buffer) {
+    return rt.encode_proto(buffer, TunmProtos.CMD_CHECK_HEART, [])
+}
+
+/**
+ * @param {RtProto} rt
+ * @param {ByteBuffer} buffer
+ * @param {*} a
+ * @param {boolean} b
+ * @param {number} c
+ * @param {number} d
+ * @param {number} e
+ * @param {number} f
+ * @param {number} g
+ * @param {number} h
+ * @param {number} i
+ * @param {number} j
+ * @param {number} k
+ * @param {number} l
+ * @param {number} m
+ * @param {string} n
+ * @param {Uint8Array} o
+ * @param {Array} p
+ * @param {Object} q
+ */
+TunmProtos.send_cmd_all_types = function (rt, buffer, a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, p, q) {
+    return rt.encode_proto(buffer, TunmProtos.CMD_ALL_TYPES, [a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, p, q])
+}
+
+// call `handler.on_<proto>(args...)`, return false if the proto is unknown
+TunmProtos.dispatch = function (proto, list, handler) {
+    switch (proto) {
+        case TunmProtos.CMD_INTERNAL_AUTH:
+            if (list.length != 2 || !handler.on_cmd_internal_auth) {
+                return false
+            }
+            handler.on_cmd_internal_auth(list[0], list[1])
+            return true
+        case TunmProtos.CMD_AGENT_IDENTITY:
+            if (list.length != 2 || !handler.on_cmd_agent_identity) {
+                return false
+            }
+            handler.on_cmd_agent_identity(list[0], list[1])
+            return true
+        case TunmProtos.CMD_LOGIN:
+            if (list.length != 1 || !handler.on_cmd_login) {
+                return false
+            }
+            handler.on_cmd_login(list[0])
+            return true
+        case TunmProtos.MSG_ENTER_GAME:
+            if (list.length != 1 || !handler.on_msg_enter_game) {
+                return false
+            }
+            handler.on_msg_enter_game(list[0])
+            return true
+        case TunmProtos.CMD_ENTER_SERVER:
+            if (list.length != 1 || !handler.on_cmd_enter_server) {
+                return false
+            }
+            handler.on_cmd_enter_server(list[0])
+            return true
+        case TunmProtos.CMD_CHECK_HEART:
+            if (list.length != 0 || !handler.on_cmd_check_heart) {
+                return false
+            }
+            handler.on_cmd_check_heart()
+            return true
+        case TunmProtos.CMD_ALL_TYPES:
+            if (list.length != 17 || !handler.on_cmd_all_types) {
+                return false
+            }
+            handler.on_cmd_all_types(list[0], list[1], list[2], list[3], list[4], list[5], list[6], list[7], list[8], list[9], list[10], list[11], list[12], list[13], list[14], list[15], list[16])
+            return true
+    }
+    return false
+}
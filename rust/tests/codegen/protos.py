@@ -0,0 +1,89 @@
+# Code generated by tunm_codegen. DO NOT EDIT.
+from typing import Any
+
+from tunm_proto import ByteBuffer, TPPacker
+
+CMD_INTERNAL_AUTH = "cmd_internal_auth"
+CMD_AGENT_IDENTITY = "cmd_agent_identity"
+CMD_LOGIN = "cmd_login"
+MSG_ENTER_GAME = "msg_enter_game"
+CMD_ENTER_SERVER = "cmd_enter_server"
+CMD_CHECK_HEART = "cmd_check_heart"
+CMD_ALL_TYPES = "cmd_all_types"
+
+
+def send_cmd_internal_auth(buffer: ByteBuffer, server: str, key: str):
+    TPPacker.encode_proto(buffer, CMD_INTERNAL_AUTH, [server, key])
+
+
+def send_cmd_agent_identity(buffer: ByteBuffer, code_type: int, code_id: int):
+    TPPacker.encode_proto(buffer, CMD_AGENT_IDENTITY, [code_type, code_id])
+
+
+def send_cmd_login(buffer: ByteBuffer, info: dict):
+    TPPacker.encode_proto(buffer, CMD_LOGIN, [info])
+
+
+def send_msg_enter_game(buffer: ByteBuffer, info: dict):
+    TPPacker.encode_proto(buffer, MSG_ENTER_GAME, [info])
+
+
+def send_cmd_enter_server(buffer: ByteBuffer, server: dict):
+    TPPacker.encode_proto(buffer, CMD_ENTER_SERVER, [server])
+
+
+def send_cmd_check_heart(buffer: ByteBuffer):
+    TPPacker.encode_proto(buffer, CMD_CHECK_HEART, [])
+
+
+def send_cmd_all_types(buffer: ByteBuffer, a: Any, b: bool, c: int, d: int, e: int, f: int, g: int, h: int, i: int, j: int, k: int, l: float, m: float, n: str, o: bytes, p: list, q: dict):
+    TPPacker.encode_proto(buffer, CMD_ALL_TYPES, [a, b, c, d, e, f, g, h, i, j, k, float(l), float(m), n, o, p, q])
+
+
+def dispatch(name: str, args: list, handler) -> bool:
+    '''
+    call `handler.on_<proto>(args...)`, return False if the proto is unknown
+    '''
+    if name == CMD_INTERNAL_AUTH:
+        func = getattr(handler, "on_cmd_internal_auth", None)
+        if func is None or len(args) != 2:
+            return False
+        func(args[0], args[1])
+        return True
+    if name == CMD_AGENT_IDENTITY:
+        func = getattr(handler, "on_cmd_agent_identity", None)
+        if func is None or len(args) != 2:
+            return False
+        func(args[0], args[1])
+        return True
+    if name == CMD_LOGIN:
+        func = getattr(handler, "on_cmd_login", None)
+        if func is None or len(args) != 1:
+            return False
+        func(args[0])
+        return True
+    if name == MSG_ENTER_GAME:
+        func = getattr(handler, "on_msg_enter_game", None)
+        if func is None or len(args) != 1:
+            return False
+        func(args[0])
+        return True
+    if name == CMD_ENTER_SERVER:
+        func = getattr(handler, "on_cmd_enter_server", None)
+        if func is None or len(args) != 1:
+            return False
+        func(args[0])
+        return True
+    if name == CMD_CHECK_HEART:
+        func = getattr(handler, "on_cmd_check_heart", None)
+        if func is None or len(args) != 0:
+            return False
+        func()
+        return True
+    if name == CMD_ALL_TYPES:
+        func = getattr(handler, "on_cmd_all_types", None)
+        if func is None or len(args) != 17:
+            return False
+        func(args[0], args[1], args[2], args[3], args[4], args[5], args[6], args[7], args[8], args[9], args[10], args[11], args[12], args[13], args[14], args[15], args[16])
+        return True
+    return False
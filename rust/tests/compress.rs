@@ -0,0 +1,112 @@
+#[macro_use]
+extern crate tunm_proto as tunm;
+
+use std::io::Write;
+use std::sync::Arc;
+
+use tunm::{Buffer, CompressConfig, Compression, ErrorKind, StrDict, Value};
+
+fn snapshot(rows: usize) -> Vec<Value> {
+    let rows = (0..rows).map(|i| value!({"id": (i as u32), "name": "player", "hp": 100u16, "pos": [1, 2, 3]}));
+    vec![Value::Arr(rows.collect())]
+}
+
+fn buffer_with(config: CompressConfig) -> Buffer {
+    let mut buffer = Buffer::new();
+    buffer.set_compress(Some(config));
+    buffer
+}
+
+fn encoded_len(buffer: &mut Buffer, args: Vec<Value>) -> usize {
+    let start = buffer.data_len();
+    tunm::encode_proto(buffer, &"cmd_snapshot".to_string(), args).unwrap();
+    buffer.data_len() - start
+}
+
+#[test]
+fn test_compress_round_trip() {
+    let plain = encoded_len(&mut Buffer::new(), snapshot(500));
+    for algorithm in [Compression::Lz4, Compression::Zstd] {
+        let mut buffer = buffer_with(CompressConfig::new(algorithm));
+        let packed = encoded_len(&mut buffer, snapshot(500));
+        assert!(packed * 2 < plain, "{:?} {} of {}", algorithm, packed, plain);
+        // below the threshold the body goes as it is, behind one flag byte
+        assert_eq!(encoded_len(&mut buffer, snapshot(1)), encoded_len(&mut Buffer::new(), snapshot(1)) + 1);
+
+        let mut reader = buffer_with(CompressConfig::new(algorithm));
+        reader.write_all(&buffer.drain_all_collect()).unwrap();
+        assert_eq!(tunm::decode_proto(&mut reader).unwrap().1, snapshot(500));
+        assert_eq!(tunm::decode_proto(&mut reader).unwrap().1, snapshot(1));
+        assert!(reader.is_empty());
+    }
+}
+
+#[test]
+fn test_compress_with_session_and_dict() {
+    let dict = Arc::new(StrDict::new(1, ["id", "name", "hp", "pos"]).unwrap());
+    let make = || {
+        let mut buffer = buffer_with(CompressConfig { threshold: 0, ..CompressConfig::new(Compression::Zstd) });
+        buffer.set_str_session(true);
+        buffer.set_str_dict(Some(dict.clone()));
+        buffer
+    };
+    let (mut writer, mut reader) = (make(), make());
+    for rows in [50, 60] {
+        tunm::encode_proto(&mut writer, &"cmd_snapshot".to_string(), snapshot(rows)).unwrap();
+        reader.write_all(&writer.drain_all_collect()).unwrap();
+        assert_eq!(tunm::decode_proto(&mut reader).unwrap().1, snapshot(rows));
+    }
+    assert_eq!(reader.str_arr, vec!["player".to_string()]);
+}
+
+/// a zstd message with the body size in its header replaced by `size`
+fn with_size(size: u64) -> Buffer {
+    let mut buffer = buffer_with(CompressConfig::new(Compression::Zstd));
+    tunm::encode_proto(&mut buffer, &"cmd_snapshot".to_string(), snapshot(200)).unwrap();
+    let mut reader = Buffer::new();
+    tunm::decode_str_raw(&mut buffer, tunm::TYPE_STR).unwrap();
+    let flag = buffer.drain_collect(buffer.get_rpos() + 1);
+    reader.write_all(&flag).unwrap();
+    tunm::decode_varint(&mut buffer).unwrap();
+    tunm::encode_varint(&mut reader, &Value::U64(size)).unwrap();
+    reader.write_all(buffer.get_write_data()).unwrap();
+    reader.set_compress(Some(CompressConfig { max_size: 64 * 1024, ..CompressConfig::new(Compression::Zstd) }));
+    reader
+}
+
+#[test]
+fn test_decompress_limits() {
+    let real = {
+        let mut buffer = Buffer::new();
+        tunm::encode_proto(&mut buffer, &"cmd_snapshot".to_string(), snapshot(200)).unwrap();
+        tunm::decode_str_raw(&mut buffer, tunm::TYPE_STR).unwrap();
+        buffer.data_len() as u64
+    };
+    assert_eq!(tunm::decode_proto(&mut with_size(real)).unwrap().1, snapshot(200));
+    let err = tunm::decode_proto(&mut with_size(1 << 40)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BufferOverMaxError);
+    let err = tunm::decode_proto(&mut with_size(real - 1)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ParseError);
+    let err = tunm::decode_proto(&mut with_size(real + 1)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ParseError);
+}
+
+#[test]
+fn test_corrupt_compressed_body() {
+    for algorithm in [Compression::Lz4, Compression::Zstd] {
+        let mut buffer = buffer_with(CompressConfig::new(algorithm));
+        tunm::encode_proto(&mut buffer, &"cmd_snapshot".to_string(), snapshot(200)).unwrap();
+        let data = buffer.drain_all_collect();
+        for at in (20..data.len()).step_by(7) {
+            let mut bad = data.clone();
+            bad[at] ^= 0x5A;
+            let mut reader = buffer_with(CompressConfig::new(algorithm));
+            reader.write_all(&bad).unwrap();
+            // any error is fine, it must not panic
+            let _ = tunm::decode_proto(&mut reader);
+        }
+        let mut reader = buffer_with(CompressConfig::new(algorithm));
+        reader.write_all(&data[..data.len() - 1]).unwrap();
+        assert!(tunm::decode_proto(&mut reader).is_err());
+    }
+}
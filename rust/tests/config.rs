@@ -0,0 +1,76 @@
+#[macro_use]
+extern crate tunm_proto as tunm;
+
+use std::io::{Read, Write};
+
+use tunm::{Buffer, BufferConfig, ErrorKind};
+
+fn config(max_size: usize) -> BufferConfig {
+    BufferConfig { initial_capacity: 512, max_size, ..BufferConfig::new() }
+}
+
+#[test]
+fn test_with_capacity_and_growth() {
+    let buffer = Buffer::with_capacity(100);
+    assert_eq!(buffer.len(), 100);
+    assert_eq!(Buffer::new().get_config(), &BufferConfig::default());
+
+    let mut buffer = Buffer::with_config(BufferConfig { growth_factor: 1, ..config(usize::MAX) });
+    buffer.write_all(&[1; 3000]).unwrap();
+    assert_eq!(buffer.len(), 3000);
+    let mut buffer = Buffer::with_config(BufferConfig { growth_factor: 3, ..config(usize::MAX) });
+    buffer.write_all(&[1; 3000]).unwrap();
+    assert_eq!(buffer.len(), 9000);
+}
+
+#[test]
+fn test_max_size() {
+    let mut buffer = Buffer::with_config(config(1000));
+    buffer.write_all(&[1; 800]).unwrap();
+    let err = buffer.write_all(&[1; 300]).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::OutOfMemory);
+    assert_eq!(buffer.data_len(), 800);
+
+    // only unread data counts
+    buffer.read_exact(&mut [0; 500]).unwrap();
+    buffer.write_all(&[2; 600]).unwrap();
+    assert_eq!(buffer.data_len(), 900);
+    assert!(buffer.len() <= 1000);
+    assert_eq!(buffer.get_read_array(4096).len(), 100);
+
+    let err = tunm::encode_proto(&mut buffer, &"cmd_big".to_string(), tunm_args![vec![0u8; 200]]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BufferOverMaxError);
+    assert_eq!(buffer.data_len(), 900);
+
+    let mut buffer = Buffer::with_config(config(1000));
+    buffer.write_all(&[0; 1000]).unwrap();
+    assert!(buffer.get_read_array(10).is_empty());
+
+    // a size past usize is over any max size
+    let mut buffer = Buffer::with_config(config(usize::MAX));
+    buffer.write_all(&[1; 10]).unwrap();
+    assert_eq!(buffer.spare_capacity_mut(usize::MAX).unwrap_err().kind(), ErrorKind::BufferOverMaxError);
+    assert_eq!(buffer.data_len(), 10);
+}
+
+#[test]
+fn test_shrink_policy() {
+    let snapshot = vec![7u8; 200 * 1024];
+    let mut buffer = Buffer::with_config(BufferConfig {
+        shrink_threshold: 512 * 1024,
+        shrink_target: 256 * 1024,
+        ..BufferConfig::new()
+    });
+    buffer.write_all(&snapshot).unwrap();
+    let grown = buffer.len();
+    buffer.read_exact(&mut vec![0; snapshot.len()]).unwrap();
+    buffer.fix_buffer();
+    // under the threshold the room is kept for the next snapshot
+    assert_eq!(buffer.len(), grown);
+
+    buffer.set_config(BufferConfig { shrink_threshold: 100 * 1024, shrink_target: 64 * 1024, ..BufferConfig::new() });
+    buffer.write_all(&snapshot).unwrap();
+    buffer.read_exact(&mut vec![0; snapshot.len()]).unwrap();
+    buffer.fix_buffer();
+    assert_eq!(buffer.len(), 64 * 1024);
+}
@@ -0,0 +1,178 @@
+#[macro_use]
+extern crate tunm_proto as tunm;
+
+use std::collections::HashMap;
+
+use tunm::{Buffer, ErrorKind, TunmDecode, TunmEncode, Value};
+
+#[derive(TunmEncode, TunmDecode, Debug, PartialEq, Default)]
+struct Player {
+    name: String,
+    #[tunm(rename = "lv")]
+    level: u16,
+    tags: Vec<String>,
+    guild: Option<String>,
+    #[tunm(skip)]
+    session: u64,
+    #[tunm(default)]
+    gold: i64,
+}
+
+#[derive(TunmEncode, TunmDecode, Debug, PartialEq)]
+#[tunm(array)]
+struct Pos {
+    x: f32,
+    y: f32,
+    #[tunm(default)]
+    z: f32,
+}
+
+#[derive(TunmEncode, TunmDecode, Debug, PartialEq)]
+struct Pair(u8, String);
+
+#[derive(TunmEncode, TunmDecode, Debug, PartialEq)]
+struct Unit;
+
+#[derive(TunmEncode, TunmDecode, Debug, PartialEq)]
+struct Id {
+    id: u32,
+}
+
+#[derive(TunmEncode, TunmDecode, Debug, PartialEq)]
+struct Wrapper<T> {
+    inner: T,
+    raw: Value,
+}
+
+/// the path of the crate as it is named here
+#[derive(TunmEncode, TunmDecode, Debug, PartialEq)]
+#[tunm(crate = "tunm")]
+struct Renamed {
+    id: u32,
+}
+
+#[derive(TunmEncode, TunmDecode, Debug, PartialEq)]
+enum Event {
+    Ping,
+    #[tunm(rename = "chat")]
+    Chat(String),
+    Move(i32, i32),
+    Login { account: String, #[tunm(skip)] token: u32 },
+    #[tunm(array)]
+    Attack { target: u32, damage: u16 },
+}
+
+fn encoded(value: &Value) -> Vec<u8> {
+    let mut buffer = Buffer::new();
+    tunm::encode_field(&mut buffer, value).unwrap();
+    let mut out = buffer.get_write_data().to_vec();
+    // the string table is not part of the field bytes
+    for s in &buffer.str_arr {
+        out.extend(s.as_bytes());
+    }
+    out
+}
+
+fn round_trip<T: TunmEncode + TunmDecode>(val: &T) -> T {
+    let mut buffer = Buffer::new();
+    tunm::encode_proto(&mut buffer, &"cmd_derive".to_string(), vec![val.to_value()]).unwrap();
+    let (_, mut args) = tunm::decode_proto(&mut buffer).unwrap();
+    T::from_value(args.remove(0)).unwrap()
+}
+
+#[test]
+fn test_struct_as_map() {
+    let player = Player {
+        name: "tunm".to_string(),
+        level: 3,
+        tags: vec!["a".to_string()],
+        guild: None,
+        session: 99,
+        gold: 10,
+    };
+    let expect = value!({"name": "tunm", "lv": 3u16, "tags": ["a"], "guild": nil, "gold": 10i64});
+    assert_eq!(player.to_value().to_string(), expect.to_string());
+    // single entry maps have a fixed order on the wire
+    assert_eq!(encoded(&Id { id: 5 }.to_value()), encoded(&value!({"id": 5u32})));
+
+    let wrapper = Wrapper { inner: 5u8, raw: Value::Raw(vec![1]) };
+    assert_eq!(wrapper.to_value().to_string(), r#"{"inner": u8(5), "raw": b"\x01"}"#);
+    assert_eq!(round_trip(&player), Player { session: 0, ..player });
+    assert_eq!(round_trip(&wrapper), wrapper);
+}
+
+#[test]
+fn test_missing_and_default_fields() {
+    // guild is an Option and gold has `default`, both may be missing
+    let player = Player::from_value(value!({"name": "a", "lv": 1u16, "tags": []})).unwrap();
+    assert_eq!(player, Player { name: "a".to_string(), level: 1, ..Default::default() });
+
+    let err = Player::from_value(value!({"name": "a", "tags": []})).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MissingError);
+    assert_eq!(err.to_string(), "missing field: Player.lv");
+
+    let err = Player::from_value(value!({"name": 1u8, "lv": 1u16, "tags": []})).unwrap_err();
+    assert_eq!(err.to_string(), "value type not match: expect str, found u8");
+    let err = Player::from_value(value!({"name": "a", "lv": 70000, "tags": []})).unwrap_err();
+    assert_eq!(err.to_string(), "number out of range: 70000 for u16");
+    assert!(Player::from_value(value!([])).is_err());
+}
+
+#[test]
+fn test_struct_as_array() {
+    let pos = Pos { x: 1.5, y: -2.0, z: 0.25 };
+    assert_eq!(encoded(&pos.to_value()), encoded(&value!([1.5f32, -2.0f32, 0.25f32])));
+    assert_eq!(round_trip(&pos), pos);
+    assert_eq!(Pos::from_value(value!([1.0f32, 2.0f32])).unwrap(), Pos { x: 1.0, y: 2.0, z: 0.0 });
+    assert_eq!(Pos::from_value(value!([1.0f32])).unwrap_err().kind(), ErrorKind::MissingError);
+    let err = Pos::from_value(value!([1.0f32, 2.0f32, 3.0f32, 4.0f32])).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ParseError);
+    assert_eq!(err.to_string(), "too many items: Pos expect 3, found 4");
+
+    let pair = Pair(7, "x".to_string());
+    assert_eq!(encoded(&pair.to_value()), encoded(&value!([7u8, "x"])));
+    assert_eq!(round_trip(&pair), pair);
+    assert_eq!(Pair::from_value(value!([7u8, "x", nil])).unwrap_err().kind(), ErrorKind::ParseError);
+
+    assert_eq!(Unit.to_value(), Value::Nil);
+    assert_eq!(round_trip(&Unit), Unit);
+}
+
+#[test]
+fn test_enum_as_tagged_map() {
+    let cases = vec![
+        (Event::Ping, value!({"Ping": nil})),
+        (Event::Chat("hi".to_string()), value!({"chat": "hi"})),
+        (Event::Move(1, -1), value!({"Move": [1, -1]})),
+        (Event::Login { account: "a".to_string(), token: 0 }, value!({"Login": {"account": "a"}})),
+        (Event::Attack { target: 9, damage: 3 }, value!({"Attack": [9u32, 3u16]})),
+    ];
+    for (event, expect) in cases {
+        assert_eq!(event.to_value().to_string(), expect.to_string());
+        assert_eq!(encoded(&event.to_value()), encoded(&expect));
+        assert_eq!(round_trip(&event), event);
+    }
+    let err = Event::from_value(value!({"Jump": nil})).unwrap_err();
+    assert_eq!(err.to_string(), "unknown variant: Event \"Jump\"");
+    assert!(Event::from_value(value!({"Ping": nil, "chat": "x"})).is_err());
+    let err = Event::from_value(value!({"Move": [1, 2, 3]})).unwrap_err();
+    assert_eq!(err.to_string(), "too many items: Event::Move expect 2, found 3");
+}
+
+#[test]
+fn test_std_types() {
+    let mut map = HashMap::new();
+    map.insert("k".to_string(), vec![Some(1u32), None]);
+    assert_eq!(round_trip(&map), map);
+    assert_eq!(u8::from_value(Value::Varint(255)).unwrap(), 255);
+    assert!(u8::from_value(Value::Varint(256)).is_err());
+    assert_eq!(f64::from_value(Value::Float(0.5)).unwrap(), 0.5);
+    assert_eq!(Box::new(3i8).to_value(), Value::I8(3));
+}
+
+#[test]
+fn test_crate_path() {
+    let renamed = Renamed { id: 7 };
+    assert_eq!(renamed.to_value(), value!({"id": 7u32}));
+    assert_eq!(round_trip(&renamed), renamed);
+}
@@ -0,0 +1,97 @@
+#[macro_use]
+extern crate tunm_proto as tunm;
+
+use std::io::Write;
+use std::sync::Arc;
+
+use tunm::{Buffer, ErrorKind, StrDict, Value};
+
+fn dict(version: u32) -> Arc<StrDict> {
+    Arc::new(StrDict::new(version, ["name", "code_type", "server"]).unwrap())
+}
+
+fn buffer_with(dict: Option<Arc<StrDict>>, session: bool) -> Buffer {
+    let mut buffer = Buffer::new();
+    buffer.set_str_session(session);
+    buffer.set_str_dict(dict);
+    buffer
+}
+
+fn args() -> Vec<Value> {
+    tunm_args![{"name": "server", "code_type": "tunm"}, "server", "other"]
+}
+
+fn encoded(buffer: &mut Buffer) -> Vec<u8> {
+    tunm::encode_proto(buffer, &"cmd_state".to_string(), args()).unwrap();
+    buffer.drain_all_collect()
+}
+
+fn decoded(buffer: &mut Buffer, data: &[u8]) -> tunm::RpResult<(String, Vec<Value>)> {
+    buffer.write_all(data).unwrap();
+    let result = tunm::decode_proto(buffer);
+    buffer.clear();
+    result
+}
+
+#[test]
+fn test_dict_strings_are_not_sent() {
+    let plain = encoded(&mut Buffer::new());
+    let data = encoded(&mut buffer_with(Some(dict(7)), false));
+    let text = String::from_utf8_lossy(&data);
+    assert!(!text.contains("code_type") && !text.contains("server"), "{:?}", text);
+    assert!(text.contains("tunm") && text.contains("other"));
+    assert!(data.len() + "name".len() + "code_type".len() + "server".len() <= plain.len());
+
+    let read = decoded(&mut buffer_with(Some(dict(7)), false), &data).unwrap();
+    assert_eq!(read, ("cmd_state".to_string(), args()));
+}
+
+#[test]
+fn test_dict_version_mismatch() {
+    let data = encoded(&mut buffer_with(Some(dict(7)), false));
+    let err = decoded(&mut buffer_with(Some(dict(8)), false), &data).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::StringTableError);
+    assert!(err.to_string().contains("peer has version 7"), "{}", err);
+
+    // a peer without the dictionary fails on the version instead of misreading indices
+    assert!(decoded(&mut Buffer::new(), &data).is_err());
+    let err = decoded(&mut buffer_with(Some(dict(7)), false), &encoded(&mut Buffer::new())).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::StringTableError);
+}
+
+#[test]
+fn test_dict_with_session() {
+    let mut writer = buffer_with(Some(dict(1)), true);
+    let mut reader = buffer_with(Some(dict(1)), true);
+    let first = encoded(&mut writer);
+    let second = encoded(&mut writer);
+    assert!(second.len() + "tunm".len() + "other".len() <= first.len());
+    assert_eq!(decoded(&mut reader, &first).unwrap().1, args());
+    assert_eq!(decoded(&mut reader, &second).unwrap().1, args());
+    assert_eq!(reader.str_arr, vec!["tunm".to_string(), "other".to_string()]);
+
+    let err = decoded(&mut buffer_with(Some(dict(2)), true), &first).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::StringTableError);
+}
+
+#[test]
+fn test_dict_unknown_index() {
+    let mut buffer = buffer_with(Some(dict(1)), false);
+    tunm::encode_sure_type(&mut buffer, tunm::TYPE_STR_IDX).unwrap();
+    tunm::encode_varint(&mut buffer, &Value::U64(2)).unwrap();
+    assert_eq!(tunm::decode_field(&mut buffer).unwrap(), value!("server"));
+    tunm::encode_sure_type(&mut buffer, tunm::TYPE_STR_IDX).unwrap();
+    tunm::encode_varint(&mut buffer, &Value::U64(3)).unwrap();
+    assert_eq!(tunm::decode_field(&mut buffer).unwrap_err().kind(), ErrorKind::StringTableError);
+}
+
+#[test]
+fn test_str_dict() {
+    let dict = StrDict::from_lines(3, "name\n\ncode_type\n").unwrap();
+    assert_eq!((dict.version(), dict.len()), (3, 2));
+    assert_eq!(dict.get(1), Some("code_type"));
+    assert_eq!(dict.index_of("name"), Some(0));
+    assert_eq!(dict.index_of("server"), None);
+    let err = StrDict::new(1, ["a", "b", "a"]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::StringTableError);
+}
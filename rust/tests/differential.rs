@@ -0,0 +1,306 @@
+//! Differential test against the python port in `../python`.
+//!
+//! Random `Value` trees are encoded in rust and decoded by python and the
+//! other way around, the first mismatch is shrunk to a minimal example.
+//! Skipped when `python3` (or `TUNM_PYTHON`) can't be started, the seed and
+//! the number of cases can be set with `TUNM_DIFF_SEED` / `TUNM_DIFF_CASES`.
+
+extern crate serde_json;
+extern crate tunm_proto as tunm;
+
+mod common;
+
+use common::{from_hex, to_hex};
+use serde_json::{json, Value as Json};
+use tunm::{Buffer, Value};
+
+use std::collections::HashMap;
+use std::env;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+const PROTO_NAME: &str = "cmd_differential";
+
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        // xorshift64*
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    fn below(&mut self, max: u64) -> u64 {
+        self.next() % max
+    }
+
+    fn int(&mut self) -> i64 {
+        const EDGES: [i64; 16] = [0, 1, -1, 63, 64, -64, -65, 127, 128, -128, -129, 255, 256,
+                                  i32::MIN as i64, i64::MIN, i64::MAX];
+        match self.below(3) {
+            0 => EDGES[self.below(EDGES.len() as u64) as usize],
+            1 => self.below(1 << 16) as i64 - (1 << 15),
+            _ => self.next() as i64 >> self.below(64),
+        }
+    }
+
+    fn text(&mut self) -> String {
+        const CHARS: [char; 8] = ['a', 'z', '0', ' ', '_', 'é', '中', '🎮'];
+        let len = if self.below(10) == 0 { 70 } else { self.below(8) };
+        (0..len).map(|_| CHARS[self.below(CHARS.len() as u64) as usize]).collect()
+    }
+
+    fn value(&mut self, depth: u32) -> Value {
+        let kinds = if depth == 0 { 13 } else { 15 };
+        match self.below(kinds) {
+            0 => Value::Nil,
+            1 => Value::Bool(self.below(2) == 1),
+            2 => Value::U8(self.next() as u8),
+            3 => Value::I8(self.next() as i8),
+            4 => Value::U16(self.next() as u16),
+            5 => Value::I16(self.next() as i16),
+            6 => Value::U32(self.next() as u32),
+            7 => Value::I32(self.next() as i32),
+            8 => Value::I64(self.int()),
+            9 => Value::Varint(self.int()),
+            // multiples of 1/8 and 1/64 survive the *1000 and *1000000 scaling exactly
+            10 => Value::Float((self.below(1 << 16) as i64 - (1 << 15)) as f32 / 8.0),
+            11 => Value::Double((self.below(1 << 24) as i64 - (1 << 23)) as f64 / 64.0),
+            12 => if self.below(2) == 0 {
+                Value::Str(self.text())
+            } else {
+                Value::Raw(self.text().into_bytes())
+            },
+            13 => Value::Arr((0..self.below(5)).map(|_| self.value(depth - 1)).collect()),
+            _ => {
+                // python dict keys must be hashable and distinct, keep them to strings
+                let mut map = HashMap::new();
+                for _ in 0..self.below(5) {
+                    map.insert(Value::Str(self.text()), self.value(depth - 1));
+                }
+                Value::Map(map)
+            }
+        }
+    }
+}
+
+/// the form both sides agree on, integer widths and float precision are not kept
+fn normalize(value: &Value) -> Json {
+    match value {
+        Value::Nil => json!({ "nil": null }),
+        Value::Bool(v) => json!({ "bool": v }),
+        Value::U8(v) => json!({ "int": v }),
+        Value::I8(v) => json!({ "int": v }),
+        Value::U16(v) => json!({ "int": v }),
+        Value::I16(v) => json!({ "int": v }),
+        Value::U32(v) => json!({ "int": v }),
+        Value::I32(v) => json!({ "int": v }),
+        Value::U64(v) => json!({ "int": *v as i64 }),
+        Value::I64(v) | Value::Varint(v) => json!({ "int": v }),
+        Value::Float(v) => json!({ "float": *v as f64 }),
+        Value::Double(v) => json!({ "float": v }),
+        Value::Str(v) => json!({ "str": v }),
+        Value::Raw(v) => json!({ "raw": to_hex(v) }),
+        Value::Arr(v) => json!({ "arr": v.iter().map(normalize).collect::<Vec<_>>() }),
+        Value::Map(v) => {
+            let pairs: Vec<Json> = v.iter().map(|(k, v)| json!([normalize(k), normalize(v)])).collect();
+            sort_pairs(json!({ "map": pairs }))
+        }
+    }
+}
+
+/// sort map pairs recursively so maps compare independent of the key order
+fn sort_pairs(json: Json) -> Json {
+    match json {
+        Json::Object(obj) => Json::Object(obj.into_iter().map(|(k, v)| {
+            let v = if k == "map" {
+                let mut pairs: Vec<Json> = v.as_array().unwrap().iter().cloned().map(sort_pairs).collect();
+                pairs.sort_by_key(|p| p.to_string());
+                Json::Array(pairs)
+            } else {
+                sort_pairs(v)
+            };
+            (k, v)
+        }).collect()),
+        Json::Array(arr) => Json::Array(arr.into_iter().map(sort_pairs).collect()),
+        other => other,
+    }
+}
+
+struct PythonPeer {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl PythonPeer {
+    fn start() -> Option<PythonPeer> {
+        let python = env::var("TUNM_PYTHON").unwrap_or_else(|_| "python3".to_string());
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let mut child = Command::new(python)
+            .arg(root.join("tests/differential/python_peer.py"))
+            .arg(root.join("../python"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .ok()?;
+        let stdin = child.stdin.take()?;
+        let stdout = BufReader::new(child.stdout.take()?);
+        let mut peer = PythonPeer { child, stdin, stdout };
+        // make sure the port imports before running any case
+        peer.request("encode", &json!({ "name": PROTO_NAME, "args": [] }).to_string()).ok()?;
+        Some(peer)
+    }
+
+    fn request(&mut self, cmd: &str, arg: &str) -> Result<Json, String> {
+        writeln!(self.stdin, "{} {}", cmd, arg).map_err(|e| e.to_string())?;
+        let mut line = String::new();
+        self.stdout.read_line(&mut line).map_err(|e| e.to_string())?;
+        let reply: Json = serde_json::from_str(&line).map_err(|e| format!("bad reply {:?}: {}", line, e))?;
+        match reply.get("ok") {
+            Some(ok) => Ok(ok.clone()),
+            None => Err(reply["err"].to_string()),
+        }
+    }
+
+    /// encode in rust, decode in python
+    fn check_rust_to_python(&mut self, args: &[Value]) -> Result<(), String> {
+        let mut buffer = Buffer::new();
+        tunm::encode_proto(&mut buffer, &PROTO_NAME.to_string(), args.to_vec()).map_err(|e| e.to_string())?;
+        let decoded = self.request("decode", &to_hex(buffer.get_write_data()))?;
+        let expect = json!({ "name": PROTO_NAME, "args": args.iter().map(normalize).collect::<Vec<_>>() });
+        if sort_pairs(decoded.clone()) != expect {
+            return Err(format!("python decoded {}", decoded));
+        }
+        Ok(())
+    }
+
+    /// encode in python, decode in rust
+    fn check_python_to_rust(&mut self, args: &[Value]) -> Result<(), String> {
+        let normalized: Vec<Json> = args.iter().map(normalize).collect();
+        let hex = self.request("encode", &json!({ "name": PROTO_NAME, "args": normalized }).to_string())?;
+        let mut buffer = Buffer::new();
+        buffer.write_all(&from_hex(hex.as_str().unwrap_or_default()).unwrap()).unwrap();
+        let (name, decoded) = tunm::decode_proto(&mut buffer).map_err(|e| format!("rust decode {}: {}", hex, e))?;
+        let decoded: Vec<Json> = decoded.iter().map(normalize).collect();
+        if name != PROTO_NAME || decoded != normalized || buffer.data_len() != 0 {
+            return Err(format!("rust decoded {} {:?} from {}", name, decoded, hex));
+        }
+        Ok(())
+    }
+
+    fn check(&mut self, args: &[Value]) -> Result<(), String> {
+        self.check_rust_to_python(args)?;
+        self.check_python_to_rust(args)
+    }
+}
+
+impl Drop for PythonPeer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// simpler variants of `value`, the smallest first
+fn shrink_value(value: &Value) -> Vec<Value> {
+    let mut out = Vec::new();
+    match value {
+        Value::Nil => return out,
+        Value::Arr(items) => {
+            out.extend(items.iter().cloned());
+            for list in shrink_list(items) {
+                out.push(Value::Arr(list));
+            }
+        }
+        Value::Map(map) => {
+            out.extend(map.values().cloned());
+            for key in map.keys() {
+                let mut smaller = map.clone();
+                smaller.remove(key);
+                out.push(Value::Map(smaller));
+            }
+            for (key, sub) in map {
+                for simpler in shrink_value(sub) {
+                    let mut smaller = map.clone();
+                    smaller.insert(key.clone(), simpler);
+                    out.push(Value::Map(smaller));
+                }
+            }
+        }
+        Value::Str(s) if !s.is_empty() => {
+            out.push(Value::Str(String::new()));
+            out.push(Value::Str(s.chars().take(s.chars().count() / 2).collect()));
+        }
+        Value::Raw(r) if !r.is_empty() => {
+            out.push(Value::Raw(vec![]));
+            out.push(Value::Raw(r[..r.len() / 2].to_vec()));
+        }
+        Value::Str(_) | Value::Raw(_) => {}
+        other => {
+            if normalize(other) != normalize(&Value::Varint(0)) {
+                out.push(Value::Varint(0));
+            }
+        }
+    }
+    out.insert(0, Value::Nil);
+    out
+}
+
+fn shrink_list(items: &[Value]) -> Vec<Vec<Value>> {
+    let mut out = Vec::new();
+    for i in 0..items.len() {
+        let mut smaller = items.to_vec();
+        smaller.remove(i);
+        out.push(smaller);
+    }
+    for (i, item) in items.iter().enumerate() {
+        for simpler in shrink_value(item) {
+            let mut smaller = items.to_vec();
+            smaller[i] = simpler;
+            out.push(smaller);
+        }
+    }
+    out
+}
+
+fn minimize(peer: &mut PythonPeer, mut args: Vec<Value>) -> (Vec<Value>, String) {
+    let mut error = peer.check(&args).unwrap_err();
+    'outer: loop {
+        for candidate in shrink_list(&args) {
+            if let Err(err) = peer.check(&candidate) {
+                args = candidate;
+                error = err;
+                continue 'outer;
+            }
+        }
+        return (args, error);
+    }
+}
+
+#[test]
+fn test_differential_python() {
+    let mut peer = match PythonPeer::start() {
+        Some(peer) => peer,
+        None => {
+            eprintln!("python3 with the tunm_proto port not found, skip differential test");
+            return;
+        }
+    };
+    let seed = env::var("TUNM_DIFF_SEED").ok().and_then(|s| s.parse().ok()).unwrap_or(0x7475_6e6d);
+    let cases = env::var("TUNM_DIFF_CASES").ok().and_then(|s| s.parse().ok()).unwrap_or(300);
+    let mut rng = Rng(seed | 1);
+
+    for case in 0..cases {
+        let args: Vec<Value> = (0..rng.below(4)).map(|_| rng.value(3)).collect();
+        if peer.check(&args).is_ok() {
+            continue;
+        }
+        let (minimal, error) = minimize(&mut peer, args.clone());
+        panic!("rust and python disagree (seed {}, case {})\noriginal: {:?}\nminimal: {:?}\n{}",
+               seed, case, args, minimal, error);
+    }
+}
@@ -0,0 +1,73 @@
+'''
+peer process for tests/differential.rs, drive the python port line by line
+
+    decode <hex>   -> {"ok": {"name": ..., "args": [...]}} or {"err": ...}
+    encode <json>  -> {"ok": "<hex>"} or {"err": ...}
+
+values are written as {"nil": null}, {"bool": b}, {"int": n}, {"float": x},
+{"str": s}, {"raw": hex}, {"arr": [...]}, {"map": [[k, v], ...]}
+'''
+import json
+import sys
+
+sys.path.insert(0, sys.argv[1])
+sys.dont_write_bytecode = True
+
+from tunm_proto import ByteBuffer, TPPacker  # noqa: E402
+
+
+def describe(value):
+    if value is None:
+        return {"nil": None}
+    if isinstance(value, bool):
+        return {"bool": value}
+    if isinstance(value, int):
+        return {"int": value}
+    if isinstance(value, float):
+        return {"float": value}
+    if isinstance(value, str):
+        return {"str": value}
+    if isinstance(value, (bytes, bytearray)):
+        return {"raw": bytes(value).hex()}
+    if isinstance(value, list):
+        return {"arr": [describe(v) for v in value]}
+    if isinstance(value, dict):
+        return {"map": [[describe(k), describe(v)] for k, v in value.items()]}
+    raise Exception(f"unknown value {value!r}")
+
+
+def parse(desc):
+    (ty, val), = desc.items()
+    if ty == "raw":
+        return bytes.fromhex(val)
+    if ty == "arr":
+        return [parse(v) for v in val]
+    if ty == "map":
+        return {parse(k): parse(v) for k, v in val}
+    return val
+
+
+def handle(line):
+    cmd, _, arg = line.partition(" ")
+    if cmd == "decode":
+        buffer = ByteBuffer()
+        buffer.write_bytes(bytes.fromhex(arg))
+        name, args = TPPacker.decode_proto(buffer)
+        if buffer.get_bytes_len() != 0:
+            raise Exception("trailing bytes after decode")
+        return {"name": name, "args": describe(args)["arr"]}
+    if cmd == "encode":
+        proto = json.loads(arg)
+        buffer = ByteBuffer()
+        TPPacker.encode_proto(buffer, proto["name"], [parse(v) for v in proto["args"]])
+        return bytes(buffer.all_bytes()).hex()
+    raise Exception(f"unknown command {cmd}")
+
+
+for line in sys.stdin:
+    try:
+        result = {"ok": handle(line.strip())}
+    except Exception as err:
+        result = {"err": f"{type(err).__name__}: {err}"}
+    sys.stdout.write(json.dumps(result) + "\n")
+    sys.stdout.flush()
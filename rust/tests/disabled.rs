@@ -0,0 +1,36 @@
+extern crate tunm_proto as tunm;
+
+use std::io::Write;
+
+use tunm::{Buffer, ErrorKind};
+
+/// decode a body the peer compressed with `flag` and give the error
+#[allow(dead_code)] // only the tests of the features that are off use it
+fn decompress_err(config: tunm::CompressConfig, flag: u8) -> String {
+    let mut buffer = Buffer::new();
+    buffer.set_compress(Some(config));
+    tunm::encode_str_raw(&mut buffer, &tunm::Value::from("cmd_test")).unwrap();
+    buffer.write_all(&[flag]).unwrap();
+    tunm::encode_varint(&mut buffer, &tunm::Value::U64(3)).unwrap();
+    tunm::encode_varint(&mut buffer, &tunm::Value::U64(3)).unwrap();
+    buffer.write_all(&[1, 2, 3]).unwrap();
+    let err = tunm::decode_proto(&mut buffer).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ParseError);
+    err.to_string()
+}
+
+#[test]
+#[cfg(all(feature = "zstd", not(feature = "lz4")))]
+fn test_lz4_not_enabled() {
+    use tunm::{compress::COMPRESS_LZ4, CompressConfig, Compression};
+    let err = decompress_err(CompressConfig::new(Compression::Zstd), COMPRESS_LZ4);
+    assert_eq!(err, "compression not enabled: lz4");
+}
+
+#[test]
+#[cfg(all(feature = "lz4", not(feature = "zstd")))]
+fn test_zstd_not_enabled() {
+    use tunm::{compress::COMPRESS_ZSTD, CompressConfig, Compression};
+    let err = decompress_err(CompressConfig::new(Compression::Lz4), COMPRESS_ZSTD);
+    assert_eq!(err, "compression not enabled: zstd");
+}
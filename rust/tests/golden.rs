@@ -0,0 +1,238 @@
+//! Golden test vectors shared with the other ports.
+//!
+//! The vectors are defined here, encoded with the rust encoder and written to
+//! `tests/golden/vectors.json`. Run with `TUNM_UPDATE_GOLDEN=1` after changing
+//! them, the other ports read the same file to check their encoder and decoder.
+
+extern crate serde_json;
+extern crate tunm_proto as tunm;
+
+mod common;
+
+use common::{describe, from_hex, golden_path, load_golden, parse, to_hex};
+use serde_json::{json, Value as Json};
+use tunm::{Buffer, Value};
+
+use std::env;
+use std::fs;
+
+const GOLDEN_VERSION: u64 = 1;
+
+enum Encode {
+    /// the rust encoder must produce exactly `hex`
+    Exact,
+    /// maps with more than one key, the key order on the wire is not fixed
+    Unordered,
+    /// the rust encoder never emits this form, only the decoder is checked
+    DecodeOnly,
+}
+
+enum Input {
+    Field(Value),
+    Proto(String, Vec<Value>),
+}
+
+struct Vector {
+    name: &'static str,
+    input: Input,
+    encode: Encode,
+    /// only for `Encode::DecodeOnly`, otherwise produced by the encoder
+    hex: Option<&'static str>,
+    expect: Input,
+}
+
+fn describe_input(input: &Input) -> Json {
+    match input {
+        Input::Field(value) => describe(value),
+        Input::Proto(name, args) => json!({ "proto": name, "args": args.iter().map(describe).collect::<Vec<_>>() }),
+    }
+}
+
+fn encode(input: &Input) -> Vec<u8> {
+    let mut buffer = Buffer::new();
+    match input {
+        Input::Field(value) => tunm::encode_field(&mut buffer, value).unwrap(),
+        Input::Proto(name, args) => tunm::encode_proto(&mut buffer, name, args.clone()).unwrap(),
+    }
+    buffer.get_write_data().to_vec()
+}
+
+fn decode(kind: &str, bytes: &[u8]) -> Json {
+    let mut buffer = Buffer::new();
+    std::io::Write::write_all(&mut buffer, bytes).unwrap();
+    let json = if kind == "proto" {
+        let (name, args) = tunm::decode_proto(&mut buffer).unwrap();
+        describe_input(&Input::Proto(name, args))
+    } else {
+        describe(&tunm::decode_field(&mut buffer).unwrap())
+    };
+    assert_eq!(buffer.data_len(), 0, "trailing bytes after decode");
+    json
+}
+
+fn map(pairs: Vec<(Value, Value)>) -> Value {
+    Value::Map(pairs.into_iter().collect())
+}
+
+fn s(val: &str) -> Value {
+    Value::Str(val.to_string())
+}
+
+fn field(name: &'static str, value: Value, expect: Value) -> Vector {
+    Vector { name, input: Input::Field(value), encode: Encode::Exact, hex: None, expect: Input::Field(expect) }
+}
+
+fn decode_only(name: &'static str, hex: &'static str, expect: Value) -> Vector {
+    Vector { name, input: Input::Field(Value::Nil), encode: Encode::DecodeOnly, hex: Some(hex), expect: Input::Field(expect) }
+}
+
+fn proto(name: &'static str, proto: &str, args: Vec<Value>, expect: Vec<Value>) -> Vector {
+    Vector {
+        name,
+        input: Input::Proto(proto.to_string(), args),
+        encode: Encode::Exact,
+        hex: None,
+        expect: Input::Proto(proto.to_string(), expect),
+    }
+}
+
+fn vectors() -> Vec<Vector> {
+    let v = Value::Varint;
+    vec![
+        field("nil", Value::Nil, Value::Nil),
+        field("bool_true", Value::Bool(true), Value::Bool(true)),
+        field("bool_false", Value::Bool(false), Value::Bool(false)),
+        field("u8_zero", Value::U8(0), Value::U8(0)),
+        field("u8_max", Value::U8(255), Value::U8(255)),
+        field("i8_min", Value::I8(-128), Value::I8(-128)),
+        field("i8_max", Value::I8(127), Value::I8(127)),
+        field("u16_as_varint", Value::U16(0x1234), v(0x1234)),
+        field("u16_max", Value::U16(u16::MAX), v(u16::MAX as i64)),
+        field("i16_min", Value::I16(i16::MIN), v(i16::MIN as i64)),
+        field("u32_max", Value::U32(u32::MAX), v(u32::MAX as i64)),
+        field("i32_min", Value::I32(i32::MIN), v(i32::MIN as i64)),
+        field("u64_small", Value::U64(300), v(300)),
+        field("u64_max_wraps", Value::U64(u64::MAX), v(-1)),
+        field("i64_min", Value::I64(i64::MIN), v(i64::MIN)),
+        field("i64_max", Value::I64(i64::MAX), v(i64::MAX)),
+        field("varint_zero", v(0), v(0)),
+        field("varint_neg_one", v(-1), v(-1)),
+        field("varint_63", v(63), v(63)),
+        field("varint_64", v(64), v(64)),
+        field("varint_neg_64", v(-64), v(-64)),
+        field("varint_neg_65", v(-65), v(-65)),
+        field("varint_8191", v(8191), v(8191)),
+        field("varint_8192", v(8192), v(8192)),
+        field("float", Value::Float(1.5), Value::Float(1.5)),
+        field("float_negative", Value::Float(-2.25), Value::Float(-2.25)),
+        field("float_truncated", Value::Float(0.0005), Value::Float(0.0)),
+        field("double", Value::Double(-0.25), Value::Double(-0.25)),
+        field("double_large", Value::Double(123456789.5), Value::Double(123456789.5)),
+        field("raw_empty", Value::Raw(vec![]), Value::Raw(vec![])),
+        field("raw_bytes", Value::Raw(vec![0, 1, 0x7f, 0x80, 0xff]), Value::Raw(vec![0, 1, 0x7f, 0x80, 0xff])),
+        field("raw_len_63", Value::Raw(vec![0xab; 63]), Value::Raw(vec![0xab; 63])),
+        field("raw_len_64", Value::Raw(vec![0xcd; 64]), Value::Raw(vec![0xcd; 64])),
+        field("raw_len_8192", Value::Raw(vec![0x5a; 8192]), Value::Raw(vec![0x5a; 8192])),
+        field("arr_empty", Value::Arr(vec![]), Value::Arr(vec![])),
+        field("arr_nested", Value::Arr(vec![Value::U8(1), Value::Arr(vec![Value::Nil, Value::Bool(true)])]),
+              Value::Arr(vec![Value::U8(1), Value::Arr(vec![Value::Nil, Value::Bool(true)])])),
+        field("arr_len_64", Value::Arr(vec![Value::U8(7); 64]), Value::Arr(vec![Value::U8(7); 64])),
+        field("map_empty", map(vec![]), map(vec![])),
+        field("map_nested", map(vec![(Value::U8(1), map(vec![(Value::U8(2), map(vec![(Value::U8(3), Value::Nil)]))]))]),
+              map(vec![(Value::U8(1), map(vec![(Value::U8(2), map(vec![(Value::U8(3), Value::Nil)]))]))])),
+        decode_only("fixed_u16", "043412", Value::U16(0x1234)),
+        decode_only("fixed_i16", "05feff", Value::I16(-2)),
+        decode_only("fixed_u32", "0678563412", Value::U32(0x12345678)),
+        decode_only("fixed_i32", "07feffffff", Value::I32(-2)),
+        decode_only("fixed_u64", "08efcdab8967452301", Value::U64(0x0123456789abcdef)),
+        decode_only("fixed_i64", "09feffffffffffffff", Value::I64(-2)),
+        decode_only("str_inline", "0d06616263", s("abc")),
+        decode_only("str_inline_empty", "0d00", s("")),
+        decode_only("varint_overlong_zero", "0a8000", v(0)),
+        proto("proto_empty", "cmd_empty", vec![], vec![]),
+        proto("proto_str", "cmd_str", vec![s("tunm"), s("")], vec![s("tunm"), s("")]),
+        proto("proto_str_unicode", "cmd_unicode", vec![s("中文测试"), s("é🎮")], vec![s("中文测试"), s("é🎮")]),
+        proto("proto_str_idx_reuse", "cmd_reuse", vec![s("a"), s("b"), s("a"), Value::Arr(vec![s("b"), s("a")])],
+              vec![s("a"), s("b"), s("a"), Value::Arr(vec![s("b"), s("a")])]),
+        proto("proto_str_len_64", "cmd_long_str", vec![s(&"x".repeat(64))], vec![s(&"x".repeat(64))]),
+        proto("proto_nested_map", "cmd_nested",
+              vec![map(vec![(s("server"), map(vec![(s("code_type"), Value::U16(2))]))])],
+              vec![map(vec![(s("server"), map(vec![(s("code_type"), v(2))]))])]),
+        Vector {
+            name: "proto_map_unordered",
+            input: Input::Proto("cmd_test_op".to_string(),
+                                vec![map(vec![(s("name"), s("tunm_proto")), (s("tunm_proto"), Value::U16(1))])]),
+            encode: Encode::Unordered,
+            hex: None,
+            expect: Input::Proto("cmd_test_op".to_string(),
+                                 vec![map(vec![(s("name"), s("tunm_proto")), (s("tunm_proto"), v(1))])]),
+        },
+    ]
+}
+
+fn build_golden() -> Json {
+    let list: Vec<Json> = vectors().iter().map(|vector| {
+        let kind = match vector.input {
+            Input::Field(_) => "field",
+            Input::Proto(_, _) => "proto",
+        };
+        let (input, encode, hex) = match vector.encode {
+            Encode::Exact => (describe_input(&vector.input), "exact", to_hex(&encode(&vector.input))),
+            Encode::Unordered => (describe_input(&vector.input), "unordered", to_hex(&encode(&vector.input))),
+            Encode::DecodeOnly => (Json::Null, "decode_only", vector.hex.unwrap().to_string()),
+        };
+        json!({
+            "name": vector.name,
+            "kind": kind,
+            "encode": encode,
+            "input": input,
+            "hex": hex,
+            "expect": describe_input(&vector.expect),
+        })
+    }).collect();
+    json!({ "version": GOLDEN_VERSION, "vectors": list })
+}
+
+#[test]
+fn test_golden_file_up_to_date() {
+    let text = serde_json::to_string_pretty(&build_golden()).unwrap() + "\n";
+    if env::var_os("TUNM_UPDATE_GOLDEN").is_some() {
+        fs::write(golden_path(), &text).unwrap();
+        return;
+    }
+    let saved = fs::read_to_string(golden_path()).unwrap_or_default();
+    let saved: Json = serde_json::from_str(&saved).unwrap_or(Json::Null);
+    let built: Json = serde_json::from_str(&text).unwrap();
+    for (old, new) in saved["vectors"].as_array().into_iter().flatten().zip(built["vectors"].as_array().unwrap()) {
+        // the key order of unordered maps may differ between runs
+        if new["encode"] != "unordered" {
+            assert_eq!(old, new, "golden vector {} changed, rerun with TUNM_UPDATE_GOLDEN=1", new["name"]);
+        }
+    }
+    assert_eq!(saved["version"], built["version"]);
+    assert_eq!(saved["vectors"].as_array().map(|v| v.len()), built["vectors"].as_array().map(|v| v.len()));
+}
+
+#[test]
+fn test_golden_vectors() {
+    let golden = load_golden();
+    assert_eq!(golden["version"], GOLDEN_VERSION);
+    for vector in golden["vectors"].as_array().unwrap() {
+        let name = vector["name"].as_str().unwrap();
+        let kind = vector["kind"].as_str().unwrap();
+        let bytes = from_hex(vector["hex"].as_str().unwrap()).unwrap();
+        assert_eq!(decode(kind, &bytes), vector["expect"], "decode {}", name);
+
+        let input = match (kind, &vector["input"]) {
+            (_, Json::Null) => continue,
+            ("proto", input) => Input::Proto(input["proto"].as_str().unwrap().to_string(),
+                                             input["args"].as_array().unwrap().iter().map(|arg| parse(arg).unwrap()).collect()),
+            (_, input) => Input::Field(parse(input).unwrap()),
+        };
+        let encoded = encode(&input);
+        match vector["encode"].as_str().unwrap() {
+            "exact" => assert_eq!(to_hex(&encoded), vector["hex"], "encode {}", name),
+            _ => assert_eq!(decode(kind, &encoded), vector["expect"], "encode {}", name),
+        }
+    }
+}
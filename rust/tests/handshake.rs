@@ -0,0 +1,143 @@
+#[macro_use]
+extern crate tunm_proto as tunm;
+
+use std::io::Write;
+
+use tunm::{Buffer, Cipher, ClientHandshake, ErrorKind, ServerHandshake, SessionKeys, Value};
+
+/// one direction of the in-memory pipe
+fn pipe() -> Buffer {
+    Buffer::new()
+}
+
+fn shake(cipher: Cipher, server: &ServerHandshake) -> (SessionKeys, SessionKeys) {
+    let (mut c2s, mut s2c) = (pipe(), pipe());
+    let mut client = ClientHandshake::new(cipher, server.public_key());
+    client.hello(&mut c2s).unwrap();
+    let server_keys = server.accept(&mut c2s, &mut s2c).unwrap();
+    let client_keys = client.welcome(&mut s2c).unwrap();
+    assert!(c2s.is_empty() && s2c.is_empty());
+    (client_keys, server_keys)
+}
+
+#[test]
+fn test_handshake_then_sealed_protos() {
+    let server = ServerHandshake::generate();
+    for cipher in [Cipher::ChaCha20Poly1305, Cipher::Aes256Gcm] {
+        let (client_keys, server_keys) = shake(cipher, &server);
+        let (mut client_send, mut client_recv) = (Buffer::new(), Buffer::new());
+        let (mut server_send, mut server_recv) = (Buffer::new(), Buffer::new());
+        client_keys.install(&mut client_send, &mut client_recv);
+        server_keys.install(&mut server_send, &mut server_recv);
+
+        for i in 0..3u32 {
+            tunm::encode_proto(&mut client_send, &"cmd_login".to_string(), tunm_args!["user", i]).unwrap();
+            server_recv.write_all(&client_send.drain_all_collect()).unwrap();
+            assert_eq!(tunm::decode_proto(&mut server_recv).unwrap().1, tunm_args!["user", i]);
+
+            tunm::encode_proto(&mut server_send, &"cmd_welcome".to_string(), tunm_args![i]).unwrap();
+            client_recv.write_all(&server_send.drain_all_collect()).unwrap();
+            assert_eq!(tunm::decode_proto(&mut client_recv).unwrap().1, tunm_args![i]);
+        }
+    }
+}
+
+#[test]
+fn test_handshake_welcome_in_pieces() {
+    let server = ServerHandshake::generate();
+    let (mut c2s, mut s2c) = (pipe(), pipe());
+    let mut client = ClientHandshake::new(Cipher::ChaCha20Poly1305, server.public_key());
+    client.hello(&mut c2s).unwrap();
+    let server_keys = server.accept(&mut c2s, &mut s2c).unwrap();
+
+    // the welcome comes one byte at a time, the client waits for all of it
+    let welcome = s2c.drain_all_collect();
+    let mut recv = pipe();
+    for byte in &welcome[..welcome.len() - 1] {
+        recv.write_all(&[*byte]).unwrap();
+        assert_eq!(client.welcome(&mut recv).unwrap_err().kind(), ErrorKind::NoLeftSpaceError);
+    }
+    recv.write_all(&welcome[welcome.len() - 1..]).unwrap();
+    let client_keys = client.welcome(&mut recv).unwrap();
+    assert!(recv.is_empty());
+
+    let (mut send, mut server_recv) = (Buffer::new(), Buffer::new());
+    send.set_seal(Some(client_keys.send));
+    server_recv.set_seal(Some(server_keys.recv));
+    tunm::encode_proto(&mut send, &"cmd_login".to_string(), tunm_args!["user"]).unwrap();
+    server_recv.write_all(&send.drain_all_collect()).unwrap();
+    assert_eq!(tunm::decode_proto(&mut server_recv).unwrap().1, tunm_args!["user"]);
+    assert_eq!(client.welcome(&mut recv).unwrap_err().kind(), ErrorKind::ParseError);
+}
+
+#[test]
+fn test_handshake_keys_differ() {
+    let server = ServerHandshake::new([3; 32]);
+    let (first, _) = shake(Cipher::ChaCha20Poly1305, &server);
+    let (second, _) = shake(Cipher::ChaCha20Poly1305, &server);
+    let mut buffers = [Buffer::new(), Buffer::new()];
+    for (buffer, keys) in buffers.iter_mut().zip([first, second]) {
+        buffer.set_seal(Some(keys.send));
+        tunm::encode_proto(buffer, &"cmd_same".to_string(), vec![]).unwrap();
+    }
+    assert_ne!(buffers[0].get_write_data(), buffers[1].get_write_data());
+}
+
+#[test]
+fn test_handshake_wrong_server() {
+    let server = ServerHandshake::generate();
+    let other = ServerHandshake::generate();
+    let (mut c2s, mut s2c) = (pipe(), pipe());
+    let mut client = ClientHandshake::new(Cipher::ChaCha20Poly1305, other.public_key());
+    client.hello(&mut c2s).unwrap();
+    server.accept(&mut c2s, &mut s2c).unwrap();
+    assert_eq!(client.welcome(&mut s2c).unwrap_err().kind(), ErrorKind::AuthenticationFailed);
+}
+
+#[test]
+fn test_handshake_tampered_hello() {
+    let server = ServerHandshake::generate();
+    let (mut c2s, mut s2c) = (pipe(), pipe());
+    let mut client = ClientHandshake::new(Cipher::ChaCha20Poly1305, server.public_key());
+    client.hello(&mut c2s).unwrap();
+
+    // a man in the middle downgrades the cipher, the transcript no longer matches
+    let (name, mut args) = tunm::decode_proto(&mut c2s).unwrap();
+    args[1] = Value::from("aes-256-gcm");
+    tunm::encode_proto(&mut c2s, &name, args).unwrap();
+    server.accept(&mut c2s, &mut s2c).unwrap();
+    assert_eq!(client.welcome(&mut s2c).unwrap_err().kind(), ErrorKind::AuthenticationFailed);
+}
+
+#[test]
+fn test_handshake_protos() {
+    let server = ServerHandshake::generate();
+    let (mut c2s, mut s2c) = (pipe(), pipe());
+    let mut client = ClientHandshake::new(Cipher::ChaCha20Poly1305, server.public_key());
+    assert_eq!(client.welcome(&mut s2c).unwrap_err().kind(), ErrorKind::ParseError);
+
+    let mut client = ClientHandshake::new(Cipher::ChaCha20Poly1305, server.public_key());
+    client.hello(&mut c2s).unwrap();
+    assert!(client.hello(&mut c2s).is_err());
+    let mut copy = Buffer::new();
+    copy.write_all(c2s.get_write_data()).unwrap();
+    let (name, args) = tunm::decode_proto(&mut copy).unwrap();
+    assert_eq!(name, tunm::handshake::HELLO_PROTO);
+    assert_eq!(args[0], Value::U8(tunm::handshake::HANDSHAKE_VERSION));
+
+    tunm::encode_proto(&mut s2c, &"cmd_other".to_string(), vec![]).unwrap();
+    assert_eq!(server.accept(&mut s2c, &mut c2s).unwrap_err().kind(), ErrorKind::ParseError);
+
+    let mut bad = Buffer::new();
+    tunm::encode_proto(&mut bad, &tunm::handshake::HELLO_PROTO.to_string(), tunm_args![9u8, "chacha20poly1305", [0u8; 32].to_vec()]).unwrap();
+    assert_eq!(server.accept(&mut bad, &mut s2c).unwrap_err().kind(), ErrorKind::ParseError);
+}
+
+#[test]
+fn test_handshake_low_order_key() {
+    let server = ServerHandshake::generate();
+    let mut hello = Buffer::new();
+    tunm::encode_proto(&mut hello, &tunm::handshake::HELLO_PROTO.to_string(),
+                       vec![Value::U8(1), Value::from("chacha20poly1305"), Value::Raw(vec![0; 32])]).unwrap();
+    assert_eq!(server.accept(&mut hello, &mut Buffer::new()).unwrap_err().kind(), ErrorKind::AuthenticationFailed);
+}
@@ -0,0 +1,168 @@
+#[macro_use]
+extern crate tunm_proto as tunm;
+
+use std::collections::VecDeque;
+use std::io::{self, IoSlice, Read, Write};
+use tunm::{Buffer, BufferConfig, Value};
+
+/// gives each chunk from one read, `None` reads as `WouldBlock`
+struct Socket {
+    chunks: VecDeque<Option<Vec<u8>>>,
+}
+
+impl Socket {
+    fn new(chunks: Vec<Option<&[u8]>>) -> Socket {
+        Socket { chunks: chunks.into_iter().map(|c| c.map(|c| c.to_vec())).collect() }
+    }
+}
+
+impl Read for Socket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.chunks.pop_front() {
+            None => Ok(0),
+            Some(None) => Err(io::ErrorKind::WouldBlock.into()),
+            Some(Some(mut chunk)) => {
+                let n = chunk.len().min(buf.len());
+                buf[..n].copy_from_slice(&chunk[..n]);
+                if n < chunk.len() {
+                    self.chunks.push_front(Some(chunk.split_off(n)));
+                }
+                Ok(n)
+            }
+        }
+    }
+}
+
+/// takes at most `step` bytes a write, and blocks once `room` is used up
+struct Sink {
+    data: Vec<u8>,
+    step: usize,
+    room: usize,
+    vectored: usize,
+}
+
+impl Sink {
+    fn new(step: usize, room: usize) -> Sink {
+        Sink { data: vec![], step, room, vectored: 0 }
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(self.step).min(self.room);
+        if n == 0 {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        self.data.extend_from_slice(&buf[..n]);
+        self.room -= n;
+        Ok(n)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        self.vectored += 1;
+        let all: Vec<u8> = bufs.iter().flat_map(|b| b.iter().copied()).collect();
+        self.write(&all)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_read_from() {
+    let mut socket = Socket::new(vec![Some(b"abc"), Some(b"defg"), None, Some(b"hi")]);
+    let mut buffer = Buffer::new();
+    assert_eq!(buffer.read_from(&mut socket, 5).unwrap(), 5);
+    assert_eq!(buffer.get_write_data(), b"abcde");
+    // stops at `WouldBlock` with what it has
+    assert_eq!(buffer.read_from(&mut socket, 100).unwrap(), 2);
+    assert_eq!(buffer.read_from(&mut socket, 100).unwrap(), 2);
+    assert_eq!(buffer.get_write_data(), b"abcdefghi");
+    // the end of the reader
+    assert_eq!(buffer.read_from(&mut socket, 100).unwrap(), 0);
+    assert_eq!(buffer.read_from(&mut socket, 0).unwrap(), 0);
+
+    let mut socket = Socket::new(vec![None]);
+    let err = buffer.read_from(&mut socket, 100).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+    assert_eq!(buffer.data_len(), 9);
+}
+
+#[test]
+fn test_read_from_max_size() {
+    let mut buffer = Buffer::with_config(BufferConfig { initial_capacity: 4, max_size: 6, ..BufferConfig::new() });
+    let mut socket = Socket::new(vec![Some(&[1; 10])]);
+    assert_eq!(buffer.read_from(&mut socket, 100).unwrap(), 6);
+    assert_eq!(buffer.read_from(&mut socket, 100).unwrap(), 0);
+    buffer.read_exact_slice(4).unwrap();
+    assert_eq!(buffer.read_from(&mut socket, 100).unwrap(), 4);
+    assert_eq!(buffer.get_write_data(), &[1; 6]);
+}
+
+#[test]
+fn test_read_from_unbounded() {
+    let data = vec![7; 10000];
+    let mut socket = Socket::new(vec![Some(&data), None]);
+    let mut buffer = Buffer::new();
+    assert_eq!(buffer.read_from(&mut socket, usize::MAX).unwrap(), 10000);
+    assert_eq!(buffer.get_write_data(), &data[..]);
+    // grown as the data came, not for `max`
+    assert!(buffer.len() <= 16384, "grown to {}", buffer.len());
+    assert_eq!(buffer.read_from(&mut socket, usize::MAX).unwrap(), 0);
+}
+
+#[test]
+fn test_write_to_partial() {
+    let mut buffer = Buffer::new();
+    buffer.write_all(&[9; 10]).unwrap();
+    let mut sink = Sink::new(3, 7);
+    assert_eq!(buffer.write_to(&mut sink).unwrap(), 7);
+    assert_eq!(buffer.data_len(), 3);
+
+    let err = buffer.write_to(&mut sink).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+    assert_eq!(buffer.data_len(), 3);
+
+    sink.room = 100;
+    assert_eq!(buffer.write_to(&mut sink).unwrap(), 3);
+    assert!(buffer.is_empty());
+    assert_eq!(buffer.get_wpos(), 0);
+    assert_eq!(sink.data, vec![9; 10]);
+    assert_eq!(buffer.write_to(&mut sink).unwrap(), 0);
+}
+
+#[test]
+fn test_write_vectored_to() {
+    let mut head = Buffer::new();
+    head.write_all(b"head").unwrap();
+    let mut body = Buffer::new();
+    body.write_all(b"the body").unwrap();
+
+    let mut sink = Sink::new(5, 6);
+    assert_eq!(head.write_vectored_to(&mut body, &mut sink).unwrap(), 6);
+    assert!(head.is_empty());
+    assert_eq!(body.get_write_data(), b"e body");
+
+    sink.room = 100;
+    assert_eq!(head.write_vectored_to(&mut body, &mut sink).unwrap(), 6);
+    assert!(body.is_empty());
+    assert_eq!(sink.data, b"headthe body");
+    assert_eq!(sink.vectored, 5);
+}
+
+#[test]
+fn test_proto_through_socket() {
+    let mut buffer = Buffer::new();
+    tunm::encode_proto(&mut buffer, &"cmd_io".to_string(), tunm_args![1u32, "text"]).unwrap();
+    let mut sink = Sink::new(2, usize::MAX);
+    buffer.write_to(&mut sink).unwrap();
+
+    let chunks = sink.data.chunks(3).map(Some).collect();
+    let mut socket = Socket::new(chunks);
+    let mut read = Buffer::new();
+    while read.read_from(&mut socket, 3).unwrap() > 0 {}
+    let (name, args) = tunm::decode_proto(&mut read).unwrap();
+    assert_eq!(name, "cmd_io");
+    assert_eq!(args, vec![Value::U32(1), Value::from("text")]);
+}
@@ -0,0 +1,67 @@
+#[macro_use]
+extern crate tunm_proto as tunm;
+
+use std::collections::HashMap;
+
+use tunm::{Buffer, Value};
+
+/// `Value::eq` lets varint match any integer width, this also checks the variants
+fn assert_same(left: &Value, right: &Value) {
+    assert_eq!(format!("{:#}", left), format!("{:#}", right));
+}
+
+#[test]
+fn test_value_leaf_variants() {
+    assert_same(&value!(nil), &Value::Nil);
+    assert_same(&value!(true), &Value::Bool(true));
+    assert_same(&value!(1u8), &Value::U8(1));
+    assert_same(&value!(-1i8), &Value::I8(-1));
+    assert_same(&value!(1u16), &Value::U16(1));
+    assert_same(&value!(-1i16), &Value::I16(-1));
+    assert_same(&value!(1u32), &Value::U32(1));
+    assert_same(&value!(1), &Value::I32(1));
+    assert_same(&value!(-1), &Value::I32(-1));
+    assert_same(&value!(1u64), &Value::U64(1));
+    assert_same(&value!(1i64), &Value::I64(1));
+    assert_same(&value!(2.5f32), &Value::Float(2.5));
+    assert_same(&value!(2.5), &Value::Double(2.5));
+    assert_same(&value!("tunm"), &Value::Str("tunm".to_string()));
+    assert_same(&value!(b"\x00a"), &Value::Raw(vec![0, b'a']));
+    assert_same(&value!(String::from("owned")), &Value::Str("owned".to_string()));
+    assert_same(&value!(Value::Varint(3)), &Value::Varint(3));
+}
+
+#[test]
+fn test_value_collections() {
+    let id = 7u32;
+    let tags = vec![Value::from("x")];
+    let value = value!({
+        "name": "tunm",
+        "id": id,
+        "sum": id + 1,
+        "tags": ["a", "b", nil, [], {}],
+        "more": tags,
+        "nested": {"deep": [1u16, {"x": -2.5f32}]},
+        1u8: nil,
+        (format!("k{}", 1)): "computed",
+    });
+    let expect = r#"{"id": u32(7), "k1": "computed", "more": ["x"], "name": "tunm", "nested": {"deep": [u16(1), {"x": -2.5f}]}, "sum": u32(8), "tags": ["a", "b", nil, [], {}], u8(1): nil}"#;
+    assert_eq!(value.to_string(), expect);
+    assert_same(&value!([1, -2, "a",]), &Value::Arr(vec![Value::I32(1), Value::I32(-2), Value::from("a")]));
+    assert_same(&value!({}), &Value::Map(HashMap::new()));
+}
+
+#[test]
+fn test_proto_and_args() {
+    let (name, args) = proto!("cmd_test_op", ["tunm_proto", {"name": "tunm_proto", "tunm_proto": 1u16}]);
+    assert_eq!(name, "cmd_test_op");
+    assert_same(&Value::Arr(args.clone()), &value!(["tunm_proto", {"name": "tunm_proto", "tunm_proto": 1u16}]));
+
+    let mut buffer = Buffer::new();
+    tunm::encode_proto(&mut buffer, &name, args).unwrap();
+    let decoded = tunm::decode_proto(&mut buffer).unwrap();
+    assert_eq!(decoded, proto!(name.clone(), ["tunm_proto", {"name": "tunm_proto", "tunm_proto": 1u16}]));
+
+    assert!(tunm_args![].is_empty());
+    assert_same(&Value::Arr(tunm_args![1u8, nil]), &value!([1u8, nil]));
+}
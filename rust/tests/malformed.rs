@@ -0,0 +1,121 @@
+//! Malformed input found by or written for the fuzz targets in `fuzz/`,
+//! every case must return an error instead of panicking or allocating.
+
+extern crate tunm_proto as tunm;
+
+use std::io::Write;
+use tunm::{Buffer, ErrorKind, Value};
+
+fn buffer_of(data: &[u8]) -> Buffer {
+    let mut buffer = Buffer::new();
+    buffer.write_all(data).unwrap();
+    buffer
+}
+
+#[test]
+fn test_varint_endless_continuation() {
+    let err = tunm::decode_varint(&mut buffer_of(&[0x80; 64])).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ParseError);
+    let err = tunm::decode_varint(&mut buffer_of(&[0x80; 5])).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NoLeftSpaceError);
+}
+
+#[test]
+fn test_varint_overflow() {
+    let mut max = vec![0xFF; 9];
+    max.push(0x01);
+    let val: i64 = tunm::decode_varint(&mut buffer_of(&max)).unwrap().into();
+    assert_eq!(val, i64::MIN);
+
+    // bits past the 64th used to be dropped silently
+    let mut over = vec![0xFF; 9];
+    over.push(0x02);
+    assert_eq!(tunm::decode_varint(&mut buffer_of(&over)).unwrap_err().kind(), ErrorKind::ParseError);
+}
+
+#[test]
+fn test_huge_length_prefix() {
+    // raw, str, arr and map claiming 2^62 items with nothing behind them
+    for tag in [15u8, 13, 16, 17] {
+        let mut data = vec![tag];
+        data.extend([0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x40]);
+        let err = tunm::decode_field(&mut buffer_of(&data)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NoLeftSpaceError, "tag {}", tag);
+    }
+    // negative length
+    let err = tunm::decode_field(&mut buffer_of(&[15, 0x01])).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ParseError);
+    // string table count in a proto
+    let err = tunm::decode_proto(&mut buffer_of(&[0x02, b'a', 0xFE, 0xFF, 0xFF, 0x0F])).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NoLeftSpaceError);
+}
+
+#[test]
+fn test_deep_nesting() {
+    let depth = tunm::decode::MAX_DECODE_DEPTH;
+    let nested = |levels: usize| {
+        let mut data = [16, 2].repeat(levels);
+        data.push(0);
+        data
+    };
+    assert!(tunm::decode_field(&mut buffer_of(&nested(depth))).is_ok());
+    let err = tunm::decode_field(&mut buffer_of(&nested(depth + 1))).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ParseError);
+    // deep enough to overflow the stack without the limit
+    let err = tunm::decode_field(&mut buffer_of(&nested(1_000_000))).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ParseError);
+}
+
+#[test]
+fn test_bad_pattern_on_public_decoders() {
+    let mut buffer = buffer_of(&[1, 2, 3, 4]);
+    assert_eq!(tunm::decode_number(&mut buffer, tunm::TYPE_STR).unwrap_err().kind(), ErrorKind::TypeNotMatchError);
+    assert_eq!(tunm::decode_str_raw(&mut buffer, tunm::TYPE_U8).unwrap_err().kind(), ErrorKind::TypeNotMatchError);
+    assert_eq!(tunm::decode_field(&mut buffer_of(&[200])).unwrap_err().kind(), ErrorKind::TypeNotMatchError);
+}
+
+#[test]
+fn test_str_idx_out_of_table() {
+    let err = tunm::decode_field(&mut buffer_of(&[14, 0x06])).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BufferOverMaxError);
+}
+
+#[test]
+fn test_buffer_offsets_stay_in_bounds() {
+    let mut buffer = Buffer::new();
+    assert!(buffer.get_read_array(0).is_empty());
+    buffer.write_offset(usize::MAX);
+    assert_eq!(buffer.get_write_data().len(), buffer.len());
+
+    let mut buffer = buffer_of(&[1, 2, 3]);
+    assert!(buffer.read_offset(usize::MAX));
+    assert_eq!(buffer.data_len(), 0);
+    assert_eq!(tunm::decode_field(&mut buffer).unwrap_err().kind(), ErrorKind::NoLeftSpaceError);
+    assert_eq!(Value::Nil, tunm::decode_field(&mut buffer_of(&[0])).unwrap());
+}
+
+#[test]
+fn test_large_map_decodes_in_linear_time() {
+    // every key used to hash the same, this many took minutes to insert
+    let count = 100_000;
+    let map: std::collections::HashMap<Value, Value> = (0..count)
+        .map(|i| (Value::Str(format!("key_{}", i)), Value::U32(i)))
+        .collect();
+    let mut buffer = Buffer::new();
+    tunm::encode_proto(&mut buffer, &"cmd_map".to_string(), vec![Value::Map(map)]).unwrap();
+    let (_, args) = tunm::decode_proto(&mut buffer).unwrap();
+    match &args[0] {
+        Value::Map(map) => assert_eq!(map.len(), count as usize),
+        other => panic!("not a map: {:?}", other),
+    }
+    assert_eq!(args[0]["key_99999"], Value::U32(99_999));
+
+    // a varint key is found by the same number of any width
+    let mut buffer = Buffer::new();
+    let keys = Value::Map((0..1000u32).map(|i| (Value::U32(i), Value::Nil)).collect());
+    tunm::encode_field(&mut buffer, &keys).unwrap();
+    match tunm::decode_field(&mut buffer).unwrap() {
+        Value::Map(map) => assert!(map.contains_key(&Value::U16(999))),
+        other => panic!("not a map: {:?}", other),
+    }
+}
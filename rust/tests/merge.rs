@@ -0,0 +1,109 @@
+#[macro_use]
+extern crate tunm_proto as tunm;
+
+use tunm::{ArrayMerge, ConflictMerge, ErrorKind, MergeOptions, Value};
+
+fn defaults() -> Value {
+    value!({
+        "server": {"host": "0.0.0.0", "port": 8080u16, "tags": ["a", "b"]},
+        "log": {"level": "info", "file": "run.log"},
+    })
+}
+
+fn merged(mut base: Value, other: Value, options: MergeOptions) -> Value {
+    base.merge(other, &options).unwrap();
+    base
+}
+
+#[test]
+fn test_merge_maps() {
+    let result = merged(defaults(), value!({"server": {"port": 9000u16}, "extra": 1}), MergeOptions::default());
+    assert_eq!(result, value!({
+        "server": {"host": "0.0.0.0", "port": 9000u16, "tags": ["a", "b"]},
+        "log": {"level": "info", "file": "run.log"},
+        "extra": 1,
+    }));
+
+    // without nil_deletes a nil is stored like any other value
+    let result = merged(defaults(), value!({"log": {"file": nil}}), MergeOptions::default());
+    assert_eq!(result.pointer("/log/file"), Some(&Value::Nil));
+    let options = MergeOptions { nil_deletes: true, ..MergeOptions::default() };
+    let result = merged(defaults(), value!({"log": {"file": nil}}), options);
+    assert_eq!(result["log"], value!({"level": "info"}));
+}
+
+#[test]
+fn test_merge_arrays() {
+    let other = value!({"server": {"tags": ["c"]}});
+    let replace = merged(defaults(), other.clone(), MergeOptions::default());
+    assert_eq!(replace.pointer("/server/tags"), Some(&value!(["c"])));
+
+    let options = MergeOptions { arrays: ArrayMerge::Append, ..MergeOptions::default() };
+    let append = merged(defaults(), other.clone(), options);
+    assert_eq!(append.pointer("/server/tags"), Some(&value!(["a", "b", "c"])));
+
+    let options = MergeOptions { arrays: ArrayMerge::ByIndex, ..MergeOptions::default() };
+    let by_index = merged(defaults(), other, options);
+    assert_eq!(by_index.pointer("/server/tags"), Some(&value!(["c", "b"])));
+    let nested = merged(value!([{"a": 1}, {"b": 2}]), value!([{"c": 3}, {"b": 4}, {"d": 5}]), options);
+    assert_eq!(nested, value!([{"a": 1, "c": 3}, {"b": 4}, {"d": 5}]));
+}
+
+#[test]
+fn test_merge_conflicts() {
+    let other = value!({"server": {"port": "eighty", "host": "127.0.0.1"}});
+    let result = merged(defaults(), other.clone(), MergeOptions::default());
+    assert_eq!(result.pointer("/server/port"), Some(&value!("eighty")));
+
+    let options = MergeOptions { conflicts: ConflictMerge::Error, ..MergeOptions::default() };
+    let mut base = defaults();
+    let err = base.merge(other, &options).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TypeNotMatchError);
+    assert!(err.to_string().contains("/server/port"), "{}", err);
+    assert_eq!(base, defaults());
+
+    // integer widths and float/double are not conflicts, filling a nil is not either
+    base.merge(value!({"server": {"port": 1, "ratio": 0.5}}), &options).unwrap();
+    base.merge(value!({"server": {"ratio": 0.25f32}, "pid": nil}), &options).unwrap();
+    base.merge(value!({"pid": 42}), &options).unwrap();
+    assert!(base.merge(value!({"server": []}), &options).is_err());
+    // storing nil over a value is, unless nil deletes
+    assert!(base.merge(value!({"pid": nil}), &options).is_err());
+    let deletes = MergeOptions { nil_deletes: true, ..options };
+    base.merge(value!({"pid": nil}), &deletes).unwrap();
+    assert_eq!(base.get("pid"), None);
+
+    let options = MergeOptions { arrays: ArrayMerge::ByIndex, conflicts: ConflictMerge::Error, nil_deletes: false };
+    let err = merged(value!([1]), value!([1]), options).merge(value!(["x"]), &options).unwrap_err();
+    assert!(err.to_string().contains("/0"), "{}", err);
+}
+
+/// the examples of RFC 7386 appendix A
+#[test]
+fn test_merge_patch_rfc() {
+    let cases = vec![
+        (value!({"a": "b"}), value!({"a": "c"}), value!({"a": "c"})),
+        (value!({"a": "b"}), value!({"b": "c"}), value!({"a": "b", "b": "c"})),
+        (value!({"a": "b"}), value!({"a": nil}), value!({})),
+        (value!({"a": "b", "b": "c"}), value!({"a": nil}), value!({"b": "c"})),
+        (value!({"a": ["b"]}), value!({"a": "c"}), value!({"a": "c"})),
+        (value!({"a": "c"}), value!({"a": ["b"]}), value!({"a": ["b"]})),
+        (value!({"a": {"b": "c"}}), value!({"a": {"b": "d", "c": nil}}), value!({"a": {"b": "d"}})),
+        (value!({"a": [{"b": "c"}]}), value!({"a": [1]}), value!({"a": [1]})),
+        (value!(["a", "b"]), value!(["c", "d"]), value!(["c", "d"])),
+        (value!({"a": "b"}), value!(["c"]), value!(["c"])),
+        (value!({"a": "foo"}), value!(nil), value!(nil)),
+        (value!({"a": "foo"}), value!("bar"), value!("bar")),
+        (value!({"e": nil}), value!({"a": 1}), value!({"e": nil, "a": 1})),
+        (value!([1, 2]), value!({"a": "b", "c": nil}), value!({"a": "b"})),
+        (value!({}), value!({"a": {"bb": {"ccc": nil}}}), value!({"a": {"bb": {}}})),
+    ];
+    for (target, patch, expect) in cases {
+        let mut result = target.clone();
+        result.merge_patch(patch.clone());
+        assert_eq!(result, expect, "{} patched by {}", target, patch);
+        let mut result = target;
+        result.merge(patch, &MergeOptions::merge_patch()).unwrap();
+        assert_eq!(result, expect);
+    }
+}
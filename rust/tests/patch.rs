@@ -0,0 +1,122 @@
+#[macro_use]
+extern crate tunm_proto as tunm;
+
+use tunm::{Buffer, ErrorKind, Patch, PatchOp, TunmDecode, TunmEncode, Value};
+
+fn state() -> Value {
+    value!({
+        "tick": 100,
+        "players": {"alice": {"hp": 30u16, "pos": [1, 2]}, "bob": {"hp": 12u16, "pos": [5, 5]}},
+        "log": ["a", "b", "c", "d"],
+    })
+}
+
+fn assert_apply(old: &Value, new: &Value) -> Patch {
+    let patch = Value::diff(old, new);
+    let mut patched = old.clone();
+    patched.apply(&patch).unwrap();
+    assert_eq!(format!("{:#}", patched), format!("{:#}", new));
+    patch
+}
+
+#[test]
+fn test_diff_same_is_empty() {
+    assert!(Value::diff(&state(), &state()).is_empty());
+    assert!(Value::diff(&Value::Double(f64::NAN), &Value::Double(f64::NAN)).is_empty());
+}
+
+#[test]
+fn test_diff_scalar_and_map() {
+    let old = state();
+    let mut new = state();
+    new["tick"] = value!(101);
+    let patch = assert_apply(&old, &new);
+    assert_eq!(patch.ops, vec![PatchOp::Set { path: vec![value!("tick")], value: value!(101) }]);
+
+    new["players"]["carol"] = value!({"hp": 50u16});
+    new["players"]["bob"]["hp"] = value!(0u16);
+    let mut gone = new.clone();
+    if let Value::Map(map) = gone.get_mut("players").unwrap() {
+        map.remove(&value!("alice"));
+    }
+    let patch = assert_apply(&old, &gone);
+    assert_eq!(patch.ops.len(), 4);
+    assert!(patch.ops.contains(&PatchOp::Remove { path: vec![value!("players"), value!("alice")] }));
+
+    // a change of the variant alone is a change
+    let patch = assert_apply(&value!(1u8), &value!(1));
+    assert_eq!(patch.ops, vec![PatchOp::Set { path: vec![], value: value!(1) }]);
+    assert_apply(&value!({1u8: "x"}), &value!({1: "x"}));
+    assert_apply(&value!([1, 2]), &value!({"a": 1}));
+}
+
+#[test]
+fn test_diff_array_splice() {
+    let old = value!(["a", "b", "c", "d"]);
+    let patch = assert_apply(&old, &value!(["x", "a", "b", "c", "d"]));
+    assert_eq!(patch.ops, vec![PatchOp::Splice { path: vec![], index: 0, remove: 0, insert: vec![value!("x")] }]);
+
+    let patch = assert_apply(&old, &value!(["a", "d"]));
+    assert_eq!(patch.ops, vec![PatchOp::Splice { path: vec![], index: 1, remove: 2, insert: vec![] }]);
+
+    let patch = assert_apply(&old, &value!(["a", "B", "c", "d"]));
+    assert_eq!(patch.ops, vec![PatchOp::Set { path: vec![value!(1)], value: value!("B") }]);
+
+    assert_apply(&old, &value!([]));
+    assert_apply(&value!([]), &old);
+    assert_apply(&value!([[1, 2], [3]]), &value!([[1, 2, 3], [4], [5]]));
+}
+
+#[test]
+fn test_patch_over_the_wire() {
+    let old = state();
+    let mut new = state();
+    new["log"] = value!(["b", "c", "d", "e"]);
+    new["players"]["alice"]["pos"][0] = value!(3);
+    new["players"]["bob"] = Value::Nil;
+    let patch = Value::diff(&old, &new);
+
+    let mut buffer = Buffer::new();
+    tunm::encode_proto(&mut buffer, &"cmd_state_delta".to_string(), vec![patch.to_value()]).unwrap();
+    let (_, args) = tunm::decode_proto(&mut buffer).unwrap();
+    let read = Patch::from_value(args.into_iter().next().unwrap()).unwrap();
+
+    let mut patched = old;
+    patched.apply(&read).unwrap();
+    assert_eq!(patched, new);
+}
+
+#[test]
+fn test_patch_value_errors() {
+    let err = Patch::from_value(value!([["move", []]])).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TypeNotMatchError);
+    let err = Patch::from_value(value!([["set", []]])).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MissingError);
+    let err = Patch::from_value(value!([["remove", [], 1]])).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TypeNotMatchError);
+    let err = Patch::from_value(value!([["splice", [], -1, 0, []]])).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TypeNotMatchError);
+    assert!(Patch::from_value(value!([["splice", ["log"], 0, 1, ["x"]]])).is_ok());
+}
+
+#[test]
+fn test_apply_errors() {
+    let mut value = state();
+    let missing = Patch { ops: vec![PatchOp::Set { path: vec![value!("nope"), value!("x")], value: Value::Nil }] };
+    assert_eq!(value.apply(&missing).unwrap_err().kind(), ErrorKind::MissingError);
+    let scalar = Patch { ops: vec![PatchOp::Remove { path: vec![value!("tick"), value!("x")] }] };
+    assert_eq!(value.apply(&scalar).unwrap_err().kind(), ErrorKind::TypeNotMatchError);
+    let range = Patch { ops: vec![PatchOp::Splice { path: vec![value!("log")], index: 3, remove: 2, insert: vec![] }] };
+    assert_eq!(value.apply(&range).unwrap_err().kind(), ErrorKind::MissingError);
+    let root = Patch { ops: vec![PatchOp::Remove { path: vec![] }] };
+    assert_eq!(value.apply(&root).unwrap_err().kind(), ErrorKind::MissingError);
+    assert_eq!(value, state());
+}
+
+#[test]
+fn test_diff_nan_key() {
+    let old = Value::Map([(Value::Float(f32::NAN), value!(1))].into_iter().collect());
+    let new = Value::Map([(Value::Float(f32::NAN), value!(2))].into_iter().collect());
+    assert_apply(&old, &new);
+    assert_apply(&old, &value!({}));
+}
@@ -0,0 +1,108 @@
+#[macro_use]
+extern crate tunm_proto as tunm;
+
+use std::collections::HashMap;
+
+use tunm::{ErrorKind, Value};
+
+fn config() -> Value {
+    value!({
+        "server": {"code_type": "tunm", "ports": [8080u16, 8081u16]},
+        "a/b": {"~x": 1},
+        "levels": {1u8: "one", 2i64: "two"},
+    })
+}
+
+#[test]
+fn test_pointer() {
+    let conf = config();
+    assert_eq!(conf.pointer(""), Some(&conf));
+    assert_eq!(conf.pointer("/server/code_type"), Some(&Value::from("tunm")));
+    assert_eq!(conf.pointer("/server/ports/1"), Some(&Value::U16(8081)));
+    assert_eq!(conf.pointer("/a~1b/~0x"), Some(&Value::Varint(1)));
+    assert_eq!(conf.pointer("/levels/1"), Some(&Value::from("one")));
+    assert_eq!(conf.pointer("/levels/2"), Some(&Value::from("two")));
+
+    assert_eq!(conf.pointer("server"), None);
+    assert_eq!(conf.pointer("/server/missing"), None);
+    assert_eq!(conf.pointer("/server/ports/2"), None);
+    assert_eq!(conf.pointer("/server/ports/01"), None);
+    assert_eq!(conf.pointer("/server/ports/-1"), None);
+    assert_eq!(conf.pointer("/server/code_type/x"), None);
+}
+
+#[test]
+fn test_pointer_mut() {
+    let mut conf = config();
+    *conf.pointer_mut("/server/ports/0").unwrap() = Value::U16(9000);
+    assert_eq!(conf.pointer("/server/ports/0"), Some(&Value::U16(9000)));
+    assert!(conf.pointer_mut("/server/nope").is_none());
+}
+
+#[test]
+fn test_get_and_index() {
+    let mut conf = config();
+    assert_eq!(conf.get("server").and_then(|s| s.get("code_type")), Some(&Value::from("tunm")));
+    assert_eq!(conf["server"]["ports"].get_index(0), Some(&Value::U16(8080)));
+    assert_eq!(conf.get_index(0), None);
+    assert_eq!(conf["server"].get("ports").and_then(|p| p.get("x")), None);
+
+    assert_eq!(conf["server"]["ports"][1], Value::U16(8081));
+    assert_eq!(conf["nope"]["deeper"][3], Value::Nil);
+    assert_eq!(Value::U8(1)["key"], Value::Nil);
+
+    conf["server"]["ports"][1] = Value::U16(1);
+    conf["server"]["name"] = value!("main");
+    conf["fresh"]["key"] = value!(true);
+    assert_eq!(conf.pointer("/server/ports/1"), Some(&Value::U16(1)));
+    assert_eq!(conf.pointer("/server/name"), Some(&Value::from("main")));
+    assert_eq!(conf.pointer("/fresh/key"), Some(&Value::Bool(true)));
+
+    *conf.get_mut("server").and_then(|s| s.get_mut("ports")).and_then(|p| p.get_index_mut(0)).unwrap() = Value::Nil;
+    assert_eq!(conf["server"]["ports"][0], Value::Nil);
+    assert!(conf.get_index_mut(0).is_none());
+}
+
+#[test]
+#[should_panic]
+fn test_index_mut_out_of_range() {
+    let mut conf = config();
+    conf["server"]["ports"][5] = Value::Nil;
+}
+
+#[test]
+fn test_set_path() {
+    let mut conf = Value::Nil;
+    assert_eq!(conf.set_path("/server/code_type", value!("tunm")).unwrap(), None);
+    assert_eq!(conf.set_path("/server/ports", value!([8080u16])).unwrap(), None);
+    assert_eq!(conf.set_path("/server/ports/-", value!(8081u16)).unwrap(), None);
+    assert_eq!(conf.set_path("/server/ports/2", value!(8082u16)).unwrap(), None);
+    assert_eq!(conf.set_path("/server/ports/0", value!(80u16)).unwrap(), Some(Value::U16(8080)));
+    assert_eq!(conf.set_path("/server/code_type", value!("x")).unwrap(), Some(Value::from("tunm")));
+    assert_eq!(conf, value!({"server": {"code_type": "x", "ports": [80u16, 8081u16, 8082u16]}}));
+
+    let mut levels = config();
+    levels.set_path("/levels/1", value!("uno")).unwrap();
+    assert_eq!(levels["levels"], Value::Map(HashMap::from([
+        (Value::U8(1), Value::from("uno")),
+        (Value::I64(2), Value::from("two")),
+    ])));
+
+    assert_eq!(conf.set_path("", Value::U8(1)).unwrap().map(|old| old.pointer("/server/code_type").cloned()),
+               Some(Some(Value::from("x"))));
+    assert_eq!(conf, Value::U8(1));
+}
+
+#[test]
+fn test_set_path_errors() {
+    let mut conf = config();
+    let err = conf.set_path("server", Value::Nil).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ParseError);
+    let err = conf.set_path("/server/code_type/x", Value::Nil).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TypeNotMatchError);
+    let err = conf.set_path("/server/ports/5", Value::Nil).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MissingError);
+    let err = conf.set_path("/server/ports/5/x", Value::Nil).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MissingError);
+    assert_eq!(conf, config());
+}
@@ -0,0 +1,103 @@
+#[macro_use]
+extern crate tunm_proto as tunm;
+
+use std::io::Write;
+use std::thread;
+
+use tunm::{Buffer, BufferPool, Value};
+
+fn args(i: u32) -> Vec<Value> {
+    tunm_args![{"name": "server", "seq": i}, ["a", "b", "a"], i]
+}
+
+#[test]
+fn test_pool_reuses_cleared_buffers() {
+    let pool = BufferPool::new(4);
+    {
+        let mut buffer = pool.get();
+        buffer.set_str_session(true);
+        tunm::encode_proto(&mut buffer, &"cmd_test".to_string(), args(1)).unwrap();
+        buffer.set_canonical(true);
+        #[cfg(feature = "crc32c")]
+        buffer.set_checksum(Some(tunm::Checksum::Crc32c));
+        assert!(!buffer.str_arr.is_empty());
+    }
+    assert_eq!(pool.idle_len(), 1);
+
+    let buffer = pool.get();
+    assert_eq!(pool.idle_len(), 0);
+    assert!(buffer.is_empty());
+    assert!(buffer.str_arr.is_empty() && buffer.str_map.is_empty());
+    assert!(!buffer.is_str_session());
+    assert!(!buffer.is_canonical());
+    assert_eq!(buffer.get_checksum(), None);
+    // the allocation is kept
+    assert!(buffer.len() >= 2048);
+}
+
+#[test]
+fn test_pool_caps() {
+    let pool = BufferPool::with_limits(2, 4096);
+    let buffers: Vec<_> = (0..5).map(|_| pool.get()).collect();
+    drop(buffers);
+    assert_eq!(pool.idle_len(), 2);
+
+    let mut big = pool.get();
+    big.write_all(&[0; 8192]).unwrap();
+    drop(big);
+    assert_eq!(pool.idle_len(), 1);
+
+    // a buffer taken out never goes back
+    let buffer = pool.get().into_inner();
+    assert_eq!(pool.idle_len(), 0);
+    drop(buffer);
+    assert_eq!(pool.idle_len(), 0);
+}
+
+#[test]
+fn test_encode_with_pool_same_bytes() {
+    let pool = BufferPool::new(8);
+    #[allow(unused_mut)]
+    let mut settings: Vec<fn(&mut Buffer)> = vec![
+        |_| {},
+        |buffer| buffer.set_str_session(true),
+    ];
+    #[cfg(feature = "xxhash")]
+    settings.push(|buffer| buffer.set_checksum(Some(tunm::Checksum::XxHash64)));
+    #[cfg(feature = "lz4")]
+    settings.push(|buffer| {
+        buffer.set_compress(Some(tunm::CompressConfig { threshold: 0, ..tunm::CompressConfig::new(tunm::Compression::Lz4) }))
+    });
+    for setting in settings {
+        let (mut plain, mut pooled) = (Buffer::new(), Buffer::new());
+        for buffer in [&mut plain, &mut pooled] {
+            buffer.set_canonical(true);
+            setting(buffer);
+        }
+        for i in 0..3 {
+            tunm::encode_proto(&mut plain, &"cmd_test".to_string(), args(i)).unwrap();
+            tunm::encode_proto_with_pool(&mut pooled, "cmd_test", args(i), &pool).unwrap();
+        }
+        assert_eq!(plain.get_write_data(), pooled.get_write_data());
+        assert!(pool.idle_len() > 0);
+    }
+}
+
+#[test]
+fn test_pool_between_threads() {
+    let pool = BufferPool::new(4);
+    let handles: Vec<_> = (0..8).map(|i| {
+        let pool = pool.clone();
+        thread::spawn(move || {
+            for j in 0..100 {
+                let mut buffer = pool.get();
+                tunm::encode_proto_with_pool(&mut buffer, "cmd_test", args(i * 100 + j), &pool).unwrap();
+                assert_eq!(tunm::decode_proto(&mut buffer).unwrap().1, args(i * 100 + j));
+            }
+        })
+    }).collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert!(pool.idle_len() <= pool.max_buffers());
+}
@@ -0,0 +1,143 @@
+extern crate arbitrary;
+extern crate proptest;
+extern crate tunm_proto as tunm;
+
+use arbitrary::{Arbitrary, Unstructured};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use tunm::strategy::value_strategy;
+use tunm::{normalize, Buffer, Patch, TunmDecode, TunmEncode, Value};
+
+fn field_round_trip(value: &Value) -> Value {
+    let mut buffer = Buffer::new();
+    tunm::encode_field(&mut buffer, value).unwrap();
+    let read = tunm::decode_field(&mut buffer).unwrap();
+    assert_eq!(buffer.data_len(), 0);
+    read
+}
+
+fn value_map(items: Vec<(Value, Value)>) -> Value {
+    Value::Map(items.into_iter().collect())
+}
+
+/// `Value::eq` lets varint match any integer width, this also checks the variants
+fn same(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Arr(l), Value::Arr(r)) => l.len() == r.len() && l.iter().zip(r).all(|(l, r)| same(l, r)),
+        (Value::Map(l), Value::Map(r)) => {
+            l.len() == r.len() && l.iter().all(|(k, v)| {
+                r.get_key_value(k).is_some_and(|(rk, rv)| same(k, rk) && same(v, rv))
+            })
+        }
+        _ => std::mem::discriminant(left) == std::mem::discriminant(right) && left == right,
+    }
+}
+
+fn assert_same(left: &Value, right: &Value) {
+    assert!(same(left, right), "{:?} != {:?}", left, right);
+}
+
+proptest! {
+    #[test]
+    fn prop_field_round_trip(value in value_strategy()) {
+        let mut buffer = Buffer::new();
+        tunm::encode_field(&mut buffer, &value).unwrap();
+        // strings go through the string table, fill the decoder's table the same way
+        let mut read_buffer = Buffer::new();
+        std::io::Write::write_all(&mut read_buffer, buffer.get_write_data()).unwrap();
+        for s in &buffer.str_arr {
+            read_buffer.add_str(s.clone());
+        }
+        let read = tunm::decode_field(&mut read_buffer).unwrap();
+        assert_same(&read, &normalize(&value));
+        prop_assert_eq!(read_buffer.data_len(), 0);
+    }
+
+    #[test]
+    fn prop_proto_round_trip(name in any::<String>(), args in vec(value_strategy(), 0..6)) {
+        let mut buffer = Buffer::new();
+        tunm::encode_proto(&mut buffer, &name, args.clone()).unwrap();
+        let (read_name, read_args) = tunm::decode_proto(&mut buffer).unwrap();
+        prop_assert_eq!(read_name, name);
+        prop_assert_eq!(read_args.len(), args.len());
+        for (read, arg) in read_args.iter().zip(&args) {
+            assert_same(read, &normalize(arg));
+        }
+        prop_assert_eq!(buffer.data_len(), 0);
+    }
+
+    #[test]
+    fn prop_text_round_trip(value in value_strategy()) {
+        // nan never equals itself, compare the text instead of the values
+        let text = value.to_string();
+        prop_assert_eq!(tunm::parse_value(&text).unwrap().to_string(), text);
+        let pretty = format!("{:#}", value);
+        prop_assert_eq!(format!("{:#}", tunm::parse_value(&pretty).unwrap()), pretty);
+    }
+
+    #[test]
+    fn prop_diff_apply(old in value_strategy(), new in value_strategy()) {
+        let patch = Value::diff(&old, &new);
+        let mut patched = old.clone();
+        patched.apply(&patch).unwrap();
+        prop_assert_eq!(patched.to_string(), new.to_string());
+        prop_assert!(Value::diff(&new, &new).is_empty());
+
+        // the patch as a value gives back the same patch
+        let read = Patch::from_value(patch.to_value()).unwrap();
+        let mut patched = old;
+        patched.apply(&read).unwrap();
+        prop_assert_eq!(patched.to_string(), new.to_string());
+    }
+
+    #[test]
+    fn prop_diff_apply_nested(old in value_strategy(), new in value_strategy(), key in value_strategy()) {
+        // share most of the tree so the diff goes deep instead of replacing the root
+        let wrap = |inner: &Value| value_map(vec![(key.clone(), inner.clone()), (Value::from("same"), old.clone())]);
+        let (old, new) = (wrap(&old), wrap(&new));
+        let mut patched = old.clone();
+        patched.apply(&Value::diff(&old, &new)).unwrap();
+        prop_assert_eq!(patched.to_string(), new.to_string());
+    }
+
+    #[test]
+    fn prop_canonical_reencode(args in vec(value_strategy(), 0..6)) {
+        let encode = |args: Vec<Value>| {
+            let mut buffer = Buffer::new();
+            buffer.set_canonical(true);
+            tunm::encode_proto(&mut buffer, &"cmd_canonical".to_string(), args).unwrap();
+            buffer
+        };
+        // the first trip may merge map keys the wire can not tell apart,
+        // after it a decoded proto encodes to the bytes it came from
+        let (_, read) = tunm::decode_proto(&mut encode(args)).unwrap();
+        let mut second = encode(read);
+        let data = second.get_write_data().to_vec();
+        let (_, read) = tunm::decode_proto(&mut second).unwrap();
+        let third = encode(read);
+        prop_assert_eq!(third.get_write_data(), &data[..]);
+    }
+
+    #[test]
+    fn prop_arbitrary_round_trip(data in vec(any::<u8>(), 0..512)) {
+        let mut u = Unstructured::new(&data);
+        if let Ok(value) = Value::arbitrary(&mut u) {
+            let mut buffer = Buffer::new();
+            tunm::encode_proto(&mut buffer, &"cmd_arbitrary".to_string(), vec![value.clone()]).unwrap();
+            let (_, read) = tunm::decode_proto(&mut buffer).unwrap();
+            assert_same(&read[0], &normalize(&value));
+        }
+    }
+}
+
+#[test]
+fn test_long_length_round_trip() {
+    let long = "x".repeat(70_000);
+    assert_same(&field_round_trip(&Value::Raw(vec![1; 70_000])), &Value::Raw(vec![1; 70_000]));
+    assert_same(&field_round_trip(&Value::Arr(vec![Value::Nil; 70_000])), &Value::Arr(vec![Value::Nil; 70_000]));
+
+    let mut buffer = Buffer::new();
+    tunm::encode_proto(&mut buffer, &"cmd_long".to_string(), vec![Value::Str(long.clone())]).unwrap();
+    let (_, read) = tunm::decode_proto(&mut buffer).unwrap();
+    assert_same(&read[0], &Value::Str(long));
+}
@@ -0,0 +1,130 @@
+#[macro_use]
+extern crate tunm_proto as tunm;
+
+use std::io::{Read, Write};
+use tunm::{Buffer, BufferConfig, ErrorKind, Value};
+
+fn buffer_of(data: &[u8]) -> Buffer {
+    let mut buffer = Buffer::new();
+    buffer.write_all(data).unwrap();
+    buffer
+}
+
+#[test]
+fn test_peek() {
+    let mut buffer = buffer_of(&[1, 2, 3]);
+    assert_eq!(buffer.peek(0).unwrap(), &[] as &[u8]);
+    assert_eq!(buffer.peek(3).unwrap(), &[1, 2, 3]);
+    assert_eq!(buffer.peek(4).unwrap_err().kind(), ErrorKind::NoLeftSpaceError);
+    assert_eq!(buffer.peek_u8().unwrap(), 1);
+    assert_eq!(buffer.remaining(), 3);
+
+    buffer.read_exact(&mut [0; 3]).unwrap();
+    assert_eq!(buffer.peek(0).unwrap(), &[] as &[u8]);
+    assert_eq!(buffer.peek_u8().unwrap_err().kind(), ErrorKind::NoLeftSpaceError);
+    assert_eq!(Buffer::new().peek_u8().unwrap_err().kind(), ErrorKind::NoLeftSpaceError);
+}
+
+#[test]
+fn test_read_exact_slice() {
+    let mut buffer = buffer_of(&[1, 2, 3, 4]);
+    assert_eq!(buffer.read_exact_slice(0).unwrap(), &[] as &[u8]);
+    assert_eq!(buffer.read_exact_slice(3).unwrap(), &[1, 2, 3]);
+    // too few left reads nothing
+    assert_eq!(buffer.read_exact_slice(2).unwrap_err().kind(), ErrorKind::NoLeftSpaceError);
+    assert_eq!(buffer.remaining(), 1);
+    assert_eq!(buffer.read_exact_slice(1).unwrap(), &[4]);
+    assert_eq!(buffer.remaining(), 0);
+}
+
+#[test]
+fn test_unread() {
+    let mut buffer = buffer_of(&[1, 2, 3]);
+    assert_eq!(buffer.unread(1).unwrap_err().kind(), ErrorKind::NoLeftSpaceError);
+    buffer.read_exact_slice(2).unwrap();
+    buffer.unread(0).unwrap();
+    buffer.unread(2).unwrap();
+    assert_eq!(buffer.peek(3).unwrap(), &[1, 2, 3]);
+    assert!(buffer.unread(1).is_err());
+    assert_eq!(buffer.remaining(), 3);
+}
+
+#[test]
+fn test_spare_capacity_commit() {
+    let mut buffer = Buffer::with_capacity(4);
+    let spare = buffer.spare_capacity_mut(10).unwrap();
+    assert!(spare.len() >= 10);
+    spare[..3].copy_from_slice(&[7, 8, 9]);
+    buffer.commit(3).unwrap();
+    assert_eq!(buffer.get_write_data(), &[7, 8, 9]);
+
+    let room = buffer.len() - buffer.get_wpos();
+    assert_eq!(buffer.commit(room + 1).unwrap_err().kind(), ErrorKind::NoLeftSpaceError);
+    buffer.commit(0).unwrap();
+    assert_eq!(buffer.remaining(), 3);
+
+    let mut buffer = Buffer::with_config(BufferConfig { initial_capacity: 8, max_size: 16, ..BufferConfig::new() });
+    buffer.write_all(&[0; 10]).unwrap();
+    assert_eq!(buffer.spare_capacity_mut(7).unwrap_err().kind(), ErrorKind::BufferOverMaxError);
+    assert!(buffer.spare_capacity_mut(6).unwrap().len() >= 6);
+}
+
+#[test]
+fn test_get_read_array_len() {
+    let mut buffer = Buffer::with_capacity(8);
+    assert_eq!(buffer.get_read_array(1).len(), 1);
+    assert_eq!(buffer.get_read_array(100).len(), 100);
+    buffer.get_read_array(2).copy_from_slice(&[5, 6]);
+    buffer.write_offset(2);
+    assert_eq!(buffer.get_write_data(), &[5, 6]);
+}
+
+#[test]
+fn test_read_then_write_reuses_room() {
+    let mut buffer = Buffer::with_capacity(64);
+    for i in 0..100u8 {
+        buffer.write_all(&[i; 40]).unwrap();
+        let mut out = [0; 40];
+        buffer.read_exact(&mut out).unwrap();
+        assert_eq!(out, [i; 40]);
+    }
+    // the read bytes are moved out of the way instead of growing
+    assert_eq!(buffer.len(), 64);
+
+    let mut out = [0; 8];
+    assert_eq!(buffer.read(&mut out).unwrap(), 0);
+    assert_eq!(buffer.read(&mut []).unwrap(), 0);
+}
+
+#[test]
+fn test_read_compacts() {
+    let mut buffer = Buffer::with_capacity(64);
+    buffer.write_all(&[0; 20]).unwrap();
+    for i in 0..100u8 {
+        buffer.write_all(&[i; 20]).unwrap();
+        let mut out = [0; 20];
+        buffer.read_exact(&mut out).unwrap();
+        // the data starts before the middle after every read
+        assert!(buffer.get_rpos() <= buffer.len() / 2, "rpos {} of {}", buffer.get_rpos(), buffer.len());
+        assert_eq!(buffer.data_len(), 20);
+    }
+    assert_eq!(buffer.len(), 64);
+    assert_eq!(buffer.get_write_data(), &[99; 20]);
+
+    // reading all of it starts over at the front
+    let mut out = [0; 32];
+    assert_eq!(buffer.read(&mut out).unwrap(), 20);
+    assert_eq!((buffer.get_rpos(), buffer.get_wpos()), (0, 0));
+}
+
+#[test]
+fn test_encode_after_partial_read() {
+    let mut buffer = Buffer::with_capacity(16);
+    buffer.write_all(&[0xff; 12]).unwrap();
+    buffer.read_exact_slice(10).unwrap();
+    tunm::encode_proto(&mut buffer, &"cmd_read".to_string(), tunm_args![1u32, "text"]).unwrap();
+    assert_eq!(buffer.read_exact_slice(2).unwrap(), &[0xff, 0xff]);
+    let (name, args) = tunm::decode_proto(&mut buffer).unwrap();
+    assert_eq!(name, "cmd_read");
+    assert_eq!(args, vec![Value::U32(1), Value::from("text")]);
+}
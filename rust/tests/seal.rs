@@ -0,0 +1,133 @@
+#[macro_use]
+extern crate tunm_proto as tunm;
+
+use std::io::Write;
+
+use tunm::{Buffer, Cipher, ErrorKind, SealKey, Value};
+
+const KEY: [u8; 32] = [7; 32];
+
+fn args(i: usize) -> Vec<Value> {
+    tunm_args![{"name": "server", "seq": i as u32}, [1, 2, 3]]
+}
+
+fn sealed(cipher: Cipher) -> Buffer {
+    let mut buffer = Buffer::new();
+    buffer.set_seal(Some(SealKey::new(cipher, KEY)));
+    buffer
+}
+
+/// `count` frames sealed one after another
+fn frames(cipher: Cipher, count: usize) -> Vec<Vec<u8>> {
+    let mut buffer = sealed(cipher);
+    (0..count).map(|i| {
+        tunm::encode_proto(&mut buffer, &"cmd_state".to_string(), args(i)).unwrap();
+        buffer.drain_all_collect()
+    }).collect()
+}
+
+fn kind(buffer: &mut Buffer) -> ErrorKind {
+    tunm::decode_proto(buffer).unwrap_err().kind()
+}
+
+#[test]
+fn test_seal_round_trip() {
+    for cipher in [Cipher::ChaCha20Poly1305, Cipher::Aes256Gcm] {
+        let mut reader = sealed(cipher);
+        for (i, frame) in frames(cipher, 3).iter().enumerate() {
+            assert!(!frame.windows(6).any(|w| w == b"server"));
+            reader.write_all(frame).unwrap();
+            assert_eq!(tunm::decode_proto(&mut reader).unwrap(), ("cmd_state".to_string(), args(i)));
+        }
+        assert_eq!(reader.get_seal().unwrap().nonce(), 3);
+    }
+}
+
+#[test]
+fn test_seal_tampered() {
+    for cipher in [Cipher::ChaCha20Poly1305, Cipher::Aes256Gcm] {
+        let frame = frames(cipher, 1).remove(0);
+        for at in 0..frame.len() {
+            let mut bad = frame.clone();
+            bad[at] ^= 0x01;
+            let mut reader = sealed(cipher);
+            reader.write_all(&bad).unwrap();
+            let kind = kind(&mut reader);
+            // a broken length makes the frame look cut
+            assert!(kind == ErrorKind::AuthenticationFailed || at == 0 && kind == ErrorKind::NoLeftSpaceError,
+                    "byte {} gave {:?}", at, kind);
+        }
+    }
+}
+
+#[test]
+fn test_seal_replay_and_reorder() {
+    let cipher = Cipher::ChaCha20Poly1305;
+    let frames = frames(cipher, 3);
+
+    let mut reader = sealed(cipher);
+    reader.write_all(&frames[0]).unwrap();
+    reader.write_all(&frames[0]).unwrap();
+    assert!(tunm::decode_proto(&mut reader).is_ok());
+    assert_eq!(kind(&mut reader), ErrorKind::AuthenticationFailed);
+
+    let mut reader = sealed(cipher);
+    reader.write_all(&frames[1]).unwrap();
+    reader.write_all(&frames[0]).unwrap();
+    reader.write_all(&frames[2]).unwrap();
+    assert_eq!(kind(&mut reader), ErrorKind::AuthenticationFailed);
+    // the rejected frame did not move the counter
+    assert_eq!(tunm::decode_proto(&mut reader).unwrap().1, args(0));
+    assert_eq!(kind(&mut reader), ErrorKind::AuthenticationFailed);
+    assert_eq!(reader.get_seal().unwrap().nonce(), 1);
+}
+
+#[test]
+fn test_seal_wrong_key() {
+    let frame = frames(Cipher::ChaCha20Poly1305, 1).remove(0);
+    let mut reader = Buffer::new();
+    reader.set_seal(Some(SealKey::new(Cipher::ChaCha20Poly1305, [8; 32])));
+    reader.write_all(&frame).unwrap();
+    assert_eq!(kind(&mut reader), ErrorKind::AuthenticationFailed);
+
+    let mut reader = sealed(Cipher::Aes256Gcm);
+    reader.write_all(&frame).unwrap();
+    assert_eq!(kind(&mut reader), ErrorKind::AuthenticationFailed);
+
+    let mut plain = Buffer::new();
+    plain.write_all(&frame).unwrap();
+    assert!(tunm::decode_proto(&mut plain).is_err());
+}
+
+#[test]
+fn test_seal_partial_frame_waits() {
+    let cipher = Cipher::Aes256Gcm;
+    let frame = frames(cipher, 1).remove(0);
+    let mut reader = sealed(cipher);
+    for (i, byte) in frame.iter().enumerate() {
+        if i > 0 {
+            assert_eq!(kind(&mut reader), ErrorKind::NoLeftSpaceError);
+            assert_eq!(reader.data_len(), i);
+        }
+        reader.write_all(&[*byte]).unwrap();
+    }
+    assert_eq!(tunm::decode_proto(&mut reader).unwrap().1, args(0));
+}
+
+#[test]
+#[cfg(feature = "zstd")]
+fn test_seal_with_compress_and_session() {
+    use tunm::{CompressConfig, Compression};
+    let make = || {
+        let mut buffer = sealed(Cipher::ChaCha20Poly1305);
+        buffer.set_compress(Some(CompressConfig { threshold: 0, ..CompressConfig::new(Compression::Zstd) }));
+        buffer.set_str_session(true);
+        buffer
+    };
+    let (mut writer, mut reader) = (make(), make());
+    for i in 0..3 {
+        tunm::encode_proto(&mut writer, &"cmd_state".to_string(), args(i)).unwrap();
+        reader.write_all(&writer.drain_all_collect()).unwrap();
+        assert_eq!(tunm::decode_proto(&mut reader).unwrap().1, args(i));
+    }
+}
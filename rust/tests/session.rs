@@ -0,0 +1,162 @@
+#[macro_use]
+extern crate tunm_proto as tunm;
+
+use std::io::Write;
+
+use tunm::{Buffer, ErrorKind, Value};
+
+fn session_buffer() -> Buffer {
+    let mut buffer = Buffer::new();
+    buffer.set_str_session(true);
+    buffer
+}
+
+fn args(code_type: &str) -> Vec<Value> {
+    tunm_args![{"name": "server", "code_type": code_type}, "server"]
+}
+
+/// encode one message and move its bytes to the peer's read buffer
+fn send(writer: &mut Buffer, reader: &mut Buffer, proto: &str, args: Vec<Value>) -> usize {
+    tunm::encode_proto(writer, &proto.to_string(), args).unwrap();
+    let data = writer.drain_all_collect();
+    reader.write_all(&data).unwrap();
+    data.len()
+}
+
+#[test]
+fn test_session_sends_strings_once() {
+    let (mut writer, mut reader) = (session_buffer(), session_buffer());
+    let first = send(&mut writer, &mut reader, "cmd_state", args("tunm"));
+    assert_eq!(tunm::decode_proto(&mut reader).unwrap(), ("cmd_state".to_string(), args("tunm")));
+    let second = send(&mut writer, &mut reader, "cmd_state", args("tunm"));
+    assert_eq!(tunm::decode_proto(&mut reader).unwrap(), ("cmd_state".to_string(), args("tunm")));
+    assert!(second + "name".len() + "server".len() + "code_type".len() + "tunm".len() <= first);
+
+    // only the new string goes out
+    let third = send(&mut writer, &mut reader, "cmd_state", args("json"));
+    assert_eq!(third, second + "json".len() + 1);
+    assert_eq!(tunm::decode_proto(&mut reader).unwrap().1, args("json"));
+    assert_eq!(writer.str_arr, reader.str_arr);
+}
+
+#[test]
+fn test_plain_messages_have_their_own_table() {
+    let mut buffer = Buffer::new();
+    tunm::encode_proto(&mut buffer, &"cmd_a".to_string(), tunm_args!["a", "b"]).unwrap();
+    tunm::encode_proto(&mut buffer, &"cmd_b".to_string(), tunm_args!["b", "c"]).unwrap();
+    assert_eq!(tunm::decode_proto(&mut buffer).unwrap().1, tunm_args!["a", "b"]);
+    assert_eq!(tunm::decode_proto(&mut buffer).unwrap().1, tunm_args!["b", "c"]);
+}
+
+#[test]
+fn test_session_reset() {
+    let (mut writer, mut reader) = (session_buffer(), session_buffer());
+    send(&mut writer, &mut reader, "cmd_state", args("tunm"));
+    tunm::decode_proto(&mut reader).unwrap();
+
+    writer.clear_str();
+    send(&mut writer, &mut reader, "cmd_other", tunm_args!["x"]);
+    assert_eq!(tunm::decode_proto(&mut reader).unwrap().1, tunm_args!["x"]);
+    assert_eq!(reader.str_arr, vec!["x".to_string()]);
+}
+
+#[test]
+fn test_session_out_of_sync() {
+    let (mut writer, mut reader) = (session_buffer(), session_buffer());
+    send(&mut writer, &mut reader, "cmd_state", args("tunm"));
+    // the reader lost the first message
+    reader.clear();
+    send(&mut writer, &mut reader, "cmd_state", args("tunm"));
+    let err = tunm::decode_proto(&mut reader).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::StringTableError);
+
+    // resync: the writer starts over
+    reader.clear();
+    writer.clear_str();
+    send(&mut writer, &mut reader, "cmd_state", args("json"));
+    assert_eq!(tunm::decode_proto(&mut reader).unwrap().1, args("json"));
+}
+
+#[test]
+fn test_session_bad_tables() {
+    let message = |base: u64, strs: &[&str], idx: u64| {
+        let mut buffer = session_buffer();
+        tunm::encode_str_raw(&mut buffer, &value!("cmd")).unwrap();
+        tunm::encode_varint(&mut buffer, &Value::U64(base)).unwrap();
+        tunm::encode_varint(&mut buffer, &Value::U64(strs.len() as u64)).unwrap();
+        for s in strs {
+            tunm::encode_str_raw(&mut buffer, &value!(*s)).unwrap();
+        }
+        tunm::encode_sure_type(&mut buffer, tunm::TYPE_ARR).unwrap();
+        tunm::encode_varint(&mut buffer, &Value::U64(1)).unwrap();
+        tunm::encode_sure_type(&mut buffer, tunm::TYPE_STR_IDX).unwrap();
+        tunm::encode_varint(&mut buffer, &Value::U64(idx)).unwrap();
+        buffer
+    };
+    assert_eq!(tunm::decode_proto(&mut message(0, &["a"], 0)).unwrap().1, tunm_args!["a"]);
+    let unknown = tunm::decode_proto(&mut message(0, &["a"], 1)).unwrap_err();
+    assert_eq!(unknown.kind(), ErrorKind::StringTableError);
+    let twice = tunm::decode_proto(&mut message(0, &["a", "a"], 0)).unwrap_err();
+    assert_eq!(twice.kind(), ErrorKind::StringTableError);
+    let base = tunm::decode_proto(&mut message(3, &[], 0)).unwrap_err();
+    assert_eq!(base.kind(), ErrorKind::StringTableError);
+}
+
+#[test]
+fn test_session_failed_encode_forgets_strings() {
+    let config = tunm::BufferConfig { initial_capacity: 16, max_size: 64, ..tunm::BufferConfig::new() };
+    let mut writer = Buffer::with_config(config);
+    writer.set_str_session(true);
+    let mut reader = session_buffer();
+    send(&mut writer, &mut reader, "cmd_state", args("tunm"));
+    assert_eq!(tunm::decode_proto(&mut reader).unwrap().1, args("tunm"));
+
+    let strs = writer.str_arr.clone();
+    let err = tunm::encode_proto(&mut writer, &"cmd_state".to_string(), args(&"x".repeat(100))).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BufferOverMaxError);
+    assert!(writer.is_empty());
+    assert_eq!(writer.str_arr, strs);
+
+    // the peer never saw the long string, the next message still decodes
+    send(&mut writer, &mut reader, "cmd_state", args("json"));
+    assert_eq!(tunm::decode_proto(&mut reader).unwrap().1, args("json"));
+    assert_eq!(writer.str_arr, reader.str_arr);
+}
+
+#[test]
+fn test_session_partial_proto_waits() {
+    let (mut writer, mut reader) = (session_buffer(), session_buffer());
+    send(&mut writer, &mut reader, "cmd_state", args("tunm"));
+    tunm::decode_proto(&mut reader).unwrap();
+    let strs = reader.str_arr.clone();
+
+    tunm::encode_proto(&mut writer, &"cmd_state".to_string(), args("json")).unwrap();
+    let data = writer.drain_all_collect();
+    for (i, byte) in data.iter().enumerate() {
+        if i > 0 {
+            assert_eq!(tunm::decode_proto(&mut reader).unwrap_err().kind(), ErrorKind::NoLeftSpaceError);
+            assert_eq!(reader.data_len(), i);
+            assert_eq!(reader.str_arr, strs);
+        }
+        reader.write_all(&[*byte]).unwrap();
+    }
+    assert_eq!(tunm::decode_proto(&mut reader).unwrap().1, args("json"));
+    send(&mut writer, &mut reader, "cmd_other", args("tunm"));
+    assert_eq!(tunm::decode_proto(&mut reader).unwrap().1, args("tunm"));
+}
+
+#[test]
+fn test_strings_past_the_table_go_inline() {
+    let strs: Vec<Value> = (0..70000).map(|i| Value::from(format!("s{}", i))).collect();
+    let mut buffer = Buffer::new();
+    tunm::encode_proto(&mut buffer, &"cmd_many".to_string(), vec![Value::Arr(strs.clone())]).unwrap();
+    assert_eq!(tunm::decode_proto(&mut buffer).unwrap().1, vec![Value::Arr(strs.clone())]);
+
+    let (mut writer, mut reader) = (session_buffer(), session_buffer());
+    send(&mut writer, &mut reader, "cmd_many", vec![Value::Arr(strs.clone())]);
+    assert_eq!(tunm::decode_proto(&mut reader).unwrap().1, vec![Value::Arr(strs)]);
+    // the full table starts over with the next message
+    send(&mut writer, &mut reader, "cmd_state", args("tunm"));
+    assert_eq!(tunm::decode_proto(&mut reader).unwrap().1, args("tunm"));
+    assert_eq!(writer.str_arr, reader.str_arr);
+}
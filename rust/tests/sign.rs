@@ -0,0 +1,122 @@
+#[macro_use]
+extern crate tunm_proto as tunm;
+
+use std::io::Write;
+use std::sync::Arc;
+
+use tunm::{Buffer, ErrorKind, SignKeys, Value};
+
+fn args() -> Vec<Value> {
+    tunm_args![{"svr_type": "logic", "svr_id": 3u16, "cost": 0.003f32, "ratio": 2.2,
+                "players": {"a": 1, "b": 2, "c": [1u32, 2u64, {"x": 1, "y": 2.5f32}]}}]
+}
+
+fn signed(keys: SignKeys) -> Buffer {
+    let mut buffer = Buffer::new();
+    buffer.set_sign_keys(Some(Arc::new(keys)));
+    buffer
+}
+
+fn frame(keys: SignKeys, args: Vec<Value>) -> Vec<u8> {
+    let mut buffer = signed(keys);
+    tunm::encode_proto(&mut buffer, &"cmd_route".to_string(), args).unwrap();
+    buffer.drain_all_collect()
+}
+
+fn decode(keys: SignKeys, data: &[u8]) -> tunm::RpResult<(String, Vec<Value>)> {
+    let mut buffer = signed(keys);
+    buffer.write_all(data).unwrap();
+    tunm::decode_proto(&mut buffer)
+}
+
+#[test]
+fn test_sign_round_trip() {
+    let data = frame(SignKeys::new(1, "secret"), args());
+    let (name, decoded) = decode(SignKeys::new(1, "secret"), &data).unwrap();
+    assert_eq!(name, "cmd_route");
+    assert_eq!(decoded, args());
+    // the proto is readable, only the tag is added
+    assert!(data.windows(5).any(|w| w == b"logic"));
+}
+
+#[test]
+fn test_sign_reencode_same_bytes() {
+    let keys = SignKeys::new(1, "secret");
+    let data = frame(keys.clone(), args());
+    for _ in 0..8 {
+        assert_eq!(frame(keys.clone(), args()), data);
+    }
+    // a router that decodes and encodes again sends the very same frame
+    let (_, decoded) = decode(keys.clone(), &data).unwrap();
+    assert_eq!(frame(keys.clone(), decoded.clone()), data);
+    let (_, again) = decode(keys.clone(), &frame(keys, decoded.clone())).unwrap();
+    assert_eq!(again, decoded);
+}
+
+#[test]
+fn test_sign_tampered() {
+    let keys = SignKeys::new(1, "secret");
+    let data = frame(keys.clone(), args());
+    for at in 2..data.len() {
+        let mut bad = data.clone();
+        bad[at] ^= 0x01;
+        assert_eq!(decode(keys.clone(), &bad).unwrap_err().kind(), ErrorKind::AuthenticationFailed, "byte {}", at);
+    }
+    assert_eq!(decode(SignKeys::new(1, "other"), &data).unwrap_err().kind(), ErrorKind::AuthenticationFailed);
+}
+
+#[test]
+fn test_sign_key_rotation() {
+    let old = frame(SignKeys::new(1, "old"), args());
+
+    let mut keys = SignKeys::new(1, "old");
+    keys.add(2, "new");
+    keys.set_current(2).unwrap();
+    let new = frame(keys.clone(), args());
+    assert_ne!(old, new);
+    assert!(decode(keys.clone(), &old).is_ok());
+    assert!(decode(keys.clone(), &new).is_ok());
+
+    assert!(keys.remove(2).is_err());
+    keys.remove(1).unwrap();
+    assert!(!keys.contains(1));
+    let err = decode(keys.clone(), &old).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AuthenticationFailed);
+    assert_eq!(err.to_string(), "unknown key id: 1");
+    assert_eq!(keys.set_current(1).unwrap_err().kind(), ErrorKind::MissingError);
+    // an unsigned peer can not read the frame
+    let mut plain = Buffer::new();
+    plain.write_all(&new).unwrap();
+    assert!(tunm::decode_proto(&mut plain).is_err());
+}
+
+#[test]
+fn test_sign_partial_frame_waits() {
+    let keys = SignKeys::new(7, "secret");
+    let data = frame(keys.clone(), args());
+    let mut buffer = signed(keys);
+    for (i, byte) in data.iter().enumerate() {
+        if i > 0 {
+            assert_eq!(tunm::decode_proto(&mut buffer).unwrap_err().kind(), ErrorKind::NoLeftSpaceError);
+            assert_eq!(buffer.data_len(), i);
+        }
+        buffer.write_all(&[*byte]).unwrap();
+    }
+    assert_eq!(tunm::decode_proto(&mut buffer).unwrap().1, args());
+}
+
+#[test]
+fn test_canonical_without_keys() {
+    let encode = |canonical: bool| {
+        let mut buffer = Buffer::new();
+        buffer.set_canonical(canonical);
+        tunm::encode_proto(&mut buffer, &"cmd_route".to_string(), args()).unwrap();
+        buffer
+    };
+    let mut first = encode(true);
+    assert_eq!(encode(true).get_write_data(), first.get_write_data());
+    let (_, decoded) = tunm::decode_proto(&mut first).unwrap();
+    // rounding keeps the float that truncating loses
+    assert_eq!(decoded[0]["cost"], Value::Float(0.003));
+    assert!(!encode(false).is_canonical());
+}
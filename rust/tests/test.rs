@@ -4,6 +4,7 @@ use tunm::{Value, Buffer};
 use std::{collections::{HashMap}, io::{Write, Read}};
 
 #[test]
+#[allow(clippy::unnecessary_cast)]
 fn test_encode_u8() {
     let mut buffer = Buffer::new();
     let value = Value::U8(12 as u8);
@@ -20,6 +21,7 @@ fn test_encode_u8() {
 }
 
 #[test]
+#[allow(clippy::unnecessary_cast)]
 fn test_encode_16() {
     let mut buffer = Buffer::new();
     
@@ -46,6 +48,7 @@ fn test_encode_16() {
 
 
 #[test]
+#[allow(clippy::unnecessary_cast)]
 fn test_encode_u32() {
     let mut buffer = Buffer::new();
     tunm::encode_field(&mut buffer, &Value::I32(-90 as i32)).unwrap();
@@ -112,6 +115,7 @@ fn test_encode_str() {
 }
 
 #[test]
+#[allow(clippy::unnecessary_cast)]
 fn test_encode_map() {
     let mut hash_value = HashMap::<Value, Value>::new();
     hash_value.insert(Value::Str("name".to_string()), Value::Str("tickbh".to_string()));
@@ -151,6 +155,7 @@ fn test_encode_array_u8() {
 
 
 #[test]
+#[allow(clippy::unnecessary_cast, clippy::match_single_binding)]
 fn test_base_proto() {
     let mut hash_value = HashMap::<Value, Value>::new();
     hash_value.insert(Value::Str("name".to_string()), Value::Str("I'm a chinese people".to_string()));
@@ -175,6 +180,7 @@ fn test_base_proto() {
 
 
 #[test]
+#[allow(clippy::unused_io_amount)]
 fn test_buffer_size() {
     let mut buffer = Buffer::new();
     assert!(buffer.len() == 2048);
@@ -0,0 +1,120 @@
+extern crate tunm_proto as tunm;
+
+use std::collections::HashMap;
+
+use tunm::{parse_value, ErrorKind, Value};
+
+fn map(pairs: Vec<(Value, Value)>) -> Value {
+    Value::Map(pairs.into_iter().collect::<HashMap<_, _>>())
+}
+
+fn s(val: &str) -> Value {
+    Value::Str(val.to_string())
+}
+
+#[test]
+fn test_print_every_variant() {
+    let cases = vec![
+        (Value::Nil, "nil"),
+        (Value::Bool(true), "true"),
+        (Value::U8(255), "u8(255)"),
+        (Value::I8(-128), "i8(-128)"),
+        (Value::U16(5), "u16(5)"),
+        (Value::I16(-5), "i16(-5)"),
+        (Value::U32(7), "u32(7)"),
+        (Value::I32(-7), "i32(-7)"),
+        (Value::U64(u64::MAX), "u64(18446744073709551615)"),
+        (Value::I64(i64::MIN), "i64(-9223372036854775808)"),
+        (Value::Varint(-3), "-3"),
+        (Value::Float(2.5), "2.5f"),
+        (Value::Float(1e-7), "1e-7f"),
+        (Value::Float(f32::INFINITY), "float(inf)"),
+        (Value::Double(2.0), "2.0"),
+        (Value::Double(f64::NAN), "double(nan)"),
+        (Value::Double(f64::NEG_INFINITY), "double(-inf)"),
+        (s("a\"b\\\n\u{1}中"), r#""a\"b\\\n\u{1}中""#),
+        (Value::Raw(vec![0, b'a', b'"', 0xFF]), r#"b"\x00a\"\xff""#),
+        (Value::Arr(vec![]), "[]"),
+        (map(vec![]), "{}"),
+        (Value::Arr(vec![Value::Varint(1), Value::Float(2.5)]), "[1, 2.5f]"),
+        (map(vec![(s("k"), Value::Arr(vec![Value::Varint(1), Value::Float(2.5)])), (Value::U8(1), Value::Nil)]),
+         r#"{"k": [1, 2.5f], u8(1): nil}"#),
+    ];
+    for (value, text) in cases {
+        assert_eq!(value.to_string(), text);
+        assert_eq!(parse_value(text).unwrap().to_string(), text, "parse {}", text);
+    }
+}
+
+#[test]
+fn test_pretty_print() {
+    let value = map(vec![
+        (s("list"), Value::Arr(vec![Value::Varint(1), Value::Arr(vec![]), map(vec![(s("x"), Value::Nil)])])),
+        (s("name"), s("tunm")),
+    ]);
+    let text = format!("{:#}", value);
+    assert_eq!(text, r#"{
+    "list": [
+        1,
+        [],
+        {
+            "x": nil
+        }
+    ],
+    "name": "tunm"
+}"#);
+    assert_eq!(parse_value(&text).unwrap(), value);
+}
+
+#[test]
+fn test_parse_syntax() {
+    let text = r#"
+        # a config like file
+        {
+            "port": u16( 8080 ),   # typed
+            "ratio": +1.5e3,
+            "scale": float(2),
+            "bytes": b"ab\x01\n",
+            "escape": "\u{4e2d}\t",
+            "list": [1, -2, varint(3),],
+        }
+    "#;
+    let value = parse_value(text).unwrap();
+    let expect = map(vec![
+        (s("port"), Value::U16(8080)),
+        (s("ratio"), Value::Double(1500.0)),
+        (s("scale"), Value::Float(2.0)),
+        (s("bytes"), Value::Raw(vec![b'a', b'b', 1, b'\n'])),
+        (s("escape"), s("中\t")),
+        (s("list"), Value::Arr(vec![Value::Varint(1), Value::Varint(-2), Value::Varint(3)])),
+    ]);
+    assert_eq!(value, expect);
+    assert_eq!("u16(5)".parse::<Value>().unwrap().to_string(), "u16(5)");
+}
+
+fn parse_error(text: &str) -> String {
+    let err = parse_value(text).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ParseError);
+    err.extension_error_detail().unwrap().to_string()
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!(parse_error(""), "line 1, column 1: expect a value, found end of text");
+    assert_eq!(parse_error("[1, 2"), "line 1, column 6: expect ',', found end of text");
+    assert_eq!(parse_error("{\n  \"a\": 1\n  \"b\": 2}"), "line 3, column 3: expect ',', found '\"'");
+    assert_eq!(parse_error("[u8(300)]"), "line 1, column 5: bad u8 \"300\"");
+    assert_eq!(parse_error("[1] 2"), "line 1, column 5: unexpected text after the value");
+    assert_eq!(parse_error("\"abc"), "line 1, column 1: unterminated string");
+    assert_eq!(parse_error("b\"中\""), "line 1, column 3: raw strings take ascii only, use \\xNN");
+    assert_eq!(parse_error("{\"a\": 2, \"a\": 3}"), "line 1, column 10: duplicate key \"a\"");
+    assert_eq!(parse_error("[hello]"), "line 1, column 2: unknown value \"hello\"");
+    assert_eq!(parse_error("u128(1)"), "line 1, column 6: unknown type \"u128\"");
+    assert_eq!(parse_error(&"[".repeat(100_000)), "line 1, column 65: nested too deep");
+}
+
+#[test]
+fn test_debug_labels() {
+    assert_eq!(format!("{:?}", Value::Raw(vec![1])), "raw([1])");
+    assert_eq!(format!("{:?}", map(vec![(s("a"), Value::Nil)])), "map({str(\"a\"): nil})");
+}
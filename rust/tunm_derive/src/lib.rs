@@ -0,0 +1,321 @@
+//! `#[derive(TunmEncode, TunmDecode)]` for `tunm_proto`, use it through the
+//! `derive` feature of `tunm_proto`.
+//!
+//! - structs with named fields are maps keyed by the field name, tuple structs
+//!   and structs with `#[tunm(array)]` are arrays in field order
+//! - enums are maps with one entry, the variant name to its fields: nil for
+//!   unit variants, the value for newtype variants, an array for tuple variants
+//!   and a map (or an array with `#[tunm(array)]`) for struct variants
+//! - `#[tunm(rename = "name")]` on fields and variants changes the key,
+//!   `#[tunm(skip)]` leaves a field out and decodes it as `Default::default()`,
+//!   `#[tunm(default)]` uses `Default::default()` when the field is missing
+//! - an array with more items than the fields fails to decode
+//! - `#[tunm(crate = "path")]` on the type gives the path of `tunm_proto` when
+//!   the dependency is renamed, `::tunm_proto` by default
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Fields, GenericParam, Generics, Ident, LitStr, Member, Path};
+
+#[derive(Default)]
+struct Attrs {
+    array: bool,
+    rename: Option<String>,
+    skip: bool,
+    default: bool,
+    krate: Option<Path>,
+}
+
+fn parse_attrs(attrs: &[Attribute]) -> syn::Result<Attrs> {
+    let mut out = Attrs::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("tunm")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("array") {
+                out.array = true;
+            } else if meta.path.is_ident("skip") {
+                out.skip = true;
+            } else if meta.path.is_ident("default") {
+                out.default = true;
+            } else if meta.path.is_ident("rename") {
+                out.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("crate") {
+                out.krate = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+            } else {
+                return Err(meta.error("unknown tunm attribute, expect array, rename, skip, default or crate"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(out)
+}
+
+/// the attributes of a field or a variant, `crate` only goes on the type
+fn parse_inner_attrs(attrs: &[Attribute]) -> syn::Result<Attrs> {
+    let out = parse_attrs(attrs)?;
+    if let Some(krate) = &out.krate {
+        return Err(syn::Error::new_spanned(krate, "`crate` goes on the struct or the enum"));
+    }
+    Ok(out)
+}
+
+/// the path of `tunm_proto` in the generated code
+fn crate_path(attrs: &Attrs) -> TokenStream2 {
+    match &attrs.krate {
+        Some(path) => quote!(#path),
+        None => quote!(::tunm_proto),
+    }
+}
+
+struct Field {
+    member: Member,
+    /// the map key, the field name or its `rename`
+    name: String,
+    skip: bool,
+    default: bool,
+}
+
+fn parse_fields(fields: &Fields) -> syn::Result<Vec<Field>> {
+    fields.iter().enumerate().map(|(idx, field)| {
+        let attrs = parse_inner_attrs(&field.attrs)?;
+        if attrs.array {
+            return Err(syn::Error::new_spanned(field, "`array` goes on the struct or the variant"));
+        }
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(idx.into()),
+        };
+        let name = attrs.rename.unwrap_or_else(|| match &member {
+            Member::Named(ident) => ident.to_string(),
+            Member::Unnamed(index) => index.index.to_string(),
+        });
+        Ok(Field { member, name, skip: attrs.skip, default: attrs.default })
+    }).collect()
+}
+
+/// the local name a field is bound to when matching an enum variant
+fn binding(field: &Field) -> Ident {
+    match &field.member {
+        Member::Named(ident) => format_ident!("__field_{}", ident),
+        Member::Unnamed(index) => format_ident!("__field_{}", index.index),
+    }
+}
+
+fn add_bounds(generics: &Generics, bound: TokenStream2) -> Generics {
+    let mut generics = generics.clone();
+    for param in &mut generics.params {
+        if let GenericParam::Type(ty) = param {
+            ty.bounds.push(syn::parse2(bound.clone()).unwrap());
+        }
+    }
+    generics
+}
+
+/// the value of `fields`, `access` gives a reference to each of them
+fn encode_fields(krate: &TokenStream2, fields: &[Field], access: &[TokenStream2], array: bool) -> TokenStream2 {
+    let kept: Vec<_> = fields.iter().zip(access).filter(|(field, _)| !field.skip).collect();
+    let values = kept.iter().map(|(_, access)| quote!(#krate::TunmEncode::to_value(#access)));
+    if array {
+        return quote!(#krate::Value::Arr(::std::vec![#(#values),*]));
+    }
+    let names = kept.iter().map(|(field, _)| &field.name);
+    quote!({
+        let mut map = ::std::collections::HashMap::new();
+        #(map.insert(#krate::Value::Str(::std::string::String::from(#names)), #values);)*
+        #krate::Value::Map(map)
+    })
+}
+
+/// build `path { fields }` out of the value `value`
+fn decode_fields(krate: &TokenStream2, ty: &str, path: TokenStream2, fields: &[Field], array: bool,
+                 value: TokenStream2) -> TokenStream2 {
+    let members = fields.iter().map(|field| &field.member);
+    let exprs = fields.iter().map(|field| {
+        if field.skip {
+            return quote!(::std::default::Default::default());
+        }
+        let name = &field.name;
+        let missing = if field.default {
+            quote!(::std::default::Default::default())
+        } else {
+            quote!(#krate::TunmDecode::from_value(#krate::Value::Nil)
+                   .map_err(|_| #krate::convert::missing_field(#ty, #name))?)
+        };
+        let lookup = if array {
+            quote!(__items.next())
+        } else {
+            quote!(__items.remove(&#krate::Value::Str(::std::string::String::from(#name))))
+        };
+        quote!(match #lookup {
+            ::std::option::Option::Some(value) => #krate::TunmDecode::from_value(value)?,
+            ::std::option::Option::None => #missing,
+        })
+    });
+    let items = if array {
+        quote!(match #value {
+            #krate::Value::Arr(arr) => arr.into_iter(),
+            other => return ::std::result::Result::Err(#krate::convert::type_mismatch("arr", &other)),
+        })
+    } else {
+        quote!(match #value {
+            #krate::Value::Map(map) => map,
+            other => return ::std::result::Result::Err(#krate::convert::type_mismatch("map", &other)),
+        })
+    };
+    if !array {
+        return quote!({
+            #[allow(unused_mut, unused_variables)]
+            let mut __items = #items;
+            #path { #(#members: #exprs),* }
+        });
+    }
+    let expect = fields.iter().filter(|field| !field.skip).count();
+    quote!({
+        #[allow(unused_mut)]
+        let mut __items = #items;
+        let __value = #path { #(#members: #exprs),* };
+        let extra = __items.len();
+        if extra > 0 {
+            return ::std::result::Result::Err(#krate::convert::extra_items(#ty, #expect, #expect + extra));
+        }
+        __value
+    })
+}
+
+fn expand_encode(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    let attrs = parse_attrs(&input.attrs)?;
+    let krate = &crate_path(&attrs);
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let fields = parse_fields(&data.fields)?;
+            let access: Vec<_> = fields.iter().map(|field| {
+                let member = &field.member;
+                quote!(&self.#member)
+            }).collect();
+            match data.fields {
+                Fields::Unit => quote!(#krate::Value::Nil),
+                Fields::Unnamed(_) => encode_fields(krate, &fields, &access, true),
+                Fields::Named(_) => encode_fields(krate, &fields, &access, attrs.array),
+            }
+        }
+        Data::Enum(data) => {
+            let arms = data.variants.iter().map(|variant| {
+                let attrs = parse_inner_attrs(&variant.attrs)?;
+                let var = &variant.ident;
+                let name = attrs.rename.unwrap_or_else(|| var.to_string());
+                let fields = parse_fields(&variant.fields)?;
+                let bindings: Vec<_> = fields.iter().map(binding).collect();
+                let access: Vec<_> = bindings.iter().map(|binding| quote!(#binding)).collect();
+                let (pattern, payload) = match variant.fields {
+                    Fields::Unit => (quote!(Self::#var), quote!(#krate::Value::Nil)),
+                    Fields::Unnamed(_) if fields.len() == 1 && !fields[0].skip => {
+                        (quote!(Self::#var(#(#bindings),*)), quote!(#krate::TunmEncode::to_value(#(#bindings)*)))
+                    }
+                    Fields::Unnamed(_) => (quote!(Self::#var(#(#bindings),*)), encode_fields(krate, &fields, &access, true)),
+                    Fields::Named(_) => {
+                        let members = fields.iter().map(|field| &field.member);
+                        (quote!(Self::#var { #(#members: #bindings),* }), encode_fields(krate, &fields, &access, attrs.array))
+                    }
+                };
+                Ok(quote!(#pattern => {
+                    let mut map = ::std::collections::HashMap::new();
+                    map.insert(#krate::Value::Str(::std::string::String::from(#name)), #payload);
+                    #krate::Value::Map(map)
+                }))
+            }).collect::<syn::Result<Vec<_>>>()?;
+            quote!(match self {
+                #(#arms)*
+            })
+        }
+        Data::Union(_) => return Err(syn::Error::new(Span::call_site(), "TunmEncode does not support unions")),
+    };
+    let generics = add_bounds(&input.generics, quote!(#krate::TunmEncode));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #krate::TunmEncode for #ident #ty_generics #where_clause {
+            fn to_value(&self) -> #krate::Value {
+                #body
+            }
+        }
+    })
+}
+
+fn expand_decode(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    let ty = ident.to_string();
+    let attrs = parse_attrs(&input.attrs)?;
+    let krate = &crate_path(&attrs);
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let fields = parse_fields(&data.fields)?;
+            let built = match data.fields {
+                Fields::Unit => quote!({
+                    let _ = value;
+                    Self
+                }),
+                Fields::Unnamed(_) => decode_fields(krate, &ty, quote!(Self), &fields, true, quote!(value)),
+                Fields::Named(_) => decode_fields(krate, &ty, quote!(Self), &fields, attrs.array, quote!(value)),
+            };
+            quote!(::std::result::Result::Ok(#built))
+        }
+        Data::Enum(data) => {
+            let arms = data.variants.iter().map(|variant| {
+                let attrs = parse_inner_attrs(&variant.attrs)?;
+                let var = &variant.ident;
+                let name = attrs.rename.unwrap_or_else(|| var.to_string());
+                let fields = parse_fields(&variant.fields)?;
+                let path = format!("{}::{}", ty, var);
+                let built = match variant.fields {
+                    Fields::Unit => quote!(Self::#var),
+                    Fields::Unnamed(_) if fields.len() == 1 && !fields[0].skip => {
+                        quote!(Self::#var(#krate::TunmDecode::from_value(payload)?))
+                    }
+                    Fields::Unnamed(_) => decode_fields(krate, &path, quote!(Self::#var), &fields, true, quote!(payload)),
+                    Fields::Named(_) => decode_fields(krate, &path, quote!(Self::#var), &fields, attrs.array, quote!(payload)),
+                };
+                Ok(quote!(#name => ::std::result::Result::Ok(#built),))
+            }).collect::<syn::Result<Vec<_>>>()?;
+            quote!({
+                let (tag, payload) = match value {
+                    #krate::Value::Map(map) if map.len() == 1 => map.into_iter().next().unwrap(),
+                    other => return ::std::result::Result::Err(#krate::convert::type_mismatch("map", &other)),
+                };
+                let tag = match tag {
+                    #krate::Value::Str(tag) => tag,
+                    other => return ::std::result::Result::Err(#krate::convert::unknown_variant(#ty, &other)),
+                };
+                #[allow(unused_variables)]
+                match tag.as_str() {
+                    #(#arms)*
+                    _ => ::std::result::Result::Err(
+                        #krate::convert::unknown_variant(#ty, &#krate::Value::Str(tag))),
+                }
+            })
+        }
+        Data::Union(_) => return Err(syn::Error::new(Span::call_site(), "TunmDecode does not support unions")),
+    };
+    let generics = add_bounds(&input.generics, quote!(#krate::TunmDecode));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #krate::TunmDecode for #ident #ty_generics #where_clause {
+            fn from_value(value: #krate::Value) -> #krate::RpResult<Self> {
+                #body
+            }
+        }
+    })
+}
+
+#[proc_macro_derive(TunmEncode, attributes(tunm))]
+pub fn derive_encode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_encode(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+#[proc_macro_derive(TunmDecode, attributes(tunm))]
+pub fn derive_decode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_decode(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}